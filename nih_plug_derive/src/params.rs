@@ -32,12 +32,11 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
     let mut param_mapping_self_tokens = Vec::new();
     let mut field_serialize_tokens = Vec::new();
     let mut field_deserialize_tokens = Vec::new();
-    let mut nested_params_field_idents: Vec<syn::Ident> = Vec::new();
-    let mut nested_params_group_names: Vec<String> = Vec::new();
+    let mut nested_params_tokens = Vec::new();
 
-    // We'll also enforce that there are no duplicate keys at compile time
-    // TODO: This doesn't work for nested fields since we don't know anything about the fields on
-    //       the nested structs
+    // We'll also enforce that there are no duplicate keys at compile time. We don't know anything
+    // about the fields on nested structs, so duplicates from those are checked at runtime in the
+    // generated functions instead.
     let mut param_ids = HashSet::new();
     let mut persist_ids = HashSet::new();
     for field in fields.named {
@@ -49,8 +48,8 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
         // These two attributes are mutually exclusive
         let mut id_attr: Option<String> = None;
         let mut persist_attr: Option<String> = None;
-        // And the `#[nested = "..."]` attribute contains a group name we should use
-        let mut nested_attr: Option<String> = None;
+        // And the `#[nested = "..."]` or `#[nested(...)]` attributes contain the group's metadata
+        let mut nested_attr: Option<NestedAttr> = None;
         for attr in &field.attrs {
             if attr.path.is_ident("id") {
                 match attr.parse_meta() {
@@ -99,37 +98,16 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                     }
                 };
             } else if attr.path.is_ident("nested") {
-                match attr.parse_meta() {
-                    Ok(syn::Meta::NameValue(syn::MetaNameValue {
-                        lit: syn::Lit::Str(s),
-                        ..
-                    })) => {
-                        let s = s.value();
-                        if s.is_empty() {
-                            return syn::Error::new(attr.span(), "Group names cannot be empty")
-                                .to_compile_error()
-                                .into();
-                        } else if s.contains('/') {
-                            return syn::Error::new(attr.span(), "Group names may not contain slashes")
-                                .to_compile_error()
-                                .into();
-                        } else if nested_attr.is_some() {
-                            return syn::Error::new(attr.span(), "Duplicate nested attribute")
-                                .to_compile_error()
-                                .into();
-                        } else {
-                            nested_attr = Some(s);
-                        }
-                    }
-                    _ => {
-                        return syn::Error::new(
-                            attr.span(),
-                            "The nested attribute should be a key-value pair with a string argument: #[nested = \"Group Name\"]",
-                        )
+                if nested_attr.is_some() {
+                    return syn::Error::new(attr.span(), "Duplicate nested attribute")
                         .to_compile_error()
-                        .into()
-                    }
-                };
+                        .into();
+                }
+
+                match NestedAttr::parse(attr) {
+                    Ok(parsed) => nested_attr = Some(parsed),
+                    Err(err) => return err.to_compile_error().into(),
+                }
             }
         }
 
@@ -213,13 +191,61 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
             (None, None) => (),
         }
 
-        if let Some(nested_group_name) = nested_attr {
-            nested_params_field_idents.push(field_name.clone());
-            // FIXME: Generate the insertion code here
-            nested_params_group_names.push(nested_group_name);
+        // Every nested field is turned into one or more `(index, params, group)` triples, where
+        // `index` is the one-based array index used to suffix IDs for arrays of nested structs
+        if let Some(NestedAttr {
+            group,
+            id,
+            collapsed,
+            hidden,
+            array,
+        }) = nested_attr
+        {
+            let id = id.unwrap_or_else(|| group.clone());
+            if array {
+                nested_params_tokens.push(quote! {
+                    for (idx, nested_params) in self.#field_name.iter().enumerate() {
+                        let idx = idx + 1;
+                        nested_params_groups.push((
+                            Some(idx),
+                            nested_params as &dyn ::nih_plug::param::internals::Params,
+                            ::nih_plug::param::internals::ParamGroup {
+                                path: #group.replace("{}", &idx.to_string()),
+                                id: #id.replace("{}", &idx.to_string()),
+                                collapsed: #collapsed,
+                                hidden: #hidden,
+                            },
+                        ));
+                    }
+                });
+            } else {
+                nested_params_tokens.push(quote! {
+                    nested_params_groups.push((
+                        None,
+                        &self.#field_name as &dyn ::nih_plug::param::internals::Params,
+                        ::nih_plug::param::internals::ParamGroup {
+                            path: String::from(#group),
+                            id: String::from(#id),
+                            collapsed: #collapsed,
+                            hidden: #hidden,
+                        },
+                    ));
+                });
+            }
         }
     }
 
+    // This is used in all of the functions below to iterate over the nested fields
+    let nested_params_groups_tokens = quote! {
+        #[allow(unused_mut)]
+        let mut nested_params_groups: Vec<(
+            Option<usize>,
+            &dyn ::nih_plug::param::internals::Params,
+            ::nih_plug::param::internals::ParamGroup,
+        )> = Vec::new();
+        #(#nested_params_tokens)*
+    };
+
     quote! {
         unsafe impl #impl_generics Params for #struct_name #ty_generics #where_clause {
             fn param_map(&self) -> Vec<(String, nih_plug::prelude::ParamPtr, String)> {
@@ -228,26 +254,51 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
 
                 let mut param_map = vec![#(#param_mapping_self_tokens),*];
 
-                let nested_params_fields: &[&dyn Params] = &[#(&self.#nested_params_field_idents),*];
-                let nested_params_groups: &[String] = &[#(String::from(#nested_params_group_names)),*];
-                for (nested_params, group_name) in
-                    nested_params_fields.into_iter().zip(nested_params_groups)
-                {
+                // Duplicate IDs within this struct are caught at compile time, but we can only
+                // check for duplicates coming from nested structs at runtime
+                let mut param_ids: ::std::collections::HashSet<String> = if cfg!(debug_assertions) {
+                    param_map.iter().map(|(param_id, _, _)| param_id.clone()).collect()
+                } else {
+                    ::std::collections::HashSet::new()
+                };
+
+                #nested_params_groups_tokens
+                for (idx, nested_params, group) in nested_params_groups {
                     let nested_param_map = nested_params.param_map();
-                    let prefixed_nested_param_map =
+                    let prefixed_nested_param_map: Vec<_> =
                         nested_param_map
                             .into_iter()
                             .map(|(param_id, param_ptr, nested_group_name)| {
                                 (
-                                    param_id,
+                                    match idx {
+                                        Some(idx) => format!("{}_{}", param_id, idx),
+                                        None => param_id,
+                                    },
                                     param_ptr,
                                     if nested_group_name.is_empty() {
-                                        group_name.to_string()
+                                        group.path.clone()
                                     } else {
-                                        format!("{}/{}", group_name, nested_group_name)
+                                        format!("{}/{}", group.path, nested_group_name)
                                     }
                                 )
-                            });
+                            })
+                            .collect();
+
+                    if cfg!(debug_assertions) {
+                        // Duplicates within the nested struct have already been reported there
+                        let nested_param_ids: ::std::collections::HashSet<&String> =
+                            prefixed_nested_param_map.iter().map(|(param_id, _, _)| param_id).collect();
+                        for param_id in nested_param_ids {
+                            if !param_ids.insert(param_id.clone()) {
+                                panic!(
+                                    "Duplicate parameter ID '{}' found in '{}', parameter IDs need \
+                                     to be unique across nested parameter structs",
+                                    param_id,
+                                    group.path
+                                );
+                            }
+                        }
+                    }
 
                     param_map.extend(prefixed_nested_param_map);
                 }
@@ -255,13 +306,53 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                 param_map
             }
 
+            fn param_groups(&self) -> Vec<::nih_plug::param::internals::ParamGroup> {
+                let mut param_groups = Vec::new();
+
+                #nested_params_groups_tokens
+                for (_, nested_params, group) in nested_params_groups {
+                    let prefixed_nested_param_groups = nested_params
+                        .param_groups()
+                        .into_iter()
+                        .map(|nested_group| ::nih_plug::param::internals::ParamGroup {
+                            path: format!("{}/{}", group.path, nested_group.path),
+                            id: format!("{}/{}", group.id, nested_group.id),
+                            ..nested_group
+                        })
+                        .collect::<Vec<_>>();
+
+                    param_groups.push(group);
+                    param_groups.extend(prefixed_nested_param_groups);
+                }
+
+                param_groups
+            }
+
             fn serialize_fields(&self) -> ::std::collections::HashMap<String, String> {
                 let mut serialized = ::std::collections::HashMap::new();
                 #(#field_serialize_tokens)*
 
-                let nested_params_fields: &[&dyn Params] = &[#(&self.#nested_params_field_idents),*];
-                for nested_params in nested_params_fields {
-                    serialized.extend(nested_params.serialize_fields());
+                #nested_params_groups_tokens
+                for (idx, nested_params, group) in nested_params_groups {
+                    let nested_serialized = nested_params.serialize_fields().into_iter();
+                    let prefixed_nested_serialized: Vec<(String, String)> = match idx {
+                        Some(idx) => nested_serialized
+                            .map(|(key, data)| (format!("{}_{}", key, idx), data))
+                            .collect(),
+                        None => nested_serialized.collect(),
+                    };
+
+                    for (key, data) in prefixed_nested_serialized {
+                        debug_assert!(
+                            !serialized.contains_key(&key),
+                            "Duplicate persist key '{}' found in '{}', persist keys need to be \
+                             unique across nested parameter structs",
+                            key,
+                            group.path
+                        );
+
+                        serialized.insert(key, data);
+                    }
                 }
 
                 serialized
@@ -279,12 +370,125 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                 //        parameter structs. An easy fix would be to use
                 //        https://doc.rust-lang.org/std/collections/struct.HashMap.html#method.drain_filter
                 //        once that gets stabilized.
-                let nested_params_fields: &[&dyn Params] = &[#(&self.#nested_params_field_idents),*];
-                for nested_params in nested_params_fields {
-                    nested_params.deserialize_fields(serialized);
+                #nested_params_groups_tokens
+                for (idx, nested_params, _) in nested_params_groups {
+                    match idx {
+                        Some(idx) => {
+                            // Array elements only receive their own fields, without the suffix
+                            let suffix = format!("_{}", idx);
+                            let nested_serialized = serialized
+                                .iter()
+                                .filter_map(|(key, data)| {
+                                    key.strip_suffix(&suffix)
+                                        .map(|key| (key.to_string(), data.clone()))
+                                })
+                                .collect();
+
+                            nested_params.deserialize_fields(&nested_serialized);
+                        }
+                        None => nested_params.deserialize_fields(serialized),
+                    }
                 }
             }
         }
     }
     .into()
 }
+
+/// The parsed contents of a `#[nested = "Group Name"]` or a
+/// `#[nested(group = "Group Name", id = "stable", collapsed, hidden, array)]` attribute.
+struct NestedAttr {
+    /// The group's name. For arrays this contains a `{}` placeholder for the index.
+    group: String,
+    /// The group's stable ID, if it differs from the group name.
+    id: Option<String>,
+    collapsed: bool,
+    hidden: bool,
+    /// Whether the field is an array of nested `Params` objects.
+    array: bool,
+}
+
+impl NestedAttr {
+    fn parse(attr: &syn::Attribute) -> syn::Result<Self> {
+        let mut group: Option<String> = None;
+        let mut id: Option<String> = None;
+        let mut collapsed = false;
+        let mut hidden = false;
+        let mut array = false;
+        match attr.parse_meta() {
+            Ok(syn::Meta::NameValue(syn::MetaNameValue {
+                lit: syn::Lit::Str(s),
+                ..
+            })) => group = Some(s.value()),
+            Ok(syn::Meta::List(list)) => {
+                for nested in list.nested {
+                    match nested {
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                            path,
+                            lit: syn::Lit::Str(s),
+                            ..
+                        })) if path.is_ident("group") => group = Some(s.value()),
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                            path,
+                            lit: syn::Lit::Str(s),
+                            ..
+                        })) if path.is_ident("id") => id = Some(s.value()),
+                        syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("collapsed") => {
+                            collapsed = true
+                        }
+                        syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("hidden") => {
+                            hidden = true
+                        }
+                        syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("array") => {
+                            array = true
+                        }
+                        nested => {
+                            return Err(syn::Error::new(
+                                nested.span(),
+                                "Unknown nested attribute argument, expected one of `group = \"...\"`, `id = \"...\"`, `collapsed`, `hidden`, or `array`",
+                            ))
+                        }
+                    }
+                }
+            }
+            _ => {
+                return Err(syn::Error::new(
+                    attr.span(),
+                    "The nested attribute should be a key-value pair with a string argument: #[nested = \"Group Name\"], or a list: #[nested(group = \"Group Name\")]",
+                ))
+            }
+        }
+
+        let group =
+            match group {
+                Some(group) => group,
+                None => return Err(syn::Error::new(
+                    attr.span(),
+                    "The nested attribute needs a group name: #[nested(group = \"Group Name\")]",
+                )),
+            };
+        if group.is_empty() {
+            return Err(syn::Error::new(attr.span(), "Group names cannot be empty"));
+        } else if group.contains('/') {
+            return Err(syn::Error::new(
+                attr.span(),
+                "Group names may not contain slashes",
+            ));
+        } else if array
+            && !(group.contains("{}") && id.as_ref().map_or(true, |id| id.contains("{}")))
+        {
+            return Err(syn::Error::new(
+                attr.span(),
+                "Group names and IDs for nested arrays need to contain a `{}` placeholder for the index",
+            ));
+        }
+
+        Ok(Self {
+            group,
+            id,
+            collapsed,
+            hidden,
+            array,
+        })
+    }
+}
@@ -3,12 +3,14 @@
 //! TODO: Proper usage example, for now check out the gain_gui example
 
 use baseview::gl::GlConfig;
-use baseview::{Size, WindowHandle, WindowOpenOptions, WindowScalePolicy};
+use baseview::{PhySize, Size, WindowHandle, WindowOpenOptions, WindowScalePolicy};
 use crossbeam::atomic::AtomicCell;
 use egui::Context;
 use egui_baseview::EguiWindow;
-use nih_plug::prelude::{Editor, GuiContext, ParamSetter, ParentWindowHandle};
+use nih_plug::nih_log;
+use nih_plug::prelude::{Editor, GuiContext, ParamSetter, ParentWindowHandle, ResizeHints};
 use parking_lot::RwLock;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
@@ -58,6 +60,11 @@ where
 //       to make this serializable (only restoring the size of course) so it can be persisted.
 pub struct EguiState {
     size: AtomicCell<(u32, u32)>,
+    /// If set, then the host is allowed to resize the GUI within these constraints.
+    resize_hints: Option<ResizeHints>,
+    /// A size in logical pixels the host asked the GUI to resize to. This is applied to the window
+    /// from the GUI thread at the start of the next frame.
+    requested_size: AtomicCell<Option<(u32, u32)>>,
     open: AtomicBool,
 }
 
@@ -67,6 +74,23 @@ impl EguiState {
     pub fn from_size(width: u32, height: u32) -> Arc<EguiState> {
         Arc::new(EguiState {
             size: AtomicCell::new((width, height)),
+            resize_hints: None,
+            requested_size: AtomicCell::new(None),
+            open: AtomicBool::new(false),
+        })
+    }
+
+    /// The same as [`from_size()`][Self::from_size()], but the host is also allowed to resize the
+    /// GUI within the constraints specified by `resize_hints`.
+    pub fn from_size_resizable(
+        width: u32,
+        height: u32,
+        resize_hints: ResizeHints,
+    ) -> Arc<EguiState> {
+        Arc::new(EguiState {
+            size: AtomicCell::new((width, height)),
+            resize_hints: Some(resize_hints),
+            requested_size: AtomicCell::new(None),
             open: AtomicBool::new(false),
         })
     }
@@ -104,61 +128,80 @@ where
         &self,
         parent: ParentWindowHandle,
         context: Arc<dyn GuiContext>,
-    ) -> Box<dyn std::any::Any + Send + Sync> {
+    ) -> Option<Box<dyn std::any::Any + Send + Sync>> {
         let update = self.update.clone();
         let state = self.user_state.clone();
+        let egui_state = self.egui_state.clone();
 
         let (unscaled_width, unscaled_height) = self.egui_state.size();
         let scaling_factor = self.scaling_factor.load();
-        let window = EguiWindow::open_parented(
-            &parent,
-            WindowOpenOptions {
-                title: String::from("egui window"),
-                // Baseview should be doing the DPI scaling for us
-                size: Size::new(unscaled_width as f64, unscaled_height as f64),
-                // NOTE: For some reason passing 1.0 here causes the UI to be scaled on macOS but
-                //       not the mouse events.
-                scale: scaling_factor
-                    .map(|factor| WindowScalePolicy::ScaleFactor(factor as f64))
-                    .unwrap_or(WindowScalePolicy::SystemScaleFactor),
-
-                #[cfg(feature = "opengl")]
-                gl_config: Some(GlConfig {
-                    version: (3, 2),
-                    red_bits: 8,
-                    blue_bits: 8,
-                    green_bits: 8,
-                    alpha_bits: 8,
-                    depth_bits: 24,
-                    stencil_bits: 8,
-                    samples: None,
-                    srgb: true,
-                    double_buffer: true,
-                    vsync: true,
-                    ..Default::default()
-                }),
-            },
-            state,
-            |_, _, _| {},
-            move |egui_ctx, queue, state| {
-                let setter = ParamSetter::new(context.as_ref());
-
-                // For now, just always redraw. Most plugin GUIs have meters, and those almost always
-                // need a redraw. Later we can try to be a bit more sophisticated about this. Without
-                // this we would also have a blank GUI when it gets first opened because most DAWs open
-                // their GUI while the window is still unmapped.
-                // TODO: Are there other useful parts of this queue we could pass to thep lugin?
-                queue.request_repaint();
-                (update)(egui_ctx, &setter, &mut state.write());
-            },
-        )
-        .expect("We provided an OpenGL config, did we not?");
+        // Window creation can both fail and panic, for instance when the OpenGL context could not
+        // be created. We'll catch both here so the wrapper can report the failure to the host.
+        let window = match panic::catch_unwind(AssertUnwindSafe(|| {
+            EguiWindow::open_parented(
+                &parent,
+                WindowOpenOptions {
+                    title: String::from("egui window"),
+                    // Baseview should be doing the DPI scaling for us
+                    size: Size::new(unscaled_width as f64, unscaled_height as f64),
+                    // NOTE: For some reason passing 1.0 here causes the UI to be scaled on macOS
+                    //       but not the mouse events.
+                    scale: scaling_factor
+                        .map(|factor| WindowScalePolicy::ScaleFactor(factor as f64))
+                        .unwrap_or(WindowScalePolicy::SystemScaleFactor),
+
+                    #[cfg(feature = "opengl")]
+                    gl_config: Some(GlConfig {
+                        version: (3, 2),
+                        red_bits: 8,
+                        blue_bits: 8,
+                        green_bits: 8,
+                        alpha_bits: 8,
+                        depth_bits: 24,
+                        stencil_bits: 8,
+                        samples: None,
+                        srgb: true,
+                        double_buffer: true,
+                        vsync: true,
+                        ..Default::default()
+                    }),
+                },
+                state,
+                |_, _, _| {},
+                move |egui_ctx, queue, state| {
+                    let setter = ParamSetter::new(context.as_ref());
+
+                    // Host-initiated resizes are applied here since this runs on the GUI thread
+                    if let Some((width, height)) = egui_state.requested_size.take() {
+                        let pixels_per_point = egui_ctx.pixels_per_point();
+                        queue.resize(PhySize::new(
+                            (width as f32 * pixels_per_point).round() as u32,
+                            (height as f32 * pixels_per_point).round() as u32,
+                        ));
+                    }
+
+                    // For now, just always redraw. Most plugin GUIs have meters, and those almost
+                    // always need a redraw. Later we can try to be a bit more sophisticated about
+                    // this. Without this we would also have a blank GUI when it gets first opened
+                    // because most DAWs open their GUI while the window is still unmapped.
+                    // TODO: Are there other useful parts of this queue we could pass to thep lugin?
+                    queue.request_repaint();
+                    (update)(egui_ctx, &setter, &mut state.write());
+                },
+            )
+        })) {
+            Ok(Some(window)) => window,
+            Ok(None) | Err(_) => {
+                nih_log!("Failed to open the editor window");
+                return None;
+            }
+        };
 
         self.egui_state.open.store(true, Ordering::Release);
-        Box::new(EguiEditorHandle {
+        Some(Box::new(EguiEditorHandle {
             egui_state: self.egui_state.clone(),
             window,
-        })
+        }))
     }
 
     fn size(&self) -> (u32, u32) {
@@ -170,6 +213,28 @@ where
         true
     }
 
+    fn can_resize(&self) -> bool {
+        self.egui_state.resize_hints.is_some()
+    }
+
+    fn resize_hints(&self) -> ResizeHints {
+        self.egui_state.resize_hints.unwrap_or_default()
+    }
+
+    fn set_size(&self, width: u32, height: u32) -> bool {
+        // Hosts don't always ask us to constrain the size first
+        if self.resize_hints().constrain(width, height) != (width, height) {
+            return false;
+        }
+
+        self.egui_state.size.store((width, height));
+        if self.egui_state.is_open() {
+            self.egui_state.requested_size.store(Some((width, height)));
+        }
+
+        true
+    }
+
     fn param_values_changed(&self) {
         // As mentioned above, for now we'll always force a redraw to allow meter widgets to work
         // correctly. In the future we can use an `Arc<AtomicBool>` and only force a redraw when
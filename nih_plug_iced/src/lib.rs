@@ -92,8 +92,10 @@
 use baseview::{WindowOpenOptions, WindowScalePolicy};
 use crossbeam::atomic::AtomicCell;
 use crossbeam::channel;
-use nih_plug::prelude::{Editor, GuiContext, ParentWindowHandle};
+use nih_plug::nih_log;
+use nih_plug::prelude::{Editor, GuiContext, ParentWindowHandle, ResizeHints};
 use std::fmt::Debug;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
@@ -233,6 +235,11 @@ pub trait IcedEditor: 'static + Send + Sync + Sized {
 //       to make this serializable (only restoring the size of course) so it can be persisted.
 pub struct IcedState {
     size: AtomicCell<(u32, u32)>,
+    /// If set, then the host is allowed to resize the GUI within these constraints.
+    resize_hints: Option<ResizeHints>,
+    /// A size in logical pixels the host asked the GUI to resize to. This is applied to the window
+    /// from the GUI thread during the next update.
+    requested_size: AtomicCell<Option<(u32, u32)>>,
     open: AtomicBool,
 }
 
@@ -242,6 +249,23 @@ impl IcedState {
     pub fn from_size(width: u32, height: u32) -> Arc<IcedState> {
         Arc::new(IcedState {
             size: AtomicCell::new((width, height)),
+            resize_hints: None,
+            requested_size: AtomicCell::new(None),
+            open: AtomicBool::new(false),
+        })
+    }
+
+    /// The same as [`from_size()`][Self::from_size()], but the host is also allowed to resize the
+    /// GUI within the constraints specified by `resize_hints`.
+    pub fn from_size_resizable(
+        width: u32,
+        height: u32,
+        resize_hints: ResizeHints,
+    ) -> Arc<IcedState> {
+        Arc::new(IcedState {
+            size: AtomicCell::new((width, height)),
+            resize_hints: Some(resize_hints),
+            requested_size: AtomicCell::new(None),
             open: AtomicBool::new(false),
         })
     }
@@ -280,67 +304,76 @@ impl<E: IcedEditor> Editor for IcedEditorWrapper<E> {
         &self,
         parent: ParentWindowHandle,
         context: Arc<dyn GuiContext>,
-    ) -> Box<dyn std::any::Any + Send + Sync> {
+    ) -> Option<Box<dyn std::any::Any + Send + Sync>> {
         let (unscaled_width, unscaled_height) = self.iced_state.size();
         let scaling_factor = self.scaling_factor.load();
 
-        // TODO: iced_baseview does not have gracefuly error handling for context creation failures.
-        //       This will panic if the context could not be created.
-        let window = IcedWindow::<wrapper::IcedEditorWrapperApplication<E>>::open_parented(
-            &parent,
-            Settings {
-                window: WindowOpenOptions {
-                    title: String::from("iced window"),
-                    // Baseview should be doing the DPI scaling for us
-                    size: baseview::Size::new(unscaled_width as f64, unscaled_height as f64),
-                    // NOTE: For some reason passing 1.0 here causes the UI to be scaled on macOS but
-                    //       not the mouse events.
-                    scale: scaling_factor
-                        .map(|factor| WindowScalePolicy::ScaleFactor(factor as f64))
-                        .unwrap_or(WindowScalePolicy::SystemScaleFactor),
-
-                    #[cfg(feature = "opengl")]
-                    gl_config: Some(baseview::gl::GlConfig {
-                        // FIXME: glow_glyph forgot to add an `#extension`, so this won't work under
-                        //        OpenGL 3.2 at the moment. With that change applied this should work on
-                        //        OpenGL 3.2/macOS.
-                        version: (3, 3),
-                        red_bits: 8,
-                        blue_bits: 8,
-                        green_bits: 8,
-                        alpha_bits: 8,
-                        depth_bits: 24,
-                        stencil_bits: 8,
-                        samples: None,
-                        srgb: true,
-                        double_buffer: true,
-                        vsync: true,
-                        ..Default::default()
-                    }),
-                    // FIXME: Rust analyzer always thinks baseview/opengl is enabled even if we
-                    //        don't explicitly enable it, so you'd get a compile error if this line
-                    //        is missing
-                    #[cfg(not(feature = "opengl"))]
-                    gl_config: None,
-                },
-                iced_baseview: IcedBaseviewSettings {
-                    ignore_non_modifier_keys: false,
-                    always_redraw: true,
-                },
-                // We use this wrapper to be able to pass the GUI context to the editor
-                flags: (
-                    context,
-                    self.parameter_updates_receiver.clone(),
-                    self.initialization_flags.clone(),
-                ),
+        let settings = Settings {
+            window: WindowOpenOptions {
+                title: String::from("iced window"),
+                // Baseview should be doing the DPI scaling for us
+                size: baseview::Size::new(unscaled_width as f64, unscaled_height as f64),
+                // NOTE: For some reason passing 1.0 here causes the UI to be scaled on macOS but
+                //       not the mouse events.
+                scale: scaling_factor
+                    .map(|factor| WindowScalePolicy::ScaleFactor(factor as f64))
+                    .unwrap_or(WindowScalePolicy::SystemScaleFactor),
+
+                #[cfg(feature = "opengl")]
+                gl_config: Some(baseview::gl::GlConfig {
+                    // FIXME: glow_glyph forgot to add an `#extension`, so this won't work under
+                    //        OpenGL 3.2 at the moment. With that change applied this should work on
+                    //        OpenGL 3.2/macOS.
+                    version: (3, 3),
+                    red_bits: 8,
+                    blue_bits: 8,
+                    green_bits: 8,
+                    alpha_bits: 8,
+                    depth_bits: 24,
+                    stencil_bits: 8,
+                    samples: None,
+                    srgb: true,
+                    double_buffer: true,
+                    vsync: true,
+                    ..Default::default()
+                }),
+                // FIXME: Rust analyzer always thinks baseview/opengl is enabled even if we
+                //        don't explicitly enable it, so you'd get a compile error if this line
+                //        is missing
+                #[cfg(not(feature = "opengl"))]
+                gl_config: None,
             },
-        );
+            iced_baseview: IcedBaseviewSettings {
+                ignore_non_modifier_keys: false,
+                always_redraw: true,
+            },
+            // We use this wrapper to be able to pass the GUI context to the editor
+            flags: (
+                context,
+                self.iced_state.clone(),
+                self.parameter_updates_receiver.clone(),
+                self.initialization_flags.clone(),
+            ),
+        };
+
+        // iced_baseview does not have graceful error handling for context creation failures and it
+        // will panic instead. We'll catch that here so the wrapper can report the failure to the
+        // host.
+        let window = match panic::catch_unwind(AssertUnwindSafe(|| {
+            IcedWindow::<wrapper::IcedEditorWrapperApplication<E>>::open_parented(&parent, settings)
+        })) {
+            Ok(window) => window,
+            Err(_) => {
+                nih_log!("Failed to open the editor window");
+                return None;
+            }
+        };
 
         self.iced_state.open.store(true, Ordering::Release);
-        Box::new(IcedEditorHandle {
+        Some(Box::new(IcedEditorHandle {
             iced_state: self.iced_state.clone(),
             window,
-        })
+        }))
     }
 
     fn size(&self) -> (u32, u32) {
@@ -352,6 +385,30 @@ impl<E: IcedEditor> Editor for IcedEditorWrapper<E> {
         true
     }
 
+    fn can_resize(&self) -> bool {
+        self.iced_state.resize_hints.is_some()
+    }
+
+    fn resize_hints(&self) -> ResizeHints {
+        self.iced_state.resize_hints.unwrap_or_default()
+    }
+
+    fn set_size(&self, width: u32, height: u32) -> bool {
+        // Hosts don't always ask us to constrain the size first
+        if self.resize_hints().constrain(width, height) != (width, height) {
+            return false;
+        }
+
+        self.iced_state.size.store((width, height));
+        if self.iced_state.is_open() {
+            self.iced_state.requested_size.store(Some((width, height)));
+            // This forces an update so the new size gets applied to the window
+            let _ = self.parameter_updates_sender.try_send(ParameterUpdate);
+        }
+
+        true
+    }
+
     fn param_values_changed(&self) {
         if self.iced_state.is_open() {
             // If there's already a paramter change notification in the channel then we don't need
@@ -7,8 +7,8 @@ use std::sync::Arc;
 
 use crate::futures::FutureExt;
 use crate::{
-    futures, subscription, Application, Color, Command, Element, IcedEditor, Subscription,
-    WindowQueue, WindowScalePolicy, WindowSubs,
+    futures, subscription, Application, Color, Command, Element, IcedEditor, IcedState,
+    Subscription, WindowQueue, WindowScalePolicy, WindowSubs,
 };
 
 /// Wraps an `iced_baseview` [`Application`] around [`IcedEditor`]. Needed to allow editors to
@@ -16,6 +16,9 @@ use crate::{
 pub(crate) struct IcedEditorWrapperApplication<E: IcedEditor> {
     editor: E,
 
+    /// Used to apply host-initiated resizes to the window.
+    iced_state: Arc<IcedState>,
+
     /// We will receive notifications about parameters being changed on here. Whenever a parameter
     /// update gets sent, we will trigger a [`Message::parameterUpdate`] which causes the UI to be
     /// redrawn.
@@ -53,18 +56,20 @@ impl<E: IcedEditor> Application for IcedEditorWrapperApplication<E> {
     type Message = Message<E>;
     type Flags = (
         Arc<dyn GuiContext>,
+        Arc<IcedState>,
         Arc<channel::Receiver<crate::ParameterUpdate>>,
         E::InitializationFlags,
     );
 
     fn new(
-        (context, parameter_updates_receiver, flags): Self::Flags,
+        (context, iced_state, parameter_updates_receiver, flags): Self::Flags,
     ) -> (Self, Command<Self::Message>) {
         let (editor, command) = E::new(flags, context);
 
         (
             Self {
                 editor,
+                iced_state,
                 parameter_updates_receiver,
             },
             command.map(Message::EditorMessage),
@@ -77,6 +82,12 @@ impl<E: IcedEditor> Application for IcedEditorWrapperApplication<E> {
         window: &mut WindowQueue,
         message: Self::Message,
     ) -> Command<Self::Message> {
+        // `IcedEditorWrapper::set_size()` sends a parameter update message to make sure this gets
+        // called after the host resized the editor
+        if let Some((width, height)) = self.iced_state.requested_size.take() {
+            window.resize(baseview::Size::new(width as f64, height as f64));
+        }
+
         match message {
             Message::EditorMessage(message) => self
                 .editor
@@ -2,7 +2,9 @@
 
 use baseview::{WindowHandle, WindowScalePolicy};
 use crossbeam::atomic::AtomicCell;
-use nih_plug::prelude::{Editor, GuiContext, ParentWindowHandle};
+use nih_plug::nih_log;
+use nih_plug::prelude::{Editor, GuiContext, ParentWindowHandle, ResizeHints};
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use vizia::{Application, Color, Context, Entity, Model, PropSet};
@@ -79,6 +81,11 @@ pub struct ViziaState {
     /// A scale factor that should be applied to `size` separate from from any system HiDPI scaling.
     /// This can be used to allow GUIs to be scaled uniformly.
     scale_factor: AtomicCell<f64>,
+    /// If set, then the host is allowed to resize the GUI within these constraints.
+    resize_hints: Option<ResizeHints>,
+    /// A size in logical pixels before applying `scale_factor` the host asked the GUI to resize
+    /// to. This is applied to the window from the GUI thread during the next idle callback.
+    requested_size: AtomicCell<Option<(u32, u32)>>,
     open: AtomicBool,
 }
 
@@ -89,6 +96,25 @@ impl ViziaState {
         Arc::new(ViziaState {
             size: AtomicCell::new((width, height)),
             scale_factor: AtomicCell::new(1.0),
+            resize_hints: None,
+            requested_size: AtomicCell::new(None),
+            open: AtomicBool::new(false),
+        })
+    }
+
+    /// The same as [`from_size()`][Self::from_size()], but the host is also allowed to resize the
+    /// GUI within the constraints specified by `resize_hints`. These constraints are in logical
+    /// pixels after applying the user scale factor.
+    pub fn from_size_resizable(
+        width: u32,
+        height: u32,
+        resize_hints: ResizeHints,
+    ) -> Arc<ViziaState> {
+        Arc::new(ViziaState {
+            size: AtomicCell::new((width, height)),
+            scale_factor: AtomicCell::new(1.0),
+            resize_hints: Some(resize_hints),
+            requested_size: AtomicCell::new(None),
             open: AtomicBool::new(false),
         })
     }
@@ -100,6 +126,8 @@ impl ViziaState {
         Arc::new(ViziaState {
             size: AtomicCell::new((width, height)),
             scale_factor: AtomicCell::new(scale_factor),
+            resize_hints: None,
+            requested_size: AtomicCell::new(None),
             open: AtomicBool::new(false),
         })
     }
@@ -154,7 +182,7 @@ impl Editor for ViziaEditor {
         &self,
         parent: ParentWindowHandle,
         context: Arc<dyn GuiContext>,
-    ) -> Box<dyn std::any::Any + Send + Sync> {
+    ) -> Option<Box<dyn std::any::Any + Send + Sync>> {
         let app = self.app.clone();
         let vizia_state = self.vizia_state.clone();
         let apply_theming = self.apply_theming;
@@ -163,7 +191,7 @@ impl Editor for ViziaEditor {
         let system_scaling_factor = self.scaling_factor.load();
         let user_scale_factor = vizia_state.user_scale_factor();
 
-        let window = Application::new(move |cx| {
+        let application = Application::new(move |cx| {
             // Set some default styles to match the iced integration
             if apply_theming {
                 // NOTE: vizia's font rendering looks way too dark and thick. Going one font weight
@@ -200,20 +228,42 @@ impl Editor for ViziaEditor {
 
             app(cx, context.clone())
         })
+        .on_idle({
+            let vizia_state = vizia_state.clone();
+            move |cx| {
+                // Apply host-initiated resizes from the GUI thread. The `WindowModel` will see that
+                // this size already matches the size stored in `vizia_state`, so this won't cause
+                // another resize request to be sent to the host.
+                if let Some((width, height)) = vizia_state.requested_size.take() {
+                    cx.window_size.width = width;
+                    cx.window_size.height = height;
+                }
+            }
+        })
         .with_scale_policy(
             system_scaling_factor
                 .map(|factor| WindowScalePolicy::ScaleFactor(factor as f64))
                 .unwrap_or(WindowScalePolicy::SystemScaleFactor),
         )
         .inner_size((unscaled_width, unscaled_height))
-        .user_scale_factor(user_scale_factor)
-        .open_parented(&parent);
+        .user_scale_factor(user_scale_factor);
+
+        // vizia and baseview panic when the window or its OpenGL context cannot be created. We'll
+        // catch that here so the wrapper can report the failure to the host instead.
+        let window =
+            match panic::catch_unwind(AssertUnwindSafe(|| application.open_parented(&parent))) {
+                Ok(window) => window,
+                Err(_) => {
+                    nih_log!("Failed to open the editor window");
+                    return None;
+                }
+            };
 
         self.vizia_state.open.store(true, Ordering::Release);
-        Box::new(ViziaEditorHandle {
+        Some(Box::new(ViziaEditorHandle {
             vizia_state: self.vizia_state.clone(),
             window,
-        })
+        }))
     }
 
     fn size(&self) -> (u32, u32) {
@@ -229,6 +279,35 @@ impl Editor for ViziaEditor {
         true
     }
 
+    fn can_resize(&self) -> bool {
+        self.vizia_state.resize_hints.is_some()
+    }
+
+    fn resize_hints(&self) -> ResizeHints {
+        self.vizia_state.resize_hints.unwrap_or_default()
+    }
+
+    fn set_size(&self, width: u32, height: u32) -> bool {
+        // Hosts don't always ask us to constrain the size first
+        if self.resize_hints().constrain(width, height) != (width, height) {
+            return false;
+        }
+
+        // The host's size includes the user scale factor, but the size stored in the state does not
+        let user_scale_factor = self.vizia_state.user_scale_factor();
+        let inner_size = (
+            (width as f64 / user_scale_factor).round() as u32,
+            (height as f64 / user_scale_factor).round() as u32,
+        );
+
+        self.vizia_state.size.store(inner_size);
+        if self.vizia_state.is_open() {
+            self.vizia_state.requested_size.store(Some(inner_size));
+        }
+
+        true
+    }
+
     fn param_values_changed(&self) {
         // TODO: Update the GUI when this happens, right now this happens automatically as a result
         //       of of the reactivity
@@ -28,6 +28,12 @@ struct GainParams {
     /// DAW displays parameters in a tree structure.
     #[nested = "Subparameters"]
     pub sub_params: SubParams,
+
+    /// Repeating blocks of parameters can be stored in an array or a `Vec`. Every element gets its
+    /// own group, and the elements' parameter IDs are suffixed with the element's index. This means
+    /// that the parameter below will have the IDs `noope_1`, `noope_2`, and `noope_3`.
+    #[nested(array, group = "Array Parameters {}")]
+    pub array_params: [ArrayParams; 3],
 }
 
 #[derive(Params)]
@@ -45,6 +51,12 @@ struct SubSubParams {
     pub nope: FloatParam,
 }
 
+#[derive(Params)]
+struct ArrayParams {
+    #[id = "noope"]
+    pub nope: FloatParam,
+}
+
 impl Default for Gain {
     fn default() -> Self {
         Self {
@@ -88,6 +100,13 @@ impl Default for GainParams {
                     nope: FloatParam::new("Nope", 0.5, FloatRange::Linear { min: 1.0, max: 2.0 }),
                 },
             },
+            array_params: [1, 2, 3].map(|index| ArrayParams {
+                nope: FloatParam::new(
+                    format!("Nope {index}"),
+                    0.5,
+                    FloatRange::Linear { min: 1.0, max: 2.0 },
+                ),
+            }),
         }
     }
 }
@@ -114,6 +133,8 @@ impl Plugin for Gain {
     // splits.
     const SAMPLE_ACCURATE_AUTOMATION: bool = true;
 
+    type BackgroundTask = ();
+
     fn params(&self) -> Arc<dyn Params> {
         self.params.clone()
     }
@@ -131,7 +152,8 @@ impl Plugin for Gain {
     fn process(
         &mut self,
         buffer: &mut Buffer,
-        _context: &mut impl ProcessContext,
+        _aux: &mut AuxiliaryBuffers,
+        _context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
         for channel_samples in buffer.iter_samples() {
             // Smoothing is optionally built into the parameters themselves
@@ -24,21 +24,26 @@ const MIN_WINDOW_ORDER: usize = 6;
 #[allow(dead_code)]
 const MIN_WINDOW_SIZE: usize = 1 << MIN_WINDOW_ORDER; // 64
 const DEFAULT_WINDOW_ORDER: usize = 10;
-#[allow(dead_code)]
 const DEFAULT_WINDOW_SIZE: usize = 1 << DEFAULT_WINDOW_ORDER; // 1024
 const MAX_WINDOW_ORDER: usize = 15;
 const MAX_WINDOW_SIZE: usize = 1 << MAX_WINDOW_ORDER; // 32768
 
 const MIN_OVERLAP_ORDER: usize = 2;
 #[allow(dead_code)]
-const MIN_OVERLAP_TIMES: usize = 2 << MIN_OVERLAP_ORDER; // 4
+const MIN_OVERLAP_TIMES: usize = 1 << MIN_OVERLAP_ORDER; // 4
 const DEFAULT_OVERLAP_ORDER: usize = 3;
-#[allow(dead_code)]
 const DEFAULT_OVERLAP_TIMES: usize = 1 << DEFAULT_OVERLAP_ORDER; // 4
 const MAX_OVERLAP_ORDER: usize = 5;
 #[allow(dead_code)]
 const MAX_OVERLAP_TIMES: usize = 1 << MAX_OVERLAP_ORDER; // 32
 
+/// The window sizes the user can choose from, these are all the powers of two between
+/// `MIN_WINDOW_SIZE` and `MAX_WINDOW_SIZE`.
+const WINDOW_SIZES: &[i32] = &[64, 128, 256, 512, 1024, 2048, 4096, 8192, 16384, 32768];
+/// The amounts of overlap the user can choose from, these are all the powers of two between
+/// `MIN_OVERLAP_TIMES` and `MAX_OVERLAP_TIMES`.
+const OVERLAP_TIMES: &[i32] = &[4, 8, 16, 32];
+
 struct PubertySimulator {
     params: Arc<PubertySimulatorParams>,
 
@@ -69,13 +74,13 @@ struct PubertySimulatorParams {
     #[id = "pitch"]
     pitch_octaves: FloatParam,
 
-    /// The size of the FFT window as a power of two (to prevent invalid inputs).
+    /// The size of the FFT window. This can only be set to powers of two.
     #[id = "wndsz"]
-    window_size_order: IntParam,
-    /// The amount of overlap to use in the overlap-add algorithm as a power of two (again to
-    /// prevent invalid inputs).
+    window_size: IntParam,
+    /// The amount of overlap to use in the overlap-add algorithm. This can also only be set to
+    /// powers of two.
     #[id = "ovrlap"]
-    overlap_times_order: IntParam,
+    overlap_times: IntParam,
 
     /// The type of broken pitch shifting to apply.
     #[id = "mode"]
@@ -110,9 +115,6 @@ impl Default for PubertySimulator {
 
 impl Default for PubertySimulatorParams {
     fn default() -> Self {
-        let power_of_two_val2str = formatters::v2s_i32_power_of_two();
-        let power_of_two_str2val = formatters::s2v_i32_power_of_two();
-
         Self {
             pitch_octaves: FloatParam::new(
                 "Pitch",
@@ -130,26 +132,16 @@ impl Default for PubertySimulatorParams {
             .with_unit(" Octaves")
             .with_value_to_string(formatters::v2s_f32_rounded(2)),
 
-            window_size_order: IntParam::new(
+            window_size: IntParam::new(
                 "Window Size",
-                DEFAULT_WINDOW_ORDER as i32,
-                IntRange::Linear {
-                    min: MIN_WINDOW_ORDER as i32,
-                    max: MAX_WINDOW_ORDER as i32,
-                },
-            )
-            .with_value_to_string(power_of_two_val2str.clone())
-            .with_string_to_value(power_of_two_str2val.clone()),
-            overlap_times_order: IntParam::new(
+                DEFAULT_WINDOW_SIZE as i32,
+                IntRange::Table(WINDOW_SIZES),
+            ),
+            overlap_times: IntParam::new(
                 "Window Overlap",
-                DEFAULT_OVERLAP_ORDER as i32,
-                IntRange::Linear {
-                    min: MIN_OVERLAP_ORDER as i32,
-                    max: MAX_OVERLAP_ORDER as i32,
-                },
-            )
-            .with_value_to_string(power_of_two_val2str)
-            .with_string_to_value(power_of_two_str2val),
+                DEFAULT_OVERLAP_TIMES as i32,
+                IntRange::Table(OVERLAP_TIMES),
+            ),
             mode: EnumParam::new("Mode", PitchShiftingMode::InterpolateRectangular),
         }
     }
@@ -161,15 +153,29 @@ impl Plugin for PubertySimulator {
     const URL: &'static str = "https://github.com/robbert-vdh/nih-plug";
     const EMAIL: &'static str = "mail@robbertvanderhelm.nl";
 
-    const VERSION: &'static str = "0.1.0";
+    const VERSION: &'static str = "0.1.1";
 
     const DEFAULT_NUM_INPUTS: u32 = 2;
     const DEFAULT_NUM_OUTPUTS: u32 = 2;
 
+    type BackgroundTask = ();
+
     fn params(&self) -> Arc<dyn Params> {
         self.params.clone()
     }
 
+    fn filter_state(state: &mut PluginState) {
+        // Before version 0.1.1 the window size and overlap parameters stored the power of two
+        // instead of the actual value
+        if state.version.is_empty() || state.version == "0.1.0" {
+            for param_id in ["wndsz", "ovrlap"] {
+                if let Some(ParamValue::I32(order)) = state.params.get_mut(param_id) {
+                    *order = 1 << *order;
+                }
+            }
+        }
+    }
+
     fn accepts_bus_config(&self, config: &BusConfig) -> bool {
         // We'll only do stereo for simplicity's sake
         config.num_input_channels == config.num_output_channels && config.num_input_channels == 2
@@ -179,7 +185,7 @@ impl Plugin for PubertySimulator {
         &mut self,
         _bus_config: &BusConfig,
         _buffer_config: &BufferConfig,
-        context: &mut impl ProcessContext,
+        context: &mut impl ProcessContext<Self>,
     ) -> bool {
         // Planning with RustFFT is very fast, but it will still allocate we we'll plan all of the
         // FFTs we might need in advance
@@ -215,7 +221,12 @@ impl Plugin for PubertySimulator {
         self.stft.set_block_size(self.window_size());
     }
 
-    fn process(&mut self, buffer: &mut Buffer, context: &mut impl ProcessContext) -> ProcessStatus {
+    fn process(
+        &mut self,
+        buffer: &mut Buffer,
+        _aux: &mut AuxiliaryBuffers,
+        context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
         // Compensate for the window function, the overlap, and the extra gain introduced by the
         // IDFT operation
         let window_size = self.window_size();
@@ -238,7 +249,7 @@ impl Plugin for PubertySimulator {
         // These plans have already been made during initialization we can switch between versions
         // without reallocating
         let fft_plan = &mut self.plan_for_order.as_mut().unwrap()
-            [self.params.window_size_order.value as usize - MIN_WINDOW_ORDER];
+            [window_size.trailing_zeros() as usize - MIN_WINDOW_ORDER];
 
         let mut smoothed_pitch_value = 0.0;
         self.stft
@@ -388,11 +399,11 @@ impl Plugin for PubertySimulator {
 
 impl PubertySimulator {
     fn window_size(&self) -> usize {
-        1 << self.params.window_size_order.value as usize
+        self.params.window_size.value as usize
     }
 
     fn overlap_times(&self) -> usize {
-        1 << self.params.overlap_times_order.value as usize
+        self.params.overlap_times.value as usize
     }
 
     /// `window_size` should not exceed `MAX_WINDOW_SIZE` or this will allocate.
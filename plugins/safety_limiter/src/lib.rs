@@ -107,6 +107,8 @@ impl Plugin for SafetyLimiter {
     const DEFAULT_NUM_INPUTS: u32 = 2;
     const DEFAULT_NUM_OUTPUTS: u32 = 2;
 
+    type BackgroundTask = ();
+
     fn params(&self) -> Arc<dyn Params> {
         self.params.clone()
     }
@@ -119,7 +121,7 @@ impl Plugin for SafetyLimiter {
         &mut self,
         _bus_config: &BusConfig,
         buffer_config: &BufferConfig,
-        _context: &mut impl ProcessContext,
+        _context: &mut impl ProcessContext<Self>,
     ) -> bool {
         self.buffer_config = *buffer_config;
         self.morse_fadeout_samples_start =
@@ -139,7 +141,8 @@ impl Plugin for SafetyLimiter {
     fn process(
         &mut self,
         buffer: &mut Buffer,
-        _context: &mut impl ProcessContext,
+        _aux: &mut AuxiliaryBuffers,
+        _context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
         // Don't do anything when bouncing
         if self.buffer_config.process_mode == ProcessMode::Offline {
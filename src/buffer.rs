@@ -27,6 +27,22 @@ pub struct Buffer<'a> {
     output_slices: Vec<&'a mut [f32]>,
 }
 
+/// Buffers containing the plugin's auxiliary inputs and outputs, if it has any. These are passed
+/// to [`Plugin::process()`][crate::prelude::Plugin::process()] next to the main output buffer.
+/// Auxiliary inputs and outputs can be configured through
+/// [`Plugin::DEFAULT_AUX_INPUTS`][crate::prelude::Plugin::DEFAULT_AUX_INPUTS] and
+/// [`Plugin::DEFAULT_AUX_OUTPUTS`][crate::prelude::Plugin::DEFAULT_AUX_OUTPUTS]. All of these
+/// buffers contain the same number of samples as the main buffer.
+pub struct AuxiliaryBuffers<'a> {
+    /// All auxiliary (sidechain) input busses. These contain copies of the host's input data, so
+    /// they can safely be overwritten by the plugin. If the host did not connect a sidechain input,
+    /// then the corresponding buffer will contain silence.
+    pub inputs: &'a mut [Buffer<'a>],
+    /// All auxiliary output busses. These point directly to the host's output buffers and they are
+    /// not cleared beforehand, so the plugin should write to every sample in these buffers.
+    pub outputs: &'a mut [Buffer<'a>],
+}
+
 impl<'a> Buffer<'a> {
     /// Returns the numer of samples in this buffer.
     #[inline]
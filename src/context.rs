@@ -2,11 +2,14 @@
 
 use std::fmt::Display;
 
-use crate::midi::NoteEvent;
+use crate::midi::{NoteEvent, SysExMessage};
 use crate::param::internals::ParamPtr;
 use crate::param::Param;
+use crate::plugin::Plugin;
 use crate::wrapper::state::PluginState;
 
+pub use crate::event_loop::AsyncExecutor;
+
 // TODO: ProcessContext for parameter automation and sending events
 
 /// General callbacks the plugin can make during its lifetime. This is passed to the plugin during
@@ -17,10 +20,16 @@ use crate::wrapper::state::PluginState;
 //
 // The implementing wrapper needs to be able to handle concurrent requests, and it should perform
 // the actual callback within [MainThreadQueue::do_maybe_async].
-pub trait ProcessContext {
+pub trait ProcessContext<P: Plugin> {
     /// Get the current plugin API.
     fn plugin_api(&self) -> PluginApi;
 
+    /// Run a task on the wrapper's background thread using the function returned by
+    /// [`Plugin::task_executor()`][crate::prelude::Plugin::task_executor()]. This is realtime safe
+    /// and it will never block. If the task queue is full then the task will be dropped and a
+    /// debug assertion will be triggered.
+    fn execute_background(&self, task: P::BackgroundTask);
+
     /// Get information about the current transport position and status.
     fn transport(&self) -> &Transport;
 
@@ -63,14 +72,83 @@ pub trait ProcessContext {
     /// otherwise.
     fn send_event(&mut self, event: NoteEvent);
 
+    /// Get the raw data for a SysEx message received through [`next_event()`][Self::next_event()]
+    /// or created using [`new_sysex_message()`][Self::new_sysex_message()], including the leading
+    /// `0xF0` and trailing `0xF7` bytes. Long messages are only valid during the processing cycle
+    /// they were received or created in.
+    fn sysex_data<'a>(&'a self, message: &'a SysExMessage) -> &'a [u8];
+
+    /// Create a SysEx message of any length that can be sent to the host using
+    /// [`send_event()`][Self::send_event()]. This is realtime safe. Long messages are copied to the
+    /// wrapper's preallocated SysEx storage, and `None` is returned if that storage is full. See
+    /// [`SYSEX_STORAGE_SIZE`][crate::midi::SYSEX_STORAGE_SIZE] for more information.
+    fn new_sysex_message(&mut self, data: &[u8]) -> Option<SysExMessage>;
+
     /// Update the current latency of the plugin. If the plugin is currently processing audio, then
     /// this may cause audio playback to be restarted.
     fn set_latency_samples(&self, samples: u32);
 
-    // TODO: Add this, this works similar to [GuiContext::set_parameter] but it adds the parameter
-    //       change to a queue (or directly to the VST3 plugin's parameter output queues) instead of
-    //       using main thread host automation (and all the locks involved there).
-    // fn set_parameter<P: Param>(&self, param: &P, value: P::Plain);
+    /// Get the changes the host made to `param` after the first sample of the current buffer as
+    /// `(timing, plain_value)` pairs. This is only used when
+    /// [`Plugin::QUEUED_AUTOMATION`][crate::prelude::Plugin::QUEUED_AUTOMATION] is enabled, and it
+    /// will always be empty otherwise. The parameter itself will still contain the value from the
+    /// start of the buffer. These changes can be passed directly to
+    /// [`Smoother::next_block_with_changes()`][crate::prelude::Smoother::next_block_with_changes()]
+    /// together with the index of the block's first sample in the buffer.
+    ///
+    /// ```ignore
+    /// for (block_start, block) in buffer.iter_blocks(MAX_BLOCK_SIZE) {
+    ///     let gain = self.params.gain.smoothed.next_block_with_changes(
+    ///         &block,
+    ///         block_start,
+    ///         sample_rate,
+    ///         context.param_changes(&self.params.gain),
+    ///     );
+    ///
+    ///     // ...
+    /// }
+    /// ```
+    fn param_changes<'a, T: Param>(&'a self, param: &'a T) -> ParamChanges<'a, T> {
+        ParamChanges {
+            param,
+            changes: self.raw_param_changes(param.as_ptr()).iter(),
+        }
+    }
+
+    /// The same as [`param_changes()`][Self::param_changes()], but with the raw normalized values
+    /// for a type erased parameter.
+    fn raw_param_changes(&self, param: ParamPtr) -> &[ParamChange];
+
+    /// Set a parameter to the specified value from the audio thread and inform the host about the
+    /// change. This works similarly to [`ParamSetter::set_parameter()`], but it is realtime safe
+    /// and it does not need to be surrounded by begin and end calls. The change is sent to the host
+    /// along with the plugin's other output events, and the parameter's value will be updated once
+    /// the current process call has finished. This can be used for things like automatic gain
+    /// compensation, MIDI learn, or macro controls driven by the audio signal.
+    ///
+    /// If the wrapper's output parameter queue is full, then the change will be dropped and a
+    /// debug assertion will be triggered.
+    fn set_parameter<T: Param>(&self, param: &T, value: T::Plain) {
+        let ptr = param.as_ptr();
+        let normalized = param.preview_normalized(value);
+        unsafe { self.raw_set_parameter_normalized(ptr, normalized) };
+    }
+
+    /// Set a parameter to an already normalized value from the audio thread. Works exactly the
+    /// same as [`set_parameter()`][Self::set_parameter()], but this does not perform any snapping.
+    fn set_parameter_normalized<T: Param>(&self, param: &T, normalized: f32) {
+        let ptr = param.as_ptr();
+        unsafe { self.raw_set_parameter_normalized(ptr, normalized) };
+    }
+
+    /// Set a parameter to an already normalized value from the audio thread. Use
+    /// [`set_parameter()`][Self::set_parameter()] instead for a safe, user friendly API.
+    ///
+    /// # Safety
+    ///
+    /// The implementing function still needs to check if `param` actually exists. This function is
+    /// mostly marked as unsafe for API reasons.
+    unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32);
 }
 
 /// Callbacks the plugin can make when the user interacts with its GUI such as updating parameter
@@ -131,6 +209,50 @@ pub trait GuiContext: Send + Sync + 'static {
     /// host. If the plugin is currently processing audio, then the parameter values will be
     /// restored at the end of the current processing cycle.
     fn set_state(&self, state: PluginState);
+
+    /// Inform the host that the plugin's state has changed in a way the host could not have
+    /// noticed by itself, so it can prompt the user to save the project. Changes to automatable
+    /// parameters don't need this, but changes to non-automatable parameters, `#[persist]` fields
+    /// modified using [`PersistentField::set()`][crate::param::internals::PersistentField::set()],
+    /// changes made through [`record_field_changes()`][Self::record_field_changes()], and state
+    /// restored using [`set_state()`][Self::set_state()] will already call this automatically.
+    /// Any other changes to `#[persist]` fields need to be followed by a call to this function.
+    fn mark_dirty(&self);
+
+    /// Undo the most recent parameter gesture or persistent field change made from the editor.
+    /// Parameter changes are sent to the host just like any other parameter change. Returns
+    /// `false` if there was nothing to undo or if the plugin has not enabled the undo history
+    /// using [`Plugin::UNDO_HISTORY_SIZE`][crate::prelude::Plugin::UNDO_HISTORY_SIZE].
+    ///
+    /// Parameter gestures are recorded automatically when using a [`ParamSetter`]. Changes to
+    /// `#[persist]` fields need to be made inside of a
+    /// [`record_field_changes()`][Self::record_field_changes()] call to be recorded. Restoring the
+    /// plugin's state using [`set_state()`][Self::set_state()] clears the history.
+    //
+    // TODO: CLAP's undo extension is not part of the CLAP version we currently support, so for now
+    //       the history is managed entirely by the plugin
+    fn undo(&self) -> bool;
+
+    /// Redo the most recently undone change. Returns `false` if there was nothing to redo.
+    fn redo(&self) -> bool;
+
+    /// Whether there is a change that can be undone using [`undo()`][Self::undo()]. Useful for
+    /// enabling or disabling undo buttons in the GUI.
+    fn can_undo(&self) -> bool;
+
+    /// Whether there is a change that can be redone using [`redo()`][Self::redo()].
+    fn can_redo(&self) -> bool;
+
+    /// Inform the undo history that the plugin is about to modify one or more of its persistent
+    /// fields. Use [`record_field_changes()`][Self::record_field_changes()] instead for a more
+    /// convenient API.
+    fn raw_begin_field_changes(&self);
+
+    /// Inform the undo history that the plugin has finished modifying its persistent fields. Any
+    /// field that changed since the matching
+    /// [`raw_begin_field_changes()`][Self::raw_begin_field_changes()] call will be added to the
+    /// undo history as a single step.
+    fn raw_end_field_changes(&self);
 }
 
 /// Information about the plugin's transport. Depending on the plugin API and the host not all
@@ -188,6 +310,24 @@ pub struct Transport {
     pub(crate) loop_range_beats: Option<(f64, f64)>,
 }
 
+/// A single change to a parameter's value in the middle of a buffer. These are only produced when
+/// [`Plugin::QUEUED_AUTOMATION`][crate::prelude::Plugin::QUEUED_AUTOMATION] is enabled. See
+/// [`ProcessContext::param_changes()`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParamChange {
+    /// The sample index within the buffer at which the new value takes effect.
+    pub timing: u32,
+    /// The parameter's new normalized value. This does not include any monophonic modulation.
+    pub normalized_value: f32,
+}
+
+/// An iterator over a parameter's [`ParamChange`]s that yields `(timing, plain_value)` pairs.
+/// Returned by [`ProcessContext::param_changes()`].
+pub struct ParamChanges<'a, T: Param> {
+    param: &'a T,
+    changes: std::slice::Iter<'a, ParamChange>,
+}
+
 /// A convenience helper for setting parameter values. Any changes made here will be broadcasted to
 /// the host and reflected in the plugin's [`Params`][crate::param::internals::Params] object. These
 /// functions should only be called from the main thread.
@@ -214,6 +354,43 @@ impl Display for PluginApi {
     }
 }
 
+impl<T: Param> Iterator for ParamChanges<'_, T> {
+    type Item = (u32, T::Plain);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.changes.next().map(|change| {
+            (
+                change.timing,
+                self.param.preview_plain(change.normalized_value),
+            )
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.changes.size_hint()
+    }
+}
+
+impl dyn GuiContext {
+    /// Modify one or more of the plugin's `#[persist]` fields in `f` so the changes can be undone
+    /// using [`undo()`][Self::undo()]. All changes made within `f` are recorded as a single step.
+    /// Afterwards the plugin's state will be [marked as dirty][Self::mark_dirty()] so the host
+    /// knows the project has unsaved changes.
+    ///
+    /// ```ignore
+    /// context.record_field_changes(|| *params.wavetable.write() = new_wavetable);
+    /// ```
+    pub fn record_field_changes<T>(&self, f: impl FnOnce() -> T) -> T {
+        self.raw_begin_field_changes();
+        let result = f();
+        self.raw_end_field_changes();
+        self.mark_dirty();
+
+        result
+    }
+}
+
 // TODO: These conversions have not really been tested yet, there might be an error in there somewhere
 impl Transport {
     /// Initialize the transport struct without any information.
@@ -2,6 +2,7 @@
 
 use std::sync::Weak;
 
+mod background_thread;
 #[cfg(all(target_family = "unix", not(target_os = "macos")))]
 mod linux;
 // For now, also use the Linux event loop on macOS so it at least compiles
@@ -18,6 +19,9 @@ pub(crate) use self::linux::LinuxEventLoop as OsEventLoop;
 #[cfg(target_os = "windows")]
 pub(crate) use self::windows::WindowsEventLoop as OsEventLoop;
 
+pub use self::background_thread::AsyncExecutor;
+pub(crate) use self::background_thread::BackgroundThread;
+
 pub(crate) const TASK_QUEUE_CAPACITY: usize = 512;
 
 /// A trait describing the functionality of the platform-specific event loop that can execute tasks
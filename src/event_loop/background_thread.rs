@@ -0,0 +1,149 @@
+//! A dedicated worker thread for running the plugin's background tasks. Unlike the
+//! [`EventLoop`][super::EventLoop], tasks posted here are never executed on the calling thread.
+//! This is used to offload expensive work from the audio thread or the GUI thread.
+
+use crossbeam::channel;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::nih_log;
+use crate::plugin::Plugin;
+
+/// A worker thread that executes tasks of type `T` using an executor function. Posting a task is
+/// realtime safe since the task queue is preallocated.
+pub(crate) struct BackgroundThread<T> {
+    /// The thread running the executor function. This is wrapped in an `Option` so the thread can
+    /// be taken out of it and joined when this struct gets dropped.
+    worker_thread: Option<JoinHandle<()>>,
+    /// A channel for waking up the worker thread and having it perform one of the tasks from
+    /// [`Message`].
+    worker_thread_channel: channel::Sender<Message<T>>,
+    /// Set when this struct gets dropped. The worker thread checks this after every task, so it can
+    /// still shut down when the task queue is too full to send a [`Message::Shutdown`].
+    shutdown_requested: Arc<AtomicBool>,
+}
+
+/// A handle for running tasks on the plugin's background thread from the editor. This is passed to
+/// [`Plugin::editor()`], and it can be cloned and moved into the GUI as needed. Tasks are typed
+/// using [`Plugin::BackgroundTask`], so scheduling a task of the wrong type won't compile.
+pub struct AsyncExecutor<P: Plugin> {
+    worker_thread_channel: channel::Sender<Message<P::BackgroundTask>>,
+}
+
+/// A message for communicating with the worker thread.
+enum Message<T> {
+    /// A new task for the worker thread to execute.
+    Task(T),
+    /// Shut down the worker thread.
+    Shutdown,
+}
+
+impl<P: Plugin> Clone for AsyncExecutor<P> {
+    fn clone(&self) -> Self {
+        Self {
+            worker_thread_channel: self.worker_thread_channel.clone(),
+        }
+    }
+}
+
+impl<P: Plugin> AsyncExecutor<P> {
+    /// Run a task on the wrapper's background thread using the function returned by
+    /// [`Plugin::task_executor()`]. This will not block. If the task queue is full then the task
+    /// will be dropped and a debug assertion will be triggered.
+    pub fn execute(&self, task: P::BackgroundTask) {
+        schedule(&self.worker_thread_channel, task);
+    }
+}
+
+impl<T: Send + 'static> BackgroundThread<T> {
+    /// Spawn a new worker thread that passes all incoming tasks to `executor`. This should be
+    /// called when constructing the wrapper.
+    pub fn new_and_spawn(executor: Box<dyn Fn(T) + Send + Sync>) -> Self {
+        let (sender, receiver) = channel::bounded(super::TASK_QUEUE_CAPACITY);
+        let shutdown_requested = Arc::new(AtomicBool::new(false));
+
+        Self {
+            // With our drop implementation we guarentee that this thread never outlives this struct
+            worker_thread: Some({
+                let shutdown_requested = shutdown_requested.clone();
+                thread::Builder::new()
+                    .name(String::from("bg-worker"))
+                    .spawn(move || worker_thread(receiver, executor, shutdown_requested))
+                    .expect("Could not spawn background worker thread")
+            }),
+            worker_thread_channel: sender,
+            shutdown_requested,
+        }
+    }
+
+    /// Post a task to the worker thread's task queue. This function needs to be callable at any
+    /// time without blocking or allocating. If the task queue is full then the task will be dropped
+    /// and a debug assertion will be triggered.
+    pub fn schedule(&self, task: T) {
+        schedule(&self.worker_thread_channel, task);
+    }
+
+    /// Create a handle the editor can use to post tasks to this thread.
+    pub fn async_executor<P>(&self) -> AsyncExecutor<P>
+    where
+        P: Plugin<BackgroundTask = T>,
+    {
+        AsyncExecutor {
+            worker_thread_channel: self.worker_thread_channel.clone(),
+        }
+    }
+}
+
+impl<T> Drop for BackgroundThread<T> {
+    fn drop(&mut self) {
+        // If the queue is full then the worker thread is still busy, and it will see this flag
+        // after finishing its current task
+        self.shutdown_requested.store(true, Ordering::SeqCst);
+        let _ = self.worker_thread_channel.try_send(Message::Shutdown);
+
+        if let Some(join_handle) = self.worker_thread.take() {
+            join_handle
+                .join()
+                .expect("Background worker thread panicked");
+        }
+    }
+}
+
+/// Try to post a task to a worker thread's task queue, triggering a debug assertion if the queue is
+/// full.
+fn schedule<T>(sender: &channel::Sender<Message<T>>, task: T) {
+    let task_posted = sender.try_send(Message::Task(task)).is_ok();
+    nih_debug_assert!(
+        task_posted,
+        "The background task queue is full, dropping task..."
+    );
+}
+
+/// The worker thread used in [`BackgroundThread`] that executes incoming tasks using the plugin's
+/// task executor.
+fn worker_thread<T>(
+    receiver: channel::Receiver<Message<T>>,
+    executor: Box<dyn Fn(T) + Send + Sync>,
+    shutdown_requested: Arc<AtomicBool>,
+) where
+    T: Send,
+{
+    loop {
+        if shutdown_requested.load(Ordering::SeqCst) {
+            return;
+        }
+
+        match receiver.recv() {
+            Ok(Message::Task(task)) => executor(task),
+            Ok(Message::Shutdown) => return,
+            Err(err) => {
+                nih_log!(
+                    "Background worker thread got disconnected unexpectedly, shutting down: {}",
+                    err
+                );
+                return;
+            }
+        }
+    }
+}
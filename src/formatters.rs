@@ -109,7 +109,9 @@ pub fn s2v_f32_hz_then_khz() -> Arc<dyn Fn(&str) -> Option<f32> + Send + Sync> {
 }
 
 /// Format an order/power of two. Useful in conjunction with [`s2v_i32_power_of_two()`] to limit
-/// integer parameter ranges to be only powers of two.
+/// integer parameter ranges to be only powers of two. Consider using an
+/// [`IntRange::Table`][crate::param::range::IntRange::Table] containing the actual values instead,
+/// since that also stores the actual values in the plugin's state.
 pub fn v2s_i32_power_of_two() -> Arc<dyn Fn(i32) -> String + Send + Sync> {
     Arc::new(|value| format!("{}", 1 << value))
 }
@@ -106,4 +106,5 @@ mod event_loop;
 pub mod midi;
 pub mod param;
 pub mod plugin;
+pub mod preset;
 pub mod wrapper;
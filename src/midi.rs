@@ -2,6 +2,19 @@
 
 pub use midi_consts::channel_event::control_change;
 
+/// The maximum size of a MIDI SysEx message in bytes that can be stored directly inside of a
+/// [`SysExMessage`], including the leading `0xF0` and the trailing `0xF7` bytes. This is kept small
+/// so the message doesn't make every other note event larger, which is enough for short messages
+/// like MIDI Machine Control, MIDI Time Code, and single note tuning changes. Longer messages like
+/// patch dumps are stored in the wrapper's SysEx storage instead, see [`SYSEX_STORAGE_SIZE`].
+pub const SYSEX_BUFFER_SIZE: usize = 16;
+
+/// The number of bytes each wrapper preallocates for SysEx messages longer than
+/// [`SYSEX_BUFFER_SIZE`]. This storage is shared between the incoming and outgoing messages of a
+/// single processing cycle, so a plugin can receive and send up to this many bytes worth of long
+/// messages per cycle without any allocations. Messages that don't fit anymore will be dropped.
+pub const SYSEX_STORAGE_SIZE: usize = 64 * 1024;
+
 /// Determines which note events a plugin receives.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum MidiConfig {
@@ -10,9 +23,9 @@ pub enum MidiConfig {
     /// The plugin receives note on/off events, pressure, and potentially a couple standardized
     /// expression types depending on the plugin standard and host.
     Basic,
-    /// The plugin receives full MIDI CCs as well as pitch bend information. For VST3 plugins this
-    /// involves adding 130*16 parameters to bind to the the 128 MIDI CCs, pitch bend, and channel
-    /// pressure.
+    /// The plugin receives full MIDI CCs as well as pitch bend information and SysEx messages. For
+    /// VST3 plugins this involves adding 130*16 parameters to bind to the the 128 MIDI CCs, pitch
+    /// bend, and channel pressure.
     MidiCCs,
 }
 
@@ -28,6 +41,11 @@ pub enum NoteEvent {
     /// A note on event, available on [`MidiConfig::Basic`] and up.
     NoteOn {
         timing: u32,
+        /// A unique identifier for this note, if the host provides one. These are CLAP's and
+        /// VST3's note IDs. Using these to refer to a note is required when allowing overlapping
+        /// voices, and when using polyphonic modulation. This can be left as `None` when sending
+        /// note events to the host.
+        voice_id: Option<i32>,
         /// The note's channel, from 0 to 16.
         channel: u8,
         /// The note's MIDI key number, from 0 to 127.
@@ -39,6 +57,9 @@ pub enum NoteEvent {
     /// A note off event, available on [`MidiConfig::Basic`] and up.
     NoteOff {
         timing: u32,
+        /// The note's voice ID, if available. This matches the `voice_id` from the note's
+        /// [`NoteOn`][Self::NoteOn] event.
+        voice_id: Option<i32>,
         /// The note's channel, from 0 to 16.
         channel: u8,
         /// The note's MIDI key number, from 0 to 127.
@@ -47,6 +68,55 @@ pub enum NoteEvent {
         /// 127 levels available in MIDI.
         velocity: f32,
     },
+    /// Sent by the plugin to the host to indicate that a voice has ended. This should be sent when
+    /// a voice terminates, either because its release stage has finished or because it was
+    /// stolen, so the host can release any polyphonic modulation it was applying to the voice.
+    /// Only supported by CLAP plugins with [`MidiConfig::Basic`] note input or higher. The event is
+    /// ignored by the other wrappers.
+    VoiceTerminated {
+        timing: u32,
+        /// The ID of the voice that has ended, if the host assigned one. This should be the
+        /// `voice_id` from the voice's [`NoteOn`][Self::NoteOn] event.
+        voice_id: Option<i32>,
+        /// The voice's channel, from 0 to 16.
+        channel: u8,
+        /// The voice's MIDI key number, from 0 to 127.
+        note: u8,
+    },
+    /// A polyphonic modulation event, available on [`MidiConfig::Basic`] and up. These are only
+    /// sent for parameters that have polyphonic modulation enabled using
+    /// [`FloatParam::with_poly_modulation()`][crate::prelude::FloatParam::with_poly_modulation()],
+    /// and only CLAP hosts support this. The modulation applies to a single voice, and it stays
+    /// active until the voice ends or until the host sends a new modulation event for it. The
+    /// parameter's own value does not change, so the modulated value can be computed using
+    /// [`FloatParam::preview_modulated()`][crate::prelude::FloatParam::preview_modulated()].
+    PolyModulation {
+        timing: u32,
+        /// The ID of the voice this modulation applies to. This matches the `voice_id` from the
+        /// voice's [`NoteOn`][Self::NoteOn] event.
+        voice_id: i32,
+        /// The hash of the ID of the parameter this modulation belongs to. This can be compared to
+        /// [`util::hash_param_id()`][crate::util::hash_param_id()] applied to the parameter's ID
+        /// to find out which parameter is being modulated.
+        param_hash: u32,
+        /// The normalized offset to apply on top of the parameter's unmodulated normalized value
+        /// for this voice. This replaces the previous offset for this voice.
+        normalized_offset: f32,
+    },
+    /// A companion to [`PolyModulation`][Self::PolyModulation], sent when a polyphonically
+    /// modulated parameter's monophonic value changes because of automation or monophonic
+    /// modulation. This contains the parameter's new normalized value at this sample, so voices
+    /// with an active polyphonic modulation offset can recompute their values with sample
+    /// accuracy. The parameter itself will have already been updated when the plugin receives
+    /// this event.
+    MonoAutomation {
+        timing: u32,
+        /// The hash of the ID of the parameter that has changed. See
+        /// [`PolyModulation`][Self::PolyModulation] for more information.
+        param_hash: u32,
+        /// The parameter's new normalized value, including any monophonic modulation.
+        normalized_value: f32,
+    },
     /// A polyphonic note pressure/aftertouch event, available on [`MidiConfig::Basic`] and up. Not
     /// all hosts may support polyphonic aftertouch.
     ///
@@ -54,9 +124,14 @@ pub enum NoteEvent {
     ///
     /// When implementing MPE support you should use MIDI channel pressure instead as polyphonic key
     /// pressure + MPE is undefined as per the MPE specification. Or as a more generic catch all,
-    /// you may manually combine the polyphonic key pressure and MPE channel pressure.
+    /// you may manually combine the polyphonic key pressure and MPE channel pressure. When
+    /// [`Plugin::MPE_INPUT`][crate::prelude::Plugin::MPE_INPUT] is enabled, the wrapper will
+    /// convert MPE channel pressure to these events for you.
     PolyPressure {
         timing: u32,
+        /// The note's voice ID, if available. This matches the `voice_id` from the note's
+        /// [`NoteOn`][Self::NoteOn] event.
+        voice_id: Option<i32>,
         /// The note's channel, from 0 to 16.
         channel: u8,
         /// The note's MIDI key number, from 0 to 127.
@@ -68,6 +143,9 @@ pub enum NoteEvent {
     /// support these expressions.
     PolyVolume {
         timing: u32,
+        /// The note's voice ID, if available. This matches the `voice_id` from the note's
+        /// [`NoteOn`][Self::NoteOn] event.
+        voice_id: Option<i32>,
         /// The note's channel, from 0 to 16.
         channel: u8,
         /// The note's MIDI key number, from 0 to 127.
@@ -79,6 +157,9 @@ pub enum NoteEvent {
     /// support these expressions.
     PolyPan {
         timing: u32,
+        /// The note's voice ID, if available. This matches the `voice_id` from the note's
+        /// [`NoteOn`][Self::NoteOn] event.
+        voice_id: Option<i32>,
         /// The note's channel, from 0 to 16.
         channel: u8,
         /// The note's MIDI key number, from 0 to 127.
@@ -91,6 +172,9 @@ pub enum NoteEvent {
     /// these expressions.
     PolyTuning {
         timing: u32,
+        /// The note's voice ID, if available. This matches the `voice_id` from the note's
+        /// [`NoteOn`][Self::NoteOn] event.
+        voice_id: Option<i32>,
         /// The note's channel, from 0 to 16.
         channel: u8,
         /// The note's MIDI key number, from 0 to 127.
@@ -102,6 +186,9 @@ pub enum NoteEvent {
     /// these expressions.
     PolyVibrato {
         timing: u32,
+        /// The note's voice ID, if available. This matches the `voice_id` from the note's
+        /// [`NoteOn`][Self::NoteOn] event.
+        voice_id: Option<i32>,
         /// The note's channel, from 0 to 16.
         channel: u8,
         /// The note's MIDI key number, from 0 to 127.
@@ -113,6 +200,9 @@ pub enum NoteEvent {
     /// [`MidiConfig::Basic`] and up. Not all hosts may support these expressions.
     PolyExpression {
         timing: u32,
+        /// The note's voice ID, if available. This matches the `voice_id` from the note's
+        /// [`NoteOn`][Self::NoteOn] event.
+        voice_id: Option<i32>,
         /// The note's channel, from 0 to 16.
         channel: u8,
         /// The note's MIDI key number, from 0 to 127.
@@ -124,6 +214,9 @@ pub enum NoteEvent {
     /// these expressions.
     PolyBrightness {
         timing: u32,
+        /// The note's voice ID, if available. This matches the `voice_id` from the note's
+        /// [`NoteOn`][Self::NoteOn] event.
+        voice_id: Option<i32>,
         /// The note's channel, from 0 to 16.
         channel: u8,
         /// The note's MIDI key number, from 0 to 127.
@@ -163,6 +256,43 @@ pub enum NoteEvent {
         /// The CC's value, normalized to `[0, 1]`. Multiply by 127 to get the original raw value.
         value: f32,
     },
+    /// A MIDI SysEx message, available on [`MidiConfig::MidiCCs`] and up. The message's data can
+    /// be read using
+    /// [`ProcessContext::sysex_data()`][crate::prelude::ProcessContext::sysex_data()].
+    MidiSysEx {
+        timing: u32,
+        /// The raw message, including the leading `0xF0` and trailing `0xF7` bytes.
+        message: SysExMessage,
+    },
+}
+
+/// A MIDI SysEx message. Messages up to [`SYSEX_BUFFER_SIZE`] bytes are stored inline, while longer
+/// messages are a handle to the wrapper's preallocated SysEx storage. Those handles are only valid
+/// during the processing cycle the message was received or created in. The message's data can be
+/// read with [`ProcessContext::sysex_data()`][crate::prelude::ProcessContext::sysex_data()], and
+/// messages of any length can be created with
+/// [`ProcessContext::new_sysex_message()`][crate::prelude::ProcessContext::new_sysex_message()].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SysExMessage {
+    repr: SysExRepr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SysExRepr {
+    Inline {
+        buffer: [u8; SYSEX_BUFFER_SIZE],
+        len: u8,
+    },
+    Stored {
+        offset: u32,
+        len: u32,
+    },
+}
+
+/// The wrapper's preallocated storage for long SysEx messages, see [`SYSEX_STORAGE_SIZE`]. This
+/// should be cleared at the start of every processing cycle.
+pub(crate) struct SysExStorage {
+    data: Vec<u8>,
 }
 
 impl NoteEvent {
@@ -171,6 +301,9 @@ impl NoteEvent {
         match &self {
             NoteEvent::NoteOn { timing, .. } => *timing,
             NoteEvent::NoteOff { timing, .. } => *timing,
+            NoteEvent::VoiceTerminated { timing, .. } => *timing,
+            NoteEvent::PolyModulation { timing, .. } => *timing,
+            NoteEvent::MonoAutomation { timing, .. } => *timing,
             NoteEvent::PolyPressure { timing, .. } => *timing,
             NoteEvent::PolyVolume { timing, .. } => *timing,
             NoteEvent::PolyPan { timing, .. } => *timing,
@@ -181,6 +314,7 @@ impl NoteEvent {
             NoteEvent::MidiChannelPressure { timing, .. } => *timing,
             NoteEvent::MidiPitchBend { timing, .. } => *timing,
             NoteEvent::MidiCC { timing, .. } => *timing,
+            NoteEvent::MidiSysEx { timing, .. } => *timing,
         }
     }
 
@@ -190,6 +324,9 @@ impl NoteEvent {
         match self {
             NoteEvent::NoteOn { timing, .. } => *timing -= samples,
             NoteEvent::NoteOff { timing, .. } => *timing -= samples,
+            NoteEvent::VoiceTerminated { timing, .. } => *timing -= samples,
+            NoteEvent::PolyModulation { timing, .. } => *timing -= samples,
+            NoteEvent::MonoAutomation { timing, .. } => *timing -= samples,
             NoteEvent::PolyPressure { timing, .. } => *timing -= samples,
             NoteEvent::PolyVolume { timing, .. } => *timing -= samples,
             NoteEvent::PolyPan { timing, .. } => *timing -= samples,
@@ -200,6 +337,155 @@ impl NoteEvent {
             NoteEvent::MidiChannelPressure { timing, .. } => *timing -= samples,
             NoteEvent::MidiPitchBend { timing, .. } => *timing -= samples,
             NoteEvent::MidiCC { timing, .. } => *timing -= samples,
+            NoteEvent::MidiSysEx { timing, .. } => *timing -= samples,
+        }
+    }
+}
+
+impl SysExMessage {
+    /// Copy a short raw SysEx message into a new inline message. Returns `None` if the message is
+    /// longer than [`SYSEX_BUFFER_SIZE`] bytes. Use
+    /// [`ProcessContext::new_sysex_message()`][crate::prelude::ProcessContext::new_sysex_message()]
+    /// for longer messages.
+    pub fn from_slice(data: &[u8]) -> Option<Self> {
+        if data.len() > SYSEX_BUFFER_SIZE {
+            return None;
+        }
+
+        let mut buffer = [0; SYSEX_BUFFER_SIZE];
+        buffer[..data.len()].copy_from_slice(data);
+
+        Some(Self {
+            repr: SysExRepr::Inline {
+                buffer,
+                len: data.len() as u8,
+            },
+        })
+    }
+
+    /// The length of the message in bytes.
+    pub fn len(&self) -> usize {
+        match self.repr {
+            SysExRepr::Inline { len, .. } => len as usize,
+            SysExRepr::Stored { len, .. } => len as usize,
+        }
+    }
+
+    /// Returns true if the message does not contain any data.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl SysExStorage {
+    pub fn new() -> Self {
+        Self {
+            data: Vec::with_capacity(SYSEX_STORAGE_SIZE),
+        }
+    }
+
+    /// Invalidate all stored messages. This should be called at the start of a processing cycle,
+    /// once the host is no longer reading the previous cycle's output messages.
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    /// Create a message from raw SysEx data. Short messages are stored inline, and longer messages
+    /// are copied to the storage. Returns `None` if there is not enough space left.
+    pub fn push(&mut self, data: &[u8]) -> Option<SysExMessage> {
+        if let Some(message) = SysExMessage::from_slice(data) {
+            return Some(message);
+        }
+
+        self.copy_to_storage(data).map(|offset| SysExMessage {
+            repr: SysExRepr::Stored {
+                offset: offset as u32,
+                len: data.len() as u32,
+            },
+        })
+    }
+
+    /// Get a message's data. Messages from a previous processing cycle will either be empty or
+    /// contain the data of some other message.
+    pub fn get<'a>(&'a self, message: &'a SysExMessage) -> &'a [u8] {
+        match &message.repr {
+            SysExRepr::Inline { buffer, len } => &buffer[..*len as usize],
+            SysExRepr::Stored { offset, len } => self
+                .data
+                .get(*offset as usize..(*offset + *len) as usize)
+                .unwrap_or(&[]),
+        }
+    }
+
+    /// Get a message's data so that it stays at the same address until the next call to
+    /// [`clear()`][Self::clear()]. This copies inline messages to the storage. Plugin APIs only
+    /// pass pointers to SysEx data, so this is needed to output messages to the host. Returns
+    /// `None` if there is not enough space left.
+    pub fn get_pinned(&mut self, message: &SysExMessage) -> Option<&[u8]> {
+        match &message.repr {
+            SysExRepr::Inline { buffer, len } => {
+                let data = &buffer[..*len as usize];
+                self.copy_to_storage(data)
+                    .map(|offset| &self.data[offset..offset + data.len()])
+            }
+            SysExRepr::Stored { offset, len } => {
+                self.data.get(*offset as usize..(*offset + *len) as usize)
+            }
+        }
+    }
+
+    /// Append data to the storage without ever reallocating. Returns the data's offset.
+    fn copy_to_storage(&mut self, data: &[u8]) -> Option<usize> {
+        if data.len() > self.data.capacity() - self.data.len() {
+            return None;
         }
+
+        let offset = self.data.len();
+        self.data.extend_from_slice(data);
+
+        Some(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn note_event_size() {
+        // SysEx messages are stored inline, so they should not blow up the size of every other event
+        assert!(std::mem::size_of::<NoteEvent>() <= 32);
+    }
+
+    #[test]
+    fn sysex_from_slice() {
+        let data = [0xf0, 0x7e, 0x7f, 0x06, 0x01, 0xf7];
+        let message = SysExMessage::from_slice(&data).unwrap();
+        assert_eq!(SysExStorage::new().get(&message), data);
+        assert_eq!(message.len(), data.len());
+
+        assert!(SysExMessage::from_slice(&[0xf0; SYSEX_BUFFER_SIZE]).is_some());
+        assert!(SysExMessage::from_slice(&[0xf0; SYSEX_BUFFER_SIZE + 1]).is_none());
+    }
+
+    #[test]
+    fn sysex_storage() {
+        let mut storage = SysExStorage::new();
+        let short = storage.push(&[0xf0, 0x01, 0xf7]).unwrap();
+        let dump = vec![0x42; 1024];
+        let long = storage.push(&dump).unwrap();
+        assert_eq!(long.len(), dump.len());
+        assert_eq!(storage.get(&long), dump);
+
+        // Inline messages are copied to the storage so they can be passed to the host
+        let pinned = storage.get_pinned(&short).unwrap().as_ptr();
+        assert_eq!(pinned, storage.data[dump.len()..].as_ptr());
+
+        // The storage never grows
+        assert!(storage.push(&vec![0x42; SYSEX_STORAGE_SIZE]).is_none());
+        assert_eq!(storage.data.capacity(), SYSEX_STORAGE_SIZE);
+
+        storage.clear();
+        assert!(storage.get(&long).is_empty());
     }
 }
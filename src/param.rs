@@ -60,12 +60,31 @@ pub trait Param: Display {
     /// Get the unit label for this parameter, if any.
     fn unit(&self) -> &'static str;
 
-    /// Get the unnormalized value for this parameter.
+    /// Get the unnormalized value for this parameter. This includes any (monophonic) modulation
+    /// coming from the host, see [`unmodulated_plain_value()`][Self::unmodulated_plain_value()].
     fn plain_value(&self) -> Self::Plain;
 
-    /// Get the normalized `[0, 1]` value for this parameter.
+    /// Get the normalized `[0, 1]` value for this parameter. This includes any (monophonic)
+    /// modulation coming from the host, see
+    /// [`unmodulated_normalized_value()`][Self::unmodulated_normalized_value()].
     fn normalized_value(&self) -> f32;
 
+    /// The same as [`plain_value()`][Self::plain_value()]. This exists to make it explicit that
+    /// the value includes the host's modulation when it's used alongside
+    /// [`unmodulated_plain_value()`][Self::unmodulated_plain_value()].
+    #[inline]
+    fn modulated_plain_value(&self) -> Self::Plain {
+        self.plain_value()
+    }
+
+    /// The same as [`normalized_value()`][Self::normalized_value()]. This exists to make it
+    /// explicit that the value includes the host's modulation when it's used alongside
+    /// [`unmodulated_normalized_value()`][Self::unmodulated_normalized_value()].
+    #[inline]
+    fn modulated_normalized_value(&self) -> f32 {
+        self.normalized_value()
+    }
+
     /// Get the unnormalized value for this parameter before any (monophonic) modulation coming from
     /// the host has been applied. If the host is not currently modulating this parameter than this
     /// will be the same as [`plain_value()`][Self::plain_value()]. This may be useful for
@@ -80,6 +99,17 @@ pub trait Param: Display {
     /// Bitwig Studio use modulation.
     fn unmodulated_normalized_value(&self) -> f32;
 
+    /// Get the (monophonic) modulation offset the host currently applies to this parameter, in
+    /// normalized units. This is 0.0 when the parameter is not being modulated. The modulated
+    /// normalized value is the unmodulated normalized value plus this offset, clamped to `[0, 1]`.
+    /// Only the unmodulated value is stored in the plugin's state, and the parameter's smoother
+    /// always targets the modulated value. Parameters that don't support modulation can use the
+    /// default implementation.
+    #[inline]
+    fn modulation_offset(&self) -> f32 {
+        0.0
+    }
+
     /// Get the unnormalized default value for this parameter.
     fn default_plain_value(&self) -> Self::Plain;
 
@@ -141,6 +171,13 @@ pub trait Param: Display {
     /// Flags to control the parameter's behavior. See [`ParamFlags`].
     fn flags(&self) -> ParamFlags;
 
+    /// Whether polyphonic modulation has been enabled for this parameter. Only these parameters
+    /// can be modulated per voice. See [`FloatParam::with_poly_modulation()`] for more
+    /// information.
+    fn poly_modulated(&self) -> bool {
+        false
+    }
+
     /// Internal implementation detail for implementing [`Params`][internals::Params]. This should
     /// not be used directly.
     fn as_ptr(&self) -> internals::ParamPtr;
@@ -4,6 +4,7 @@ use std::fmt::Display;
 use std::sync::Arc;
 
 use super::internals::ParamPtr;
+use super::smoothing::{Smoother, SmoothingStyle};
 use super::{Param, ParamFlags, ParamMut};
 
 /// A simple boolean parameter.
@@ -25,6 +26,9 @@ pub struct BoolParam {
     modulation_offset: f32,
     /// The field's default value.
     default: bool,
+    /// An optional smoother for crossfading between the old and the new value when the parameter
+    /// changes. See [`SmoothingStyle::Crossfade`].
+    pub smoothed: Smoother<bool>,
 
     /// Flags to control the parameter's behavior. See [`ParamFlags`].
     flags: ParamFlags,
@@ -85,6 +89,11 @@ impl Param for BoolParam {
         self.unmodulated_normalized_value
     }
 
+    #[inline]
+    fn modulation_offset(&self) -> f32 {
+        self.modulation_offset
+    }
+
     #[inline]
     fn default_plain_value(&self) -> Self::Plain {
         self.default
@@ -133,7 +142,9 @@ impl Param for BoolParam {
         normalized > 0.5
     }
 
-    fn initialize_block_smoother(&mut self, _max_block_size: usize) {}
+    fn initialize_block_smoother(&mut self, max_block_size: usize) {
+        self.smoothed.initialize_block_smoother(max_block_size);
+    }
 
     fn flags(&self) -> ParamFlags {
         self.flags
@@ -182,8 +193,13 @@ impl ParamMut for BoolParam {
         self.set_normalized_value(self.unmodulated_normalized_value);
     }
 
-    fn update_smoother(&mut self, _sample_rate: f32, _init: bool) {
-        // Can't really smooth a binary parameter now can you
+    fn update_smoother(&mut self, sample_rate: f32, reset: bool) {
+        // Binary parameters can't be interpolated, but they can be crossfaded
+        if reset {
+            self.smoothed.reset(self.value);
+        } else {
+            self.smoothed.set_target(sample_rate, self.value);
+        }
     }
 }
 
@@ -198,6 +214,7 @@ impl BoolParam {
             unmodulated_normalized_value: if default { 1.0 } else { 0.0 },
             modulation_offset: 0.0,
             default,
+            smoothed: Smoother::none(),
 
             flags: ParamFlags::default(),
             value_changed: None,
@@ -208,6 +225,19 @@ impl BoolParam {
         }
     }
 
+    /// Set up a smoother that crossfades between the old and the new value when this parameter
+    /// changes. This should be used with [`SmoothingStyle::Crossfade`], since the other styles
+    /// would need to interpolate between `false` and `true`.
+    pub fn with_smoother(mut self, style: SmoothingStyle) -> Self {
+        nih_debug_assert!(
+            matches!(style, SmoothingStyle::None | SmoothingStyle::Crossfade(_)),
+            "Boolean parameters can only use crossfade smoothing"
+        );
+
+        self.smoothed = Smoother::new(style);
+        self
+    }
+
     /// Run a callback whenever this parameter's value changes. The argument passed to this function
     /// is the parameter's new value. This should not do anything expensive as it may be called
     /// multiple times in rapid succession, and it can be run from both the GUI and the audio
@@ -6,6 +6,7 @@ use std::sync::Arc;
 
 use super::internals::ParamPtr;
 use super::range::IntRange;
+use super::smoothing::{Smoother, SmoothingStyle};
 use super::{IntParam, Param, ParamFlags, ParamMut};
 
 // Re-export the derive macro
@@ -108,6 +109,11 @@ impl<T: Enum + PartialEq> Param for EnumParam<T> {
         self.inner.unmodulated_normalized_value()
     }
 
+    #[inline]
+    fn modulation_offset(&self) -> f32 {
+        self.inner.modulation_offset()
+    }
+
     #[inline]
     fn default_plain_value(&self) -> Self::Plain {
         T::from_index(self.inner.default_plain_value() as usize)
@@ -191,6 +197,11 @@ impl Param for EnumParamInner {
         self.inner.unmodulated_normalized_value()
     }
 
+    #[inline]
+    fn modulation_offset(&self) -> f32 {
+        self.inner.modulation_offset()
+    }
+
     fn step_count(&self) -> Option<usize> {
         Some(self.len() - 1)
     }
@@ -295,6 +306,19 @@ impl<T: Enum + PartialEq + 'static> EnumParam<T> {
         }
     }
 
+    /// Set up a smoother that crossfades between the old and the new variant when this parameter
+    /// changes. This should be used with [`SmoothingStyle::Crossfade`], since the other styles
+    /// would interpolate between variant indices. See [`smoothed()`][Self::smoothed()].
+    pub fn with_smoother(mut self, style: SmoothingStyle) -> Self {
+        nih_debug_assert!(
+            matches!(style, SmoothingStyle::None | SmoothingStyle::Crossfade(_)),
+            "Enum parameters can only use crossfade smoothing"
+        );
+
+        self.inner.inner = self.inner.inner.with_smoother(style);
+        self
+    }
+
     /// Run a callback whenever this parameter's value changes. The argument passed to this function
     /// is the parameter's new value. This should not do anything expensive as it may be called
     /// multiple times in rapid succession, and it can be run from both the GUI and the audio
@@ -333,6 +357,17 @@ impl<T: Enum + PartialEq + 'static> EnumParam<T> {
     pub fn value(&self) -> T {
         self.plain_value()
     }
+
+    /// The smoother set up with [`with_smoother()`][Self::with_smoother()]. This smoother produces
+    /// variant indices. Use [`Crossfade::map()`][super::smoothing::Crossfade::map()] together with
+    /// [`Enum::from_index()`] to convert those back to enum variants:
+    ///
+    /// ```ignore
+    /// let crossfade = params.filter_type.smoothed().next_crossfade().map(|idx| FilterType::from_index(idx as usize));
+    /// ```
+    pub fn smoothed(&self) -> &Smoother<i32> {
+        &self.inner.inner.smoothed
+    }
 }
 
 impl EnumParamInner {
@@ -43,6 +43,9 @@ pub struct FloatParam {
 
     /// Flags to control the parameter's behavior. See [`ParamFlags`].
     flags: ParamFlags,
+    /// Whether the host can modulate this parameter per voice. See
+    /// [`with_poly_modulation()`][Self::with_poly_modulation()].
+    poly_modulated: bool,
     /// Optional callback for listening to value changes. The argument passed to this function is
     /// the parameter's new **plain** value. This should not do anything expensive as it may be
     /// called multiple times in rapid succession.
@@ -119,6 +122,11 @@ impl Param for FloatParam {
         self.unmodulated_normalized_value
     }
 
+    #[inline]
+    fn modulation_offset(&self) -> f32 {
+        self.modulation_offset
+    }
+
     #[inline]
     fn default_plain_value(&self) -> Self::Plain {
         self.default
@@ -199,6 +207,10 @@ impl Param for FloatParam {
         self.flags
     }
 
+    fn poly_modulated(&self) -> bool {
+        self.poly_modulated
+    }
+
     fn as_ptr(&self) -> ParamPtr {
         ParamPtr::FloatParam(self as *const _ as *mut _)
     }
@@ -255,6 +267,8 @@ impl FloatParam {
     /// Build a new [`FloatParam`]. Use the other associated functions to modify the behavior of the
     /// parameter.
     pub fn new(name: impl Into<String>, default: f32, range: FloatRange) -> Self {
+        range.assert_piecewise_invariants();
+
         Self {
             value: default,
             normalized_value: range.normalize(default),
@@ -265,6 +279,7 @@ impl FloatParam {
             smoothed: Smoother::none(),
 
             flags: ParamFlags::default(),
+            poly_modulated: false,
             value_changed: None,
 
             range,
@@ -281,13 +296,11 @@ impl FloatParam {
     pub fn with_smoother(mut self, style: SmoothingStyle) -> Self {
         // Logarithmic smoothing will cause problems if the range goes through zero since then you
         // end up multplying by zero
-        let goes_through_zero = match (&style, &self.range) {
-            (
-                SmoothingStyle::Logarithmic(_),
-                FloatRange::Linear { min, max }
-                | FloatRange::Skewed { min, max, .. }
-                | FloatRange::SymmetricalSkewed { min, max, .. },
-            ) => *min == 0.0 || *max == 0.0 || min.signum() != max.signum(),
+        let goes_through_zero = match &style {
+            SmoothingStyle::Logarithmic(_) => {
+                let (min, max) = (self.range.min(), self.range.max());
+                min == 0.0 || max == 0.0 || min.signum() != max.signum()
+            }
             _ => false,
         };
         nih_debug_assert!(
@@ -299,6 +312,38 @@ impl FloatParam {
         self
     }
 
+    /// Enable polyphonic modulation for this parameter. The parameter is identified in
+    /// [`NoteEvent::PolyModulation`] and [`NoteEvent::MonoAutomation`] events by its `param_hash`,
+    /// which is the hash of the parameter's ID as computed by
+    /// [`util::hash_param_id()`][crate::util::hash_param_id()]. Hosts that support polyphonic
+    /// modulation, like Bitwig Studio through CLAP, will then send per-voice modulation offsets for
+    /// this parameter instead of modulating the parameter's value directly. Use
+    /// [`preview_modulated()`][Self::preview_modulated()] to compute a voice's modulated value.
+    ///
+    /// Modulation is only supported per note ID. Modulating all voices for a specific key or
+    /// channel is not supported since the plugin would need to keep track of the voices for every
+    /// key and channel itself, so hosts will fall back to monophonic modulation in that case.
+    ///
+    /// Voices should be terminated with a [`NoteEvent::VoiceTerminated`] event once they have
+    /// finished playing so the host knows it can stop modulating them.
+    ///
+    /// [`NoteEvent::PolyModulation`]: crate::midi::NoteEvent::PolyModulation
+    /// [`NoteEvent::MonoAutomation`]: crate::midi::NoteEvent::MonoAutomation
+    /// [`NoteEvent::VoiceTerminated`]: crate::midi::NoteEvent::VoiceTerminated
+    pub fn with_poly_modulation(mut self) -> Self {
+        self.poly_modulated = true;
+        self
+    }
+
+    /// Get the plain value this parameter would have for a voice with the normalized polyphonic
+    /// modulation offset from a [`NoteEvent::PolyModulation`] event. The offset is applied on top
+    /// of the parameter's current normalized value, including any monophonic modulation.
+    ///
+    /// [`NoteEvent::PolyModulation`]: crate::midi::NoteEvent::PolyModulation
+    pub fn preview_modulated(&self, normalized_offset: f32) -> f32 {
+        self.preview_plain((self.normalized_value + normalized_offset).clamp(0.0, 1.0))
+    }
+
     /// Run a callback whenever this parameter's value changes. The argument passed to this function
     /// is the parameter's new value. This should not do anything expensive as it may be called
     /// multiple times in rapid succession, and it can be run from both the GUI and the audio
@@ -390,3 +435,60 @@ fn decimals_from_step_size(step_size: f32) -> usize {
 
     num_digits as usize
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_param() -> FloatParam {
+        FloatParam::new(
+            "Foo",
+            5.0,
+            FloatRange::Linear {
+                min: 0.0,
+                max: 10.0,
+            },
+        )
+    }
+
+    #[test]
+    fn modulation_offset() {
+        let mut param = make_param();
+        assert_eq!(param.modulation_offset(), 0.0);
+
+        param.modulate_value(0.25);
+        assert_eq!(param.modulation_offset(), 0.25);
+        assert_eq!(param.unmodulated_plain_value(), 5.0);
+        assert_eq!(param.plain_value(), 7.5);
+        assert_eq!(param.modulated_plain_value(), 7.5);
+
+        // The offset is kept when the value changes, and the modulated value is clamped
+        param.set_plain_value(7.5);
+        param.modulate_value(0.5);
+        assert_eq!(param.unmodulated_normalized_value(), 0.75);
+        assert_eq!(param.normalized_value(), 1.0);
+        assert_eq!(param.plain_value(), 10.0);
+
+        param.modulate_value(0.0);
+        assert_eq!(param.modulation_offset(), 0.0);
+        assert_eq!(param.plain_value(), 7.5);
+    }
+
+    #[test]
+    fn preview_modulated() {
+        let mut param = make_param().with_poly_modulation();
+        assert!(param.poly_modulated());
+        assert_eq!(param.preview_modulated(0.0), 5.0);
+        assert_eq!(param.preview_modulated(0.25), 7.5);
+
+        // The modulated value is clamped to the parameter's range
+        assert_eq!(param.preview_modulated(1.0), 10.0);
+        assert_eq!(param.preview_modulated(-1.0), 0.0);
+
+        // Polyphonic modulation is applied on top of the monophonic modulation
+        param.modulate_value(0.25);
+        assert_eq!(param.preview_modulated(0.0), 7.5);
+        assert_eq!(param.preview_modulated(-0.5), 2.5);
+        assert_eq!(param.preview_modulated(0.5), 10.0);
+    }
+}
@@ -111,6 +111,11 @@ impl Param for IntParam {
         self.unmodulated_normalized_value
     }
 
+    #[inline]
+    fn modulation_offset(&self) -> f32 {
+        self.modulation_offset
+    }
+
     #[inline]
     fn default_plain_value(&self) -> Self::Plain {
         self.default
@@ -121,11 +126,27 @@ impl Param for IntParam {
     }
 
     fn previous_step(&self, from: Self::Plain) -> Self::Plain {
-        (from - 1).clamp(self.range.min(), self.range.max())
+        match &self.range {
+            IntRange::Linear { .. } => (from - 1).clamp(self.range.min(), self.range.max()),
+            // Steps through tables and reversed ranges are taken in the normalized domain, just
+            // like with `FloatParam`
+            IntRange::Reversed(_) | IntRange::Table(_) => {
+                let step_size = (self.range.step_count().max(1) as f32).recip();
+                self.range
+                    .unnormalize(self.range.normalize(from) - step_size)
+            }
+        }
     }
 
     fn next_step(&self, from: Self::Plain) -> Self::Plain {
-        (from + 1).clamp(self.range.min(), self.range.max())
+        match &self.range {
+            IntRange::Linear { .. } => (from + 1).clamp(self.range.min(), self.range.max()),
+            IntRange::Reversed(_) | IntRange::Table(_) => {
+                let step_size = (self.range.step_count().max(1) as f32).recip();
+                self.range
+                    .unnormalize(self.range.normalize(from) + step_size)
+            }
+        }
     }
 
     fn normalized_value_to_string(&self, normalized: f32, include_unit: bool) -> String {
@@ -245,9 +266,10 @@ impl IntParam {
     pub fn with_smoother(mut self, style: SmoothingStyle) -> Self {
         // Logarithmic smoothing will cause problems if the range goes through zero since then you
         // end up multplying by zero
-        let goes_through_zero = match (&style, &self.range) {
-            (SmoothingStyle::Logarithmic(_), IntRange::Linear { min, max }) => {
-                *min == 0 || *max == 0 || min.signum() != max.signum()
+        let goes_through_zero = match &style {
+            SmoothingStyle::Logarithmic(_) => {
+                let (min, max) = (self.range.min(), self.range.max());
+                min == 0 || max == 0 || min.signum() != max.signum()
             }
             _ => false,
         };
@@ -326,3 +348,36 @@ impl IntParam {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_steps() {
+        let param = IntParam::new("Foo", 0, IntRange::Linear { min: -10, max: 10 });
+        assert_eq!(param.previous_step(0), -1);
+        assert_eq!(param.next_step(0), 1);
+        assert_eq!(param.previous_step(-10), -10);
+        assert_eq!(param.next_step(10), 10);
+    }
+
+    #[test]
+    fn reversed_steps() {
+        let param = IntParam::new(
+            "Foo",
+            0,
+            IntRange::Reversed(Box::new(IntRange::Linear { min: -10, max: 10 })),
+        );
+        assert_eq!(param.previous_step(0), 1);
+        assert_eq!(param.next_step(0), -1);
+    }
+
+    #[test]
+    fn table_steps() {
+        let param = IntParam::new("Foo", 256, IntRange::Table(&[64, 128, 256, 512, 1024]));
+        assert_eq!(param.previous_step(256), 128);
+        assert_eq!(param.next_step(256), 512);
+        assert_eq!(param.next_step(1024), 1024);
+    }
+}
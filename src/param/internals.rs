@@ -1,6 +1,8 @@
 //! Implementation details for the parameter management.
 
+use std::cell::Cell;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use super::{Param, ParamFlags, ParamMut};
 
@@ -25,11 +27,19 @@ pub use serde_json::to_string as serialize_field;
 ///
 /// And finally when deriving this trait, it is also possible to inherit the parameters from other
 /// `Params` objects by adding the `#[nested = "Group Name"]` attribute to those fields. These
-/// groups will be displayed as a tree-like structure if your DAW supports it. Parameter IDs and
-/// persisting keys still need to be **unique** when usting nested parameter structs. This currently
-/// has the following caveats:
+/// groups will be displayed as a tree-like structure if your DAW supports it. The longer
+/// `#[nested(group = "Group Name", id = "stable", collapsed, hidden)]` form can be used to attach
+/// additional [`ParamGroup`] metadata to the group. Repeating parameter blocks like EQ bands can be
+/// stored in an array or a `Vec` and annotated with `#[nested(array, group = "Band {}")]`. Each
+/// element then gets its own group with the `{}` replaced by the element's one-based index, and the
+/// element's parameter IDs and persist keys are suffixed with `_1`, `_2`, and so on. A `Vec`'s
+/// length should not change after the `Params` object has been created.
+///
+/// Parameter IDs and persisting keys need to be **unique**, including those coming from nested
+/// parameter structs. Duplicates within a single struct are rejected at compile time, while
+/// duplicates across nested structs cause a panic at runtime in debug builds. This currently has
+/// the following caveat:
 ///
-/// - Enforcing that parameter IDs and persist keys are unique does not work across nested structs.
 /// - Deserializing persisted fields will give false positives about fields not existing.
 ///
 /// Take a look at the example gain plugin to see how this should be used.
@@ -56,6 +66,14 @@ pub unsafe trait Params: 'static + Send + Sync {
     /// fine to be able to support custom reusable Params implemnetations.
     fn param_map(&self) -> Vec<(String, ParamPtr, String)>;
 
+    /// Metadata for the groups used in [`param_map()`][Self::param_map()]. Groups that are not
+    /// listed here use the defaults from [`ParamGroup::new()`]. The derive macro creates an entry
+    /// for every `#[nested]` field, and it prefixes the paths and IDs of the nested struct's own
+    /// groups with that field's group.
+    fn param_groups(&self) -> Vec<ParamGroup> {
+        Vec::new()
+    }
+
     /// Serialize all fields marked with `#[persist = "stable_name"]` into a hash map containing
     /// JSON-representations of those fields so they can be written to the plugin's state and
     /// recalled later. This uses [`serialize_field()`] under the hood.
@@ -72,6 +90,26 @@ pub unsafe trait Params: 'static + Send + Sync {
     fn deserialize_fields(&self, serialized: &HashMap<String, String>) {}
 }
 
+/// Metadata for a group of parameters. Groups are identified by their slash delimited path as used in
+/// [`Params::param_map()`], which is also used as the parameter's module path in CLAP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamGroup {
+    /// The group's slash delimited path, e.g. `"Filter/Envelope"`.
+    pub path: String,
+    /// A stable identifier for the group. This is used to derive the VST3 unit ID, so setting this
+    /// explicitly lets you rename a group without breaking existing projects. Defaults to the
+    /// group's path. The unit IDs are hashes of these IDs, so every group's ID needs to hash to a
+    /// unique value that is also different from the VST3 root unit's ID 0. The VST3 wrapper panics
+    /// with a message naming the offending groups if this is not the case.
+    pub id: String,
+    /// A hint that this group should initially be displayed collapsed. None of the plugin APIs
+    /// support this, so this is only useful for plugin GUIs that display parameter groups.
+    pub collapsed: bool,
+    /// Hide all parameters in this group and its subgroups from the host's generic UI, as if they
+    /// all had the [`ParamFlags::HIDDEN`] flag set.
+    pub hidden: bool,
+}
+
 /// Internal pointers to parameters. This is an implementation detail used by the wrappers for type
 /// erasure.
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
@@ -93,8 +131,15 @@ unsafe impl Sync for ParamPtr {}
 /// The functinoality needed for persisting a field to the plugin's state, and for restoring values
 /// when loading old state.
 ///
-/// TODO: Modifying these fields (or any parameter for that matter) should mark the plugin's state
-///       as dirty.
+/// Setting a field using [`set()`][Self::set()] marks the plugin's state as dirty so the host knows
+/// the project has unsaved changes. Because the fields don't know which plugin instance they belong
+/// to, this only increments a shared counter. The wrappers check for changes to their own fields on
+/// the main thread after the next process call. Changes made through other means, like writing to
+/// a field's `RwLock` directly, or changes that should be picked up immediately, need to be made
+/// from within
+/// [`GuiContext::record_field_changes()`][crate::prelude::GuiContext::record_field_changes()] or
+/// be followed by a call to [`GuiContext::mark_dirty()`][crate::prelude::GuiContext::mark_dirty()]
+/// instead.
 pub trait PersistentField<'a, T>: Send + Sync
 where
     T: serde::Serialize + serde::Deserialize<'a>,
@@ -145,11 +190,56 @@ macro_rules! param_ptr_forward(
     };
 );
 
+impl ParamGroup {
+    /// Create metadata for the group with the specified slash delimited path. Use the other
+    /// associated functions to modify the group's behavior.
+    pub fn new(path: impl Into<String>) -> Self {
+        let path = path.into();
+
+        Self {
+            id: path.clone(),
+            path,
+            collapsed: false,
+            hidden: false,
+        }
+    }
+
+    /// Use a stable ID for this group that is independent of the group's name. See
+    /// [`id`][Self::id].
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    /// Hint that this group should initially be displayed collapsed.
+    pub fn collapsed(mut self) -> Self {
+        self.collapsed = true;
+        self
+    }
+
+    /// Hide all parameters in this group and its subgroups from the host's generic UI.
+    pub fn hide(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    /// Whether a parameter in the group with the path `group_path` is hidden by this group. This is
+    /// the case if the path refers to this group or to one of its subgroups.
+    pub fn hides(&self, group_path: &str) -> bool {
+        self.hidden
+            && group_path
+                .strip_prefix(self.path.as_str())
+                .map(|rest| rest.is_empty() || rest.starts_with('/'))
+                .unwrap_or(false)
+    }
+}
+
 impl ParamPtr {
     param_ptr_forward!(pub unsafe fn name(&self) -> &str);
     param_ptr_forward!(pub unsafe fn unit(&self) -> &'static str);
     param_ptr_forward!(pub unsafe fn normalized_value(&self) -> f32);
     param_ptr_forward!(pub unsafe fn unmodulated_normalized_value(&self) -> f32);
+    param_ptr_forward!(pub unsafe fn modulation_offset(&self) -> f32);
     param_ptr_forward!(pub unsafe fn default_normalized_value(&self) -> f32);
     param_ptr_forward!(pub unsafe fn step_count(&self) -> Option<usize>);
     param_ptr_forward!(pub unsafe fn previous_normalized_step(&self, from: f32) -> f32);
@@ -158,6 +248,7 @@ impl ParamPtr {
     param_ptr_forward!(pub unsafe fn normalized_value_to_string(&self, normalized: f32, include_unit: bool) -> String);
     param_ptr_forward!(pub unsafe fn string_to_normalized_value(&self, string: &str) -> Option<f32>);
     param_ptr_forward!(pub unsafe fn flags(&self) -> ParamFlags);
+    param_ptr_forward!(pub unsafe fn poly_modulated(&self) -> bool);
 
     param_ptr_forward!(pub(crate) unsafe fn set_normalized_value(&self, normalized: f32));
     param_ptr_forward!(pub(crate) unsafe fn modulate_value(&self, modulation_offset: f32));
@@ -254,12 +345,47 @@ impl ParamPtr {
     }
 }
 
+thread_local! {
+    /// Whether persistent fields are currently being restored on this thread using
+    /// [`restore_fields()`]. The field generation is not incremented in that case.
+    static RESTORING_FIELDS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Incremented whenever any plugin instance's [`PersistentField`]s are modified using
+/// [`PersistentField::set()`]. See [`field_generation()`].
+static FIELD_GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// A counter that changes whenever any [`PersistentField`] is modified using
+/// [`PersistentField::set()`]. This is cheap to poll from the audio thread, so the wrappers use it
+/// to decide when to check whether their own persistent fields have changed.
+pub(crate) fn field_generation() -> usize {
+    FIELD_GENERATION.load(Ordering::Relaxed)
+}
+
+/// Restore a [`Params`] object's persistent fields using [`Params::deserialize_fields()`] without
+/// incrementing the field generation. Restoring the plugin's state should not count as a change to
+/// that state.
+pub(crate) fn restore_fields(params: &dyn Params, serialized: &HashMap<String, String>) {
+    RESTORING_FIELDS.with(|restoring| restoring.set(true));
+    params.deserialize_fields(serialized);
+    RESTORING_FIELDS.with(|restoring| restoring.set(false));
+}
+
+/// Increment the field generation. This is called from [`PersistentField::set()`], so it must stay
+/// realtime safe.
+fn bump_field_generation() {
+    if !RESTORING_FIELDS.with(|restoring| restoring.get()) {
+        FIELD_GENERATION.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
 impl<'a, T> PersistentField<'a, T> for std::sync::RwLock<T>
 where
     T: serde::Serialize + serde::Deserialize<'a> + Send + Sync,
 {
     fn set(&self, new_value: T) {
         *self.write().expect("Poisoned RwLock on write") = new_value;
+        bump_field_generation();
     }
     fn map<F, R>(&self, f: F) -> R
     where
@@ -275,6 +401,7 @@ where
 {
     fn set(&self, new_value: T) {
         *self.write() = new_value;
+        bump_field_generation();
     }
     fn map<F, R>(&self, f: F) -> R
     where
@@ -290,6 +417,7 @@ where
 {
     fn set(&self, new_value: T) {
         *self.lock().expect("Poisoned Mutex") = new_value;
+        bump_field_generation();
     }
     fn map<F, R>(&self, f: F) -> R
     where
@@ -307,6 +435,7 @@ macro_rules! impl_persistent_field_parking_lot_mutex {
         {
             fn set(&self, new_value: T) {
                 *self.lock() = new_value;
+                bump_field_generation();
             }
             fn map<F, R>(&self, f: F) -> R
             where
@@ -324,6 +453,7 @@ where
 {
     fn set(&self, new_value: T) {
         *self.borrow_mut() = new_value;
+        bump_field_generation();
     }
     fn map<F, R>(&self, f: F) -> R
     where
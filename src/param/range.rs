@@ -20,15 +20,43 @@ pub enum FloatRange {
         factor: f32,
         center: f32,
     },
+    /// The same as the wrapped range, but with the normalized values flipped so the maximum value
+    /// is at the start of the range. Useful for things like ratio controls where turning the knob
+    /// clockwise should decrease the value.
+    Reversed(Box<FloatRange>),
+    /// A user-supplied distribution. `normalize` maps a plain value to the `[0, 1]` range and
+    /// `unnormalize` maps it back again. Both functions must be monotonic and each other's inverse.
+    /// The range's minimum and maximum values are taken from `unnormalize(0.0)` and
+    /// `unnormalize(1.0)`. This can be used for log-Hz scales and other curves that cannot be
+    /// expressed with the other variants.
+    Custom {
+        normalize: fn(f32) -> f32,
+        unnormalize: fn(f32) -> f32,
+    },
+    /// A piecewise linear range defined by a list of `(normalized, plain)` breakpoints. Values
+    /// between two breakpoints are interpolated linearly. This makes it possible to place musically
+    /// meaningful values like 1 kHz at fixed knob positions. The breakpoints must be sorted, both
+    /// the normalized and the plain values must be strictly increasing, and the first and last
+    /// breakpoints must have normalized values of 0.0 and 1.0. These invariants are checked with
+    /// debug assertions when the range is passed to [`FloatParam::new()`][super::FloatParam::new()].
+    Piecewise(&'static [(f32, f32)]),
 }
 
-/// A distribution for an integer parameter's range. All range endpoints are inclusive. Only linear
-/// ranges are supported for integers since hosts expect discrete parameters to have a fixed step
-/// size.
+/// A distribution for an integer parameter's range. All range endpoints are inclusive. Hosts expect
+/// discrete parameters to have a fixed step size, so every variant maps its values to equally
+/// spaced normalized values.
 #[derive(Debug)]
 pub enum IntRange {
     /// The values are uniformly distributed between `min` and `max`.
     Linear { min: i32, max: i32 },
+    /// The same as the wrapped range, but with the normalized values flipped so the maximum value
+    /// is at the start of the range.
+    Reversed(Box<IntRange>),
+    /// A fixed list of values, where every value takes up a single step. The values must be sorted
+    /// in ascending order and they must not contain duplicates. This can be used for things like
+    /// power of two FFT window sizes where every step doubles the value. Plain values that are not
+    /// in the table are normalized to the closest value in the table.
+    Table(&'static [i32]),
 }
 
 impl FloatRange {
@@ -71,6 +99,14 @@ impl FloatRange {
                     (1.0 - inverted_scaled_proportion.powf(*factor)) * 0.5
                 }
             }
+            FloatRange::Reversed(range) => 1.0 - range.normalize(plain),
+            FloatRange::Custom { normalize, .. } => normalize(plain),
+            FloatRange::Piecewise(breakpoints) => interpolate_breakpoints(
+                breakpoints
+                    .iter()
+                    .map(|(normalized, plain)| (*plain, *normalized)),
+                plain,
+            ),
         }
         .clamp(0.0, 1.0)
     }
@@ -103,6 +139,11 @@ impl FloatRange {
 
                 (skewed_proportion * (max - min)) + min
             }
+            FloatRange::Reversed(range) => range.unnormalize(1.0 - normalized),
+            FloatRange::Custom { unnormalize, .. } => unnormalize(normalized),
+            FloatRange::Piecewise(breakpoints) => {
+                interpolate_breakpoints(breakpoints.iter().copied(), normalized)
+            }
         }
     }
 
@@ -112,6 +153,11 @@ impl FloatRange {
             FloatRange::Linear { min, .. }
             | FloatRange::Skewed { min, .. }
             | FloatRange::SymmetricalSkewed { min, .. } => *min,
+            FloatRange::Reversed(range) => range.min(),
+            FloatRange::Custom { unnormalize, .. } => unnormalize(0.0).min(unnormalize(1.0)),
+            FloatRange::Piecewise(breakpoints) => {
+                breakpoints.first().map(|(_, plain)| *plain).unwrap_or(0.0)
+            }
         }
     }
 
@@ -121,19 +167,68 @@ impl FloatRange {
             FloatRange::Linear { max, .. }
             | FloatRange::Skewed { max, .. }
             | FloatRange::SymmetricalSkewed { max, .. } => *max,
+            FloatRange::Reversed(range) => range.max(),
+            FloatRange::Custom { unnormalize, .. } => unnormalize(0.0).max(unnormalize(1.0)),
+            FloatRange::Piecewise(breakpoints) => {
+                breakpoints.last().map(|(_, plain)| *plain).unwrap_or(0.0)
+            }
         }
     }
 
     /// Snap a vlue to a step size, clamping to the minimum and maximum value of the range.
     pub fn snap_to_step(&self, value: f32, step_size: f32) -> f32 {
-        let (min, max) = match &self {
-            FloatRange::Linear { min, max } => (min, max),
-            FloatRange::Skewed { min, max, .. } => (min, max),
-            FloatRange::SymmetricalSkewed { min, max, .. } => (min, max),
-        };
+        ((value / step_size).round() * step_size).clamp(self.min(), self.max())
+    }
+
+    /// Trigger a debug assertion failure if this is a [`FloatRange::Piecewise`] range, or a reversed
+    /// piecewise range, with breakpoints that don't uphold that variant's invariants.
+    pub(crate) fn assert_piecewise_invariants(&self) {
+        match self {
+            FloatRange::Reversed(range) => range.assert_piecewise_invariants(),
+            FloatRange::Piecewise(breakpoints) => {
+                nih_debug_assert!(
+                    breakpoints.len() >= 2,
+                    "Piecewise ranges need at least two breakpoints"
+                );
+                nih_debug_assert!(
+                    breakpoints
+                        .windows(2)
+                        .all(|pair| pair[1].0 > pair[0].0 && pair[1].1 > pair[0].1),
+                    "The breakpoints of a piecewise range need to be sorted, with strictly \
+                     increasing normalized and plain values"
+                );
+                nih_debug_assert!(
+                    breakpoints.first().map(|(normalized, _)| *normalized) == Some(0.0)
+                        && breakpoints.last().map(|(normalized, _)| *normalized) == Some(1.0),
+                    "The first and last breakpoints of a piecewise range need to have normalized \
+                     values of 0.0 and 1.0"
+                );
+            }
+            _ => (),
+        }
+    }
+}
 
-        ((value / step_size).round() * step_size).clamp(*min, *max)
+/// Linearly interpolate `x` between a list of `(x, y)` breakpoints sorted by their `x` values.
+/// Values outside of the breakpoints' range are clamped to the first or last breakpoint's `y`
+/// value. This is used for [`FloatRange::Piecewise`] in both directions.
+fn interpolate_breakpoints(breakpoints: impl Iterator<Item = (f32, f32)>, x: f32) -> f32 {
+    let mut previous: Option<(f32, f32)> = None;
+    for (x1, y1) in breakpoints {
+        match previous {
+            None if x <= x1 => return y1,
+            Some((x0, y0)) if x <= x1 => {
+                return if x1 > x0 {
+                    y0 + ((x - x0) / (x1 - x0) * (y1 - y0))
+                } else {
+                    y1
+                };
+            }
+            _ => previous = Some((x1, y1)),
+        }
     }
+
+    previous.map(|(_, y)| y).unwrap_or(0.0)
 }
 
 impl IntRange {
@@ -142,6 +237,14 @@ impl IntRange {
     pub fn normalize(&self, plain: i32) -> f32 {
         match &self {
             IntRange::Linear { min, max } => (plain - min) as f32 / (max - min) as f32,
+            IntRange::Reversed(range) => 1.0 - range.normalize(plain),
+            IntRange::Table(values) => {
+                if values.len() > 1 {
+                    closest_table_index(values, plain) as f32 / (values.len() - 1) as f32
+                } else {
+                    0.0
+                }
+            }
         }
         .clamp(0.0, 1.0)
     }
@@ -152,6 +255,11 @@ impl IntRange {
         let normalized = normalized.clamp(0.0, 1.0);
         match &self {
             IntRange::Linear { min, max } => (normalized * (max - min) as f32).round() as i32 + min,
+            IntRange::Reversed(range) => range.unnormalize(1.0 - normalized),
+            IntRange::Table(values) => {
+                let index = (normalized * values.len().saturating_sub(1) as f32).round() as usize;
+                values.get(index).copied().unwrap_or(0)
+            }
         }
     }
 
@@ -159,6 +267,8 @@ impl IntRange {
     pub fn min(&self) -> i32 {
         match self {
             IntRange::Linear { min, .. } => *min,
+            IntRange::Reversed(range) => range.min(),
+            IntRange::Table(values) => values.first().copied().unwrap_or(0),
         }
     }
 
@@ -166,6 +276,8 @@ impl IntRange {
     pub fn max(&self) -> i32 {
         match self {
             IntRange::Linear { max, .. } => *max,
+            IntRange::Reversed(range) => range.max(),
+            IntRange::Table(values) => values.last().copied().unwrap_or(0),
         }
     }
 
@@ -173,6 +285,26 @@ impl IntRange {
     pub fn step_count(&self) -> usize {
         match self {
             IntRange::Linear { min, max } => (max - min) as usize,
+            IntRange::Reversed(range) => range.step_count(),
+            IntRange::Table(values) => values.len().saturating_sub(1),
+        }
+    }
+}
+
+/// Find the index of the value in a sorted [`IntRange::Table`] that's closest to `value`.
+fn closest_table_index(values: &[i32], value: i32) -> usize {
+    match values.binary_search(&value) {
+        Ok(index) => index,
+        Err(0) => 0,
+        Err(index) if index >= values.len() => values.len() - 1,
+        Err(index) => {
+            // These are computed as 64-bit integers so tables spanning most of the `i32` range
+            // can't overflow
+            if value as i64 - values[index - 1] as i64 <= values[index] as i64 - value as i64 {
+                index - 1
+            } else {
+                index
+            }
         }
     }
 }
@@ -209,6 +341,30 @@ mod tests {
         }
     }
 
+    fn make_reversed_int_range() -> IntRange {
+        IntRange::Reversed(Box::new(make_linear_int_range()))
+    }
+
+    fn make_table_int_range() -> IntRange {
+        IntRange::Table(&[64, 128, 256, 512, 1024])
+    }
+
+    fn make_reversed_float_range() -> FloatRange {
+        FloatRange::Reversed(Box::new(make_linear_float_range()))
+    }
+
+    fn make_custom_float_range() -> FloatRange {
+        // A logarithmic 20 Hz to 20 kHz range
+        FloatRange::Custom {
+            normalize: |plain| (plain / 20.0).log10() / 3.0,
+            unnormalize: |normalized| 20.0 * 10.0f32.powf(normalized * 3.0),
+        }
+    }
+
+    fn make_piecewise_float_range() -> FloatRange {
+        FloatRange::Piecewise(&[(0.0, 20.0), (0.5, 1000.0), (1.0, 20000.0)])
+    }
+
     #[test]
     fn step_size() {
         // These are weird step sizes, but if it works here then it will work for anything
@@ -306,4 +462,148 @@ mod tests {
             assert_eq!(range.unnormalize(0.951801), 17.5);
         }
     }
+
+    mod reversed {
+        use super::*;
+
+        #[test]
+        fn range_normalize_float() {
+            let range = make_reversed_float_range();
+            assert_eq!(range.normalize(17.5), 0.25);
+        }
+
+        #[test]
+        fn range_unnormalize_float() {
+            let range = make_reversed_float_range();
+            assert_eq!(range.unnormalize(0.25), 17.5);
+        }
+
+        #[test]
+        fn range_bounds() {
+            let range = make_reversed_float_range();
+            assert_eq!(range.min(), 10.0);
+            assert_eq!(range.max(), 20.0);
+        }
+
+        #[test]
+        fn range_normalize_int() {
+            let range = make_reversed_int_range();
+            assert_eq!(range.normalize(-5), 0.75);
+        }
+
+        #[test]
+        fn range_unnormalize_int() {
+            let range = make_reversed_int_range();
+            assert_eq!(range.unnormalize(0.75), -5);
+            assert_eq!(range.step_count(), 20);
+        }
+    }
+
+    mod table {
+        use super::*;
+
+        #[test]
+        fn range_normalize_int() {
+            let range = make_table_int_range();
+            assert_eq!(range.normalize(64), 0.0);
+            assert_eq!(range.normalize(256), 0.5);
+            assert_eq!(range.normalize(1024), 1.0);
+        }
+
+        #[test]
+        fn range_normalize_int_closest() {
+            let range = make_table_int_range();
+            assert_eq!(range.normalize(300), 0.5);
+            assert_eq!(range.normalize(500), 0.75);
+            assert_eq!(range.normalize(1), 0.0);
+            assert_eq!(range.normalize(4096), 1.0);
+        }
+
+        #[test]
+        fn range_normalize_int_closest_extremes() {
+            let range = IntRange::Table(&[i32::MIN, 0, i32::MAX]);
+            assert_eq!(range.normalize(i32::MIN + 1), 0.0);
+            assert_eq!(range.normalize(i32::MAX - 1), 1.0);
+        }
+
+        #[test]
+        fn range_unnormalize_int() {
+            let range = make_table_int_range();
+            assert_eq!(range.unnormalize(0.25), 128);
+            assert_eq!(range.unnormalize(0.3), 128);
+            assert_eq!(range.unnormalize(1.0), 1024);
+        }
+
+        #[test]
+        fn range_bounds() {
+            let range = make_table_int_range();
+            assert_eq!(range.min(), 64);
+            assert_eq!(range.max(), 1024);
+            assert_eq!(range.step_count(), 4);
+        }
+    }
+
+    mod custom {
+        use super::*;
+
+        #[test]
+        fn range_normalize_float() {
+            let range = make_custom_float_range();
+            assert!((range.normalize(632.4555) - 0.5).abs() < 1e-5);
+        }
+
+        #[test]
+        fn range_unnormalize_float() {
+            let range = make_custom_float_range();
+            assert!((range.unnormalize(1.0 / 3.0) - 200.0).abs() < 1e-3);
+        }
+
+        #[test]
+        fn range_bounds() {
+            let range = make_custom_float_range();
+            assert_eq!(range.min(), 20.0);
+            assert!((range.max() - 20000.0).abs() < 1e-1);
+        }
+    }
+
+    mod piecewise {
+        use super::*;
+
+        #[test]
+        fn range_normalize_float() {
+            let range = make_piecewise_float_range();
+            assert_eq!(range.normalize(1000.0), 0.5);
+            assert_eq!(range.normalize(510.0), 0.25);
+            assert_eq!(range.normalize(10500.0), 0.75);
+        }
+
+        #[test]
+        fn range_unnormalize_float() {
+            let range = make_piecewise_float_range();
+            assert_eq!(range.unnormalize(0.5), 1000.0);
+            assert_eq!(range.unnormalize(0.25), 510.0);
+            assert_eq!(range.unnormalize(0.75), 10500.0);
+        }
+
+        #[test]
+        fn range_clamping() {
+            let range = make_piecewise_float_range();
+            assert_eq!(range.normalize(10.0), 0.0);
+            assert_eq!(range.normalize(30000.0), 1.0);
+            assert_eq!(range.unnormalize(1.5), 20000.0);
+        }
+
+        /// Ranges that don't cover the entire normalized range are not valid, but they should still
+        /// be clamped to the outermost breakpoints instead of extrapolating.
+        #[test]
+        fn range_clamping_partial_coverage() {
+            let range = FloatRange::Piecewise(&[(0.25, 100.0), (0.75, 200.0)]);
+            assert_eq!(range.normalize(50.0), 0.25);
+            assert_eq!(range.normalize(150.0), 0.5);
+            assert_eq!(range.normalize(300.0), 0.75);
+            assert_eq!(range.unnormalize(0.0), 100.0);
+            assert_eq!(range.unnormalize(0.5), 150.0);
+            assert_eq!(range.unnormalize(1.0), 200.0);
+        }
+    }
 }
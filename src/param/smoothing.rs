@@ -29,6 +29,47 @@ pub enum SmoothingStyle {
     /// This results in a smoother transition, with the caveat being that there will be a tiny jump
     /// at the end. Unlike the `Logarithmic` option, this does support crossing the zero value.
     Exponential(f32),
+    /// Don't interpolate between values, but instead crossfade from the previous value to the new
+    /// value over this many milliseconds. This is meant for discrete parameters like
+    /// [`EnumParam`][super::EnumParam]s and [`BoolParam`][super::BoolParam]s where interpolating
+    /// would not make any sense, like when switching between filter types. The smoother's regular
+    /// values jump to the new value immediately, while [`Smoother::next_crossfade()`] and
+    /// [`Smoother::next_crossfade_block()`] also provide the previous value and a fade coefficient
+    /// so the outputs for both values can be mixed. Changing the value again during a crossfade
+    /// starts a new crossfade from the value that's currently the loudest, at that value's current
+    /// gain. The quieter value is then replaced by the new value at the same gain, so only that
+    /// value changes abruptly.
+    Crossfade(f32),
+    /// Smooth parameter changes using a raised cosine curve, starting out slow, speeding up in the
+    /// middle, and then slowing down again before the target value is reached. The target value
+    /// will be reached in exactly this many milliseconds. This avoids the abrupt changes in slope
+    /// at the start and the end of `Linear` smoothing, which makes it a good fit for gain changes.
+    SCurve(f32),
+    /// A classic single-pole lowpass filter with this time constant in milliseconds. After one time
+    /// constant the value will have moved about 63.2% of the way towards the target. Unlike the
+    /// `Exponential` option this doesn't snap to the target value after a fixed amount of time.
+    /// Instead the smoother keeps approaching the target until the remaining distance is negligible
+    /// relative to the size of the change, at which point it snaps to the target value.
+    OnePole(f32),
+    /// Limit the rate of change to this many units per second, in terms of the parameter's plain
+    /// values. Smaller changes will thus be reached faster than larger changes. This is useful for
+    /// things like filter cutoff frequencies where large jumps should always take a while. The rate
+    /// needs to be positive. Otherwise no smoothing will be applied.
+    Slew(f32),
+}
+
+/// The state of a [`SmoothingStyle::Crossfade`] smoother for a single sample. The plugin should
+/// process audio for both `previous` and `current` while `fade` is below 1.0, and then mix the two
+/// outputs using [`mix()`][Self::mix()].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Crossfade<T> {
+    /// The value that's being faded out.
+    pub previous: T,
+    /// The value that's being faded in. This is the parameter's current value.
+    pub current: T,
+    /// The gain for `current`, in the range `[0, 1]`. The gain for `previous` is `1.0 - fade`. This
+    /// will always be 1.0 when the smoother is not crossfading.
+    pub fade: f32,
 }
 
 /// A smoother, providing a smoothed value for each sample.
@@ -49,16 +90,25 @@ pub struct Smoother<T> {
     ///
     /// In the case of the `Exponential` smoothing style this is the coefficient `x` that the
     /// previous sample is multplied by.
-    step_size: f32,
+    step_size: AtomicF32,
     /// The value for the current sample. Always stored as floating point for obvious reasons.
     current: AtomicF32,
-    /// The value we're smoothing towards
-    target: T,
+    /// The value we're smoothing towards. This is stored as a float so the smoother can be
+    /// retargeted from the audio thread while processing queued parameter changes.
+    target: AtomicF32,
+    /// The value that's being faded out when using [`SmoothingStyle::Crossfade`], or the value the
+    /// smoothing started at when using [`SmoothingStyle::SCurve`] or [`SmoothingStyle::OnePole`].
+    previous: AtomicF32,
+    /// The fade coefficient when using [`SmoothingStyle::Crossfade`]. See [`Crossfade::fade`].
+    fade: AtomicF32,
 
     /// A dense buffer containing smoothed values for an entire block of audio. Useful when using
     /// [`Buffer::iter_blocks()`][crate::prelude::Buffer::iter_blocks()] to process small blocks of audio
     /// multiple times.
     block_values: AtomicRefCell<Vec<T>>,
+    /// The same as `block_values`, but for [`next_crossfade_block()`][Self::next_crossfade_block()].
+    /// This is only allocated when using [`SmoothingStyle::Crossfade`].
+    crossfade_block_values: AtomicRefCell<Vec<Crossfade<T>>>,
 }
 
 /// An iterator that continuously produces smoothed values. Can be used as an alternative to the
@@ -81,11 +131,50 @@ impl<T: Smoothable> Default for Smoother<T> {
         Self {
             style: SmoothingStyle::None,
             steps_left: AtomicI32::new(0),
-            step_size: Default::default(),
+            step_size: AtomicF32::new(0.0),
             current: AtomicF32::new(0.0),
-            target: Default::default(),
+            target: AtomicF32::new(T::default().to_f32()),
+            previous: AtomicF32::new(T::default().to_f32()),
+            fade: AtomicF32::new(1.0),
 
             block_values: AtomicRefCell::new(Vec::new()),
+            crossfade_block_values: AtomicRefCell::new(Vec::new()),
+        }
+    }
+}
+
+impl<T: Smoothable> Default for Crossfade<T> {
+    fn default() -> Self {
+        Self {
+            previous: T::default(),
+            current: T::default(),
+            fade: 1.0,
+        }
+    }
+}
+
+impl<T> Crossfade<T> {
+    /// Whether the smoother is still crossfading. If this returns `false`, then only the output for
+    /// `current` needs to be computed.
+    #[inline]
+    pub fn is_fading(&self) -> bool {
+        self.fade < 1.0
+    }
+
+    /// Linearly mix the outputs computed for `previous` and `current` using the fade coefficient.
+    #[inline]
+    pub fn mix(&self, previous_output: f32, current_output: f32) -> f32 {
+        (previous_output * (1.0 - self.fade)) + (current_output * self.fade)
+    }
+
+    /// Convert the values using a function. Useful for turning an
+    /// [`EnumParam`][super::EnumParam]'s variant indices back into enum variants.
+    #[inline]
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> Crossfade<U> {
+        Crossfade {
+            previous: f(self.previous),
+            current: f(self.current),
+            fade: self.fade,
         }
     }
 }
@@ -102,6 +191,15 @@ impl<T: Smoothable> Iterator for SmootherIter<'_, T> {
 impl<T: Smoothable> Smoother<T> {
     /// Use the specified style for the smoothing.
     pub fn new(style: SmoothingStyle) -> Self {
+        // A slew rate that isn't positive would never reach the target value
+        let style = match style {
+            SmoothingStyle::Slew(rate) if rate.is_nan() || rate <= 0.0 => {
+                nih_debug_assert_failure!("The slew rate needs to be positive, got {}", rate);
+                SmoothingStyle::None
+            }
+            style => style,
+        };
+
         Self {
             style,
             ..Default::default()
@@ -142,43 +240,98 @@ impl<T: Smoothable> Smoother<T> {
         self.block_values
             .borrow_mut()
             .resize_with(max_block_size, || T::default());
+        if let SmoothingStyle::Crossfade(_) = self.style {
+            self.crossfade_block_values
+                .borrow_mut()
+                .resize_with(max_block_size, Crossfade::default);
+        }
     }
 
     /// Reset the smoother the specified value.
-    pub fn reset(&mut self, value: T) {
-        self.target = value;
+    pub fn reset(&self, value: T) {
+        self.target.store(value.to_f32(), Ordering::Relaxed);
         self.current.store(value.to_f32(), Ordering::Relaxed);
+        self.previous.store(value.to_f32(), Ordering::Relaxed);
+        self.fade.store(1.0, Ordering::Relaxed);
         self.steps_left.store(0, Ordering::Relaxed);
     }
 
-    /// Set the target value.
-    pub fn set_target(&mut self, sample_rate: f32, target: T) {
-        self.target = target;
+    /// Set the target value. Setting the same target value again does not restart the smoothing.
+    pub fn set_target(&self, sample_rate: f32, target: T) {
+        let target = target.to_f32();
+        let old_target = self.target.load(Ordering::Relaxed);
+        if target == old_target {
+            return;
+        }
+        self.target.store(target, Ordering::Relaxed);
 
+        let current = self.current.load(Ordering::Relaxed);
         let steps_left = match self.style {
             SmoothingStyle::None => 1,
             SmoothingStyle::Linear(time)
             | SmoothingStyle::Logarithmic(time)
-            | SmoothingStyle::Exponential(time) => (sample_rate * time / 1000.0).round() as i32,
+            | SmoothingStyle::Exponential(time)
+            | SmoothingStyle::Crossfade(time)
+            | SmoothingStyle::SCurve(time) => (sample_rate * time / 1000.0).round() as i32,
+            // This smoother only stops once the target has been reached, see `next_step()`
+            SmoothingStyle::OnePole(_) => i32::MAX,
+            // The last step will be smaller than the others if the distance is not an exact
+            // multiple of the step size. `Smoother::new()` makes sure the rate is positive.
+            SmoothingStyle::Slew(rate) => {
+                ((target - current).abs() / (rate / sample_rate)).ceil() as i32
+            }
         };
         self.steps_left.store(steps_left, Ordering::Relaxed);
 
-        let current = self.current.load(Ordering::Relaxed);
-        self.step_size = match self.style {
+        // The fade coefficient a crossfade starts at. This is only non-zero when the target changes
+        // in the middle of another crossfade.
+        let mut fade_start = 0.0;
+        if let SmoothingStyle::Crossfade(_) = self.style {
+            // If we're already crossfading, then we'll fade out whichever value is the loudest
+            // right now, starting from its current gain so the new fade doesn't cause a click. The
+            // other value is replaced by the new target at the same gain. Outside of a crossfade
+            // the fade coefficient is 1.0, so this simply starts a new fade from 0.0.
+            let fade = self.fade.load(Ordering::Relaxed);
+            if fade >= 0.5 {
+                self.previous.store(old_target, Ordering::Relaxed);
+            }
+            fade_start = fade.min(1.0 - fade);
+            self.fade.store(
+                if steps_left > 0 { fade_start } else { 1.0 },
+                Ordering::Relaxed,
+            );
+        }
+        if let SmoothingStyle::SCurve(_) | SmoothingStyle::OnePole(_) = self.style {
+            self.previous.store(current, Ordering::Relaxed);
+        }
+
+        let step_size = match self.style {
             SmoothingStyle::None => 0.0,
-            SmoothingStyle::Linear(_) => (self.target.to_f32() - current) / steps_left as f32,
+            SmoothingStyle::Linear(_) => (target - current) / steps_left as f32,
             SmoothingStyle::Logarithmic(_) => {
                 // We need to solve `current * (step_size ^ steps_left) = target` for
                 // `step_size`
                 nih_debug_assert_ne!(current, 0.0);
-                ((self.target.to_f32() / current) as f64).powf((steps_left as f64).recip()) as f32
+                ((target / current) as f64).powf((steps_left as f64).recip()) as f32
             }
             // In this case the step size value is the coefficient the current value will be
             // multiplied by, while the target value is multipled by one minus the coefficient. This
             // reaches 99.99% of the target value after `steps_left`. The smoother will snap to the
             // target value after that point.
             SmoothingStyle::Exponential(_) => 0.0001f64.powf(1.0 / steps_left as f64) as f32,
+            // This is the amount the fade coefficient increases by every sample. The fade always
+            // takes the full crossfade time, even when it doesn't start at 0.0.
+            SmoothingStyle::Crossfade(_) => (1.0 - fade_start) / steps_left as f32,
+            // The same goes for the progress along the S-curve
+            SmoothingStyle::SCurve(_) => (steps_left as f32).recip(),
+            // This is the filter's feedback coefficient, `current = target + (current - target) *
+            // step_size`
+            SmoothingStyle::OnePole(time) => {
+                (-1000.0 / (time as f64 * sample_rate as f64)).exp() as f32
+            }
+            SmoothingStyle::Slew(rate) => (rate / sample_rate).copysign(target - current),
         };
+        self.step_size.store(step_size, Ordering::Relaxed);
     }
 
     /// Get the next value from this smoother. The value will be equal to the previous value once
@@ -200,7 +353,8 @@ impl<T: Smoothable> Smoother<T> {
 
         if self.steps_left.load(Ordering::Relaxed) > 0 {
             let current = self.current.load(Ordering::Relaxed);
-            let target = self.target.to_f32();
+            let target = self.target.load(Ordering::Relaxed);
+            let step_size = self.step_size.load(Ordering::Relaxed);
 
             // The number of steps usually won't fit exactly, so make sure we don't end up with
             // quantization errors on overshoots or undershoots. We also need to account for the
@@ -210,25 +364,74 @@ impl<T: Smoothable> Smoother<T> {
             let old_steps_left = self.steps_left.fetch_sub(steps as i32, Ordering::Relaxed);
             let new = if old_steps_left <= steps as i32 {
                 self.steps_left.store(0, Ordering::Relaxed);
+                self.fade.store(1.0, Ordering::Relaxed);
                 target
             } else {
                 match &self.style {
                     SmoothingStyle::None => target,
-                    SmoothingStyle::Linear(_) => current + (self.step_size * steps as f32),
-                    SmoothingStyle::Logarithmic(_) => current * (self.step_size.powi(steps as i32)),
+                    // The value itself changes immediately, only the fade coefficient is smoothed
+                    SmoothingStyle::Crossfade(_) => {
+                        let fade = self.fade.load(Ordering::Relaxed) + (step_size * steps as f32);
+                        self.fade.store(fade.min(1.0), Ordering::Relaxed);
+                        target
+                    }
+                    SmoothingStyle::Linear(_) | SmoothingStyle::Slew(_) => {
+                        current + (step_size * steps as f32)
+                    }
+                    SmoothingStyle::Logarithmic(_) => current * (step_size.powi(steps as i32)),
                     SmoothingStyle::Exponential(_) => {
                         // This is the same as calculating `current = (current * step_size) +
                         // (target * (1 - step_size))` in a loop since the target value won't change
-                        let coefficient = self.step_size.powi(steps as i32);
+                        let coefficient = step_size.powi(steps as i32);
                         (current * coefficient) + (target * (1.0 - coefficient))
                     }
+                    SmoothingStyle::SCurve(_) => {
+                        let start = self.previous.load(Ordering::Relaxed);
+                        let progress = 1.0 - ((old_steps_left - steps as i32) as f32 * step_size);
+                        let curve = 0.5 - (0.5 * (std::f32::consts::PI * progress).cos());
+                        start + ((target - start) * curve)
+                    }
+                    SmoothingStyle::OnePole(_) => {
+                        let new = target + ((current - target) * step_size.powi(steps as i32));
+                        // We'll stop once the remaining distance is negligible compared to the
+                        // size of the change. Otherwise smoothing towards 0.0 would keep going
+                        // through every denormal value before it stops changing.
+                        let start = self.previous.load(Ordering::Relaxed);
+                        let epsilon = target.abs().max((start - target).abs()) * f32::EPSILON;
+                        if (new - target).abs() <= epsilon || new == current {
+                            self.steps_left.store(0, Ordering::Relaxed);
+                            target
+                        } else {
+                            new
+                        }
+                    }
                 }
             };
             self.current.store(new, Ordering::Relaxed);
 
             T::from_f32(new)
         } else {
-            self.target
+            T::from_f32(self.target.load(Ordering::Relaxed))
+        }
+    }
+
+    /// The same as [`next()`][Self::next()], but also returns the value that's being faded out and
+    /// the fade coefficient when using [`SmoothingStyle::Crossfade`]. For the other smoothing
+    /// styles `previous` and `current` are both set to the next value and `fade` is always 1.0.
+    #[inline]
+    pub fn next_crossfade(&self) -> Crossfade<T> {
+        let current = self.next();
+        match self.style {
+            SmoothingStyle::Crossfade(_) => Crossfade {
+                previous: T::from_f32(self.previous.load(Ordering::Relaxed)),
+                current,
+                fade: self.fade.load(Ordering::Relaxed),
+            },
+            _ => Crossfade {
+                previous: current,
+                current,
+                fade: 1.0,
+            },
         }
     }
 
@@ -278,6 +481,79 @@ impl<T: Smoothable> Smoother<T> {
             &mut values[..block.len()]
         }))
     }
+
+    /// The same as [`next_block()`][Self::next_block()], but produces
+    /// [`next_crossfade()`][Self::next_crossfade()] values instead. The memory for this is only
+    /// allocated in [`initialize_block_smoother()`][Self::initialize_block_smoother()] when this
+    /// smoother uses [`SmoothingStyle::Crossfade`], so this will return `None` for the other
+    /// smoothing styles.
+    ///
+    /// Returns a `None` value if the block length exceed's the allocated capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this function is called again while another block value slice is still alive.
+    pub fn next_crossfade_block(&self, block: &Block) -> Option<AtomicRefMut<[Crossfade<T>]>> {
+        let mut block_values = self.crossfade_block_values.borrow_mut();
+        if block_values.len() < block.len() {
+            return None;
+        }
+
+        (&mut block_values[..block.len()]).fill_with(|| self.next_crossfade());
+
+        Some(AtomicRefMut::map(block_values, |values| {
+            &mut values[..block.len()]
+        }))
+    }
+
+    /// The same as [`next_block()`][Self::next_block()], but the smoother's target is changed at
+    /// the sample indices from `changes` while the block is being filled. This is used together
+    /// with [`ProcessContext::param_changes()`][crate::prelude::ProcessContext::param_changes()]
+    /// when [`Plugin::QUEUED_AUTOMATION`][crate::prelude::Plugin::QUEUED_AUTOMATION] is enabled.
+    /// The changes' timings are relative to the start of the buffer and they need to be sorted.
+    /// `block_start` is the index of the block's first sample in that buffer, as yielded by
+    /// [`Buffer::iter_blocks()`][crate::prelude::Buffer::iter_blocks()]. Only the changes that fall
+    /// within the block are applied. Earlier changes are skipped since they should have been
+    /// applied while processing a previous block, and later changes are left for the next block.
+    /// The wrapper sets the parameter to its final value after the process call.
+    ///
+    /// Returns a `None` value if the block length exceed's the allocated capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this function is called again while another block value slice is still alive.
+    pub fn next_block_with_changes(
+        &self,
+        block: &Block,
+        block_start: usize,
+        sample_rate: f32,
+        changes: impl IntoIterator<Item = (u32, T)>,
+    ) -> Option<AtomicRefMut<[T]>> {
+        let mut block_values = self.block_values.borrow_mut();
+        if block_values.len() < block.len() {
+            return None;
+        }
+
+        let block_end = block_start + block.len();
+        let mut changes = changes
+            .into_iter()
+            .skip_while(|(timing, _)| (*timing as usize) < block_start)
+            .take_while(|(timing, _)| (*timing as usize) < block_end)
+            .peekable();
+        for (sample_idx, value) in (block_start..block_end).zip(&mut block_values[..block.len()]) {
+            while let Some((_, target)) =
+                changes.next_if(|(timing, _)| *timing as usize <= sample_idx)
+            {
+                self.set_target(sample_rate, target);
+            }
+
+            *value = self.next();
+        }
+
+        Some(AtomicRefMut::map(block_values, |values| {
+            &mut values[..block.len()]
+        }))
+    }
 }
 
 impl Smoothable for f32 {
@@ -304,13 +580,60 @@ impl Smoothable for i32 {
     }
 }
 
+impl Smoothable for bool {
+    #[inline]
+    fn to_f32(self) -> f32 {
+        if self {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    #[inline]
+    fn from_f32(value: f32) -> Self {
+        value >= 0.5
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::buffer::Buffer;
+
+    /// Call `f` with a single 20 sample block for testing the block smoothing functions.
+    fn with_block(f: impl FnOnce(&Block)) {
+        let mut real_buffers = vec![vec![0.0; 20]];
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.with_raw_vec(|output_slices| {
+                *output_slices = real_buffers.iter_mut().map(|b| b.as_mut_slice()).collect();
+            })
+        };
+
+        let (_, block) = buffer.iter_blocks(20).next().unwrap();
+        f(&block);
+    }
+
+    /// Split a 20 sample buffer into blocks of `max_block_size` samples, and call `f` with the
+    /// index of the block's first sample and the block itself.
+    fn with_blocks(max_block_size: usize, mut f: impl FnMut(usize, &Block)) {
+        let mut real_buffers = vec![vec![0.0; 20]];
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.with_raw_vec(|output_slices| {
+                *output_slices = real_buffers.iter_mut().map(|b| b.as_mut_slice()).collect();
+            })
+        };
+
+        for (block_start, block) in buffer.iter_blocks(max_block_size) {
+            f(block_start, &block);
+        }
+    }
 
     #[test]
     fn linear_f32_smoothing() {
-        let mut smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Linear(100.0));
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Linear(100.0));
         smoother.reset(10.0);
         assert_eq!(smoother.next(), 10.0);
 
@@ -326,7 +649,7 @@ mod tests {
 
     #[test]
     fn linear_i32_smoothing() {
-        let mut smoother: Smoother<i32> = Smoother::new(SmoothingStyle::Linear(100.0));
+        let smoother: Smoother<i32> = Smoother::new(SmoothingStyle::Linear(100.0));
         smoother.reset(10);
         assert_eq!(smoother.next(), 10);
 
@@ -341,7 +664,7 @@ mod tests {
 
     #[test]
     fn logarithmic_f32_smoothing() {
-        let mut smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Logarithmic(100.0));
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Logarithmic(100.0));
         smoother.reset(10.0);
         assert_eq!(smoother.next(), 10.0);
 
@@ -357,7 +680,7 @@ mod tests {
 
     #[test]
     fn logarithmic_i32_smoothing() {
-        let mut smoother: Smoother<i32> = Smoother::new(SmoothingStyle::Logarithmic(100.0));
+        let smoother: Smoother<i32> = Smoother::new(SmoothingStyle::Logarithmic(100.0));
         smoother.reset(10);
         assert_eq!(smoother.next(), 10);
 
@@ -373,7 +696,7 @@ mod tests {
     /// Same as [linear_f32_smoothing], but skipping steps instead.
     #[test]
     fn skipping_linear_f32_smoothing() {
-        let mut smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Linear(100.0));
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Linear(100.0));
         smoother.reset(10.0);
         assert_eq!(smoother.next(), 10.0);
 
@@ -386,7 +709,7 @@ mod tests {
     /// Same as [linear_i32_smoothing], but skipping steps instead.
     #[test]
     fn skipping_linear_i32_smoothing() {
-        let mut smoother: Smoother<i32> = Smoother::new(SmoothingStyle::Linear(100.0));
+        let smoother: Smoother<i32> = Smoother::new(SmoothingStyle::Linear(100.0));
         smoother.reset(10);
         assert_eq!(smoother.next(), 10);
 
@@ -399,7 +722,7 @@ mod tests {
     /// Same as [logarithmic_f32_smoothing], but skipping steps instead.
     #[test]
     fn skipping_logarithmic_f32_smoothing() {
-        let mut smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Logarithmic(100.0));
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Logarithmic(100.0));
         smoother.reset(10.0);
         assert_eq!(smoother.next(), 10.0);
 
@@ -412,7 +735,7 @@ mod tests {
     /// Same as [logarithmic_i32_smoothing], but skipping steps instead.
     #[test]
     fn skipping_logarithmic_i32_smoothing() {
-        let mut smoother: Smoother<i32> = Smoother::new(SmoothingStyle::Logarithmic(100.0));
+        let smoother: Smoother<i32> = Smoother::new(SmoothingStyle::Logarithmic(100.0));
         smoother.reset(10);
         assert_eq!(smoother.next(), 10);
 
@@ -422,5 +745,268 @@ mod tests {
         assert_eq!(smoother.next(), 20);
     }
 
+    #[test]
+    fn linear_f32_smoothing_with_changes() {
+        let mut smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Linear(100.0));
+        smoother.initialize_block_smoother(20);
+        smoother.reset(10.0);
+
+        // The buffer is processed in two blocks, and the target changes halfway through both of
+        // them. The timings are relative to the start of the buffer, so the second change should
+        // only be applied while processing the second block.
+        with_blocks(10, |block_start, block| {
+            let values = smoother
+                .next_block_with_changes(block, block_start, 100.0, [(5, 20.0), (15, 30.0)])
+                .unwrap();
+            if block_start == 0 {
+                assert!(values[..5].iter().all(|value| *value == 10.0));
+                assert_eq!(values[5], 11.0);
+                assert_eq!(values[9], 15.0);
+            } else {
+                assert_eq!(values[0], 16.0);
+                assert_eq!(values[4], 20.0);
+                assert_eq!(values[5], 21.0);
+                assert_eq!(values[9], 25.0);
+            }
+        });
+
+        assert!(smoother.is_smoothing());
+        for _ in 0..(5 - 1) {
+            smoother.next();
+        }
+        assert_eq!(smoother.next(), 30.0);
+    }
+
+    #[test]
+    fn crossfade_bool_smoothing() {
+        let smoother: Smoother<bool> = Smoother::new(SmoothingStyle::Crossfade(100.0));
+        smoother.reset(false);
+        assert_eq!(
+            smoother.next_crossfade(),
+            Crossfade {
+                previous: false,
+                current: false,
+                fade: 1.0
+            }
+        );
+
+        // The value changes immediately, and the old value is faded out over ten steps
+        smoother.set_target(100.0, true);
+        let crossfade = smoother.next_crossfade();
+        assert!(!crossfade.previous);
+        assert!(crossfade.current);
+        assert_eq!(crossfade.fade, 0.1);
+        assert_eq!(crossfade.mix(0.0, 1.0), 0.1);
+        for _ in 0..(10 - 3) {
+            smoother.next();
+        }
+        assert!(smoother.next_crossfade().is_fading());
+        assert!(!smoother.next_crossfade().is_fading());
+    }
+
+    #[test]
+    fn crossfade_retarget() {
+        let smoother: Smoother<i32> = Smoother::new(SmoothingStyle::Crossfade(100.0));
+        smoother.reset(0);
+
+        // Early in a fade the old value is still the loudest, so it keeps being faded out from its
+        // current gain while the value that was being faded in gets replaced
+        smoother.set_target(100.0, 1);
+        smoother.next();
+        smoother.next();
+        let before = smoother.next_crossfade();
+        smoother.set_target(100.0, 2);
+        let after = smoother.next_crossfade();
+        assert_eq!((before.previous, after.previous, after.current), (0, 0, 2));
+        assert!((after.fade - before.fade - 0.07).abs() < 1e-6);
+
+        // Later in the fade the value that's being faded in is the loudest, so that value gets
+        // faded out from its current gain instead
+        for _ in 0..5 {
+            smoother.next();
+        }
+        let before = smoother.next_crossfade();
+        smoother.set_target(100.0, 3);
+        let after = smoother.next_crossfade();
+        assert_eq!((before.current, after.previous, after.current), (2, 2, 3));
+        assert!(((1.0 - after.fade) - before.fade).abs() < 0.08);
+
+        // The fade to the new value still takes the full crossfade time
+        for _ in 0..(10 - 3) {
+            smoother.next();
+        }
+        assert!(smoother.next_crossfade().is_fading());
+        assert!(!smoother.next_crossfade().is_fading());
+    }
+
+    #[test]
+    fn crossfade_i32_block_smoothing() {
+        let mut smoother: Smoother<i32> = Smoother::new(SmoothingStyle::Crossfade(100.0));
+        smoother.initialize_block_smoother(20);
+        smoother.reset(1);
+
+        smoother.set_target(100.0, 3);
+        with_block(|block| {
+            let values = smoother.next_crossfade_block(block).unwrap();
+            assert!(values.iter().all(|crossfade| crossfade.current == 3));
+            assert_eq!(values[0].previous, 1);
+            assert!(values[8].is_fading());
+            assert!(!values[9].is_fading());
+            assert_eq!(values[19].fade, 1.0);
+        });
+    }
+
+    #[test]
+    fn s_curve_f32_smoothing() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::SCurve(100.0));
+        smoother.reset(10.0);
+        assert_eq!(smoother.next(), 10.0);
+
+        smoother.set_target(100.0, 20.0);
+        let first = smoother.next();
+        let second = smoother.next();
+        // The curve starts out slower than linear smoothing would
+        assert!(first > 10.0 && first < 11.0);
+        assert!(second - first > first - 10.0);
+        for _ in 0..(10 - 4) {
+            smoother.next();
+        }
+        // And it's symmetrical around the halfway point
+        assert!((smoother.previous_value() - (20.0 - (second - 10.0))).abs() < 1e-4);
+        assert_ne!(smoother.next(), 20.0);
+        assert_eq!(smoother.next(), 20.0);
+    }
+
+    #[test]
+    fn s_curve_i32_smoothing() {
+        let smoother: Smoother<i32> = Smoother::new(SmoothingStyle::SCurve(100.0));
+        smoother.reset(10);
+        assert_eq!(smoother.next(), 10);
+
+        // The curve flattens out near the end, so we need a larger range to test the rounding
+        smoother.set_target(100.0, 50);
+        for _ in 0..(10 - 2) {
+            smoother.next();
+        }
+        assert_ne!(smoother.next(), 50);
+        assert_eq!(smoother.next(), 50);
+    }
+
+    #[test]
+    fn one_pole_f32_smoothing() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::OnePole(100.0));
+        smoother.reset(10.0);
+        assert_eq!(smoother.next(), 10.0);
+
+        // After one time constant the value should have moved 63.2% of the way to the target
+        smoother.set_target(100.0, 20.0);
+        for _ in 0..(10 - 1) {
+            smoother.next();
+        }
+        assert!((smoother.next() - 16.321205).abs() < 1e-4);
+
+        // Unlike the exponential smoother this doesn't snap to the target value after some time
+        for _ in 0..(90 - 1) {
+            smoother.next();
+        }
+        let value = smoother.next();
+        assert!(smoother.is_smoothing());
+        assert_ne!(value, 20.0);
+        assert!((value - 20.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn one_pole_f32_smoothing_settles() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::OnePole(10.0));
+        smoother.reset(10.0);
+        smoother.set_target(100.0, 20.0);
+
+        // The smoother will eventually stop changing the value
+        for _ in 0..10_000 {
+            smoother.next();
+        }
+        assert!(!smoother.is_smoothing());
+        assert!((smoother.next() - 20.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn one_pole_f32_smoothing_settles_at_zero() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::OnePole(10.0));
+        smoother.reset(10.0);
+        smoother.set_target(1000.0, 0.0);
+
+        // This should take roughly 16 time constants, and not however long it takes to decay to
+        // the smallest denormal
+        for _ in 0..200 {
+            smoother.next();
+        }
+        assert!(!smoother.is_smoothing());
+        assert_eq!(smoother.next(), 0.0);
+    }
+
+    #[test]
+    fn slew_non_positive_rate() {
+        for rate in [0.0, -100.0, f32::NAN] {
+            let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Slew(rate));
+            smoother.reset(10.0);
+            smoother.set_target(100.0, 20.0);
+            assert_eq!(smoother.next(), 20.0);
+            assert!(!smoother.is_smoothing());
+        }
+    }
+
+    #[test]
+    fn slew_f32_smoothing() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Slew(100.0));
+        smoother.reset(10.0);
+        assert_eq!(smoother.next(), 10.0);
+
+        // At 100 units per second and a 100 Hz sample rate this moves one unit per sample
+        smoother.set_target(100.0, 15.5);
+        assert_eq!(smoother.next(), 11.0);
+        for _ in 0..(6 - 3) {
+            smoother.next();
+        }
+        assert_eq!(smoother.next(), 15.0);
+        assert_eq!(smoother.next(), 15.5);
+        assert!(!smoother.is_smoothing());
+
+        // The rate is the same for larger changes and changes in the other direction
+        smoother.set_target(100.0, 5.5);
+        assert_eq!(smoother.steps_left(), 10);
+        assert_eq!(smoother.next(), 14.5);
+    }
+
+    #[test]
+    fn slew_i32_smoothing() {
+        let smoother: Smoother<i32> = Smoother::new(SmoothingStyle::Slew(200.0));
+        smoother.reset(10);
+        assert_eq!(smoother.next(), 10);
+
+        smoother.set_target(100.0, 20);
+        for _ in 0..(5 - 2) {
+            smoother.next();
+        }
+        assert_eq!(smoother.next(), 18);
+        assert_eq!(smoother.next(), 20);
+    }
+
+    #[test]
+    fn s_curve_and_slew_block_smoothing() {
+        for style in [SmoothingStyle::SCurve(100.0), SmoothingStyle::Slew(100.0)] {
+            let mut smoother: Smoother<f32> = Smoother::new(style);
+            smoother.initialize_block_smoother(20);
+            smoother.reset(10.0);
+            smoother.set_target(100.0, 20.0);
+
+            with_block(|block| {
+                let values = smoother.next_block(block).unwrap();
+                assert_ne!(values[8], 20.0);
+                assert_eq!(values[9], 20.0);
+                assert_eq!(values[19], 20.0);
+            });
+        }
+    }
+
     // TODO: Tests for the exponential smoothing
 }
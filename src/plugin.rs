@@ -4,10 +4,15 @@ use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
 use std::any::Any;
 use std::sync::Arc;
 
-use crate::buffer::Buffer;
-use crate::context::{GuiContext, ProcessContext};
+use crate::buffer::{AuxiliaryBuffers, Buffer};
+use crate::context::{AsyncExecutor, GuiContext, ProcessContext};
 use crate::midi::MidiConfig;
 use crate::param::internals::Params;
+use crate::wrapper::state::{PluginState, StateFormat};
+
+/// A function that executes a plugin's background tasks. This is returned from
+/// [`Plugin::task_executor()`] and it is run on a dedicated worker thread.
+pub type TaskExecutor<P> = Box<dyn Fn(<P as Plugin>::BackgroundTask) + Send + Sync>;
 
 /// Basic functionality that needs to be implemented by a plugin. The wrappers will use this to
 /// expose the plugin in a particular plugin format.
@@ -18,7 +23,7 @@ use crate::param::internals::Params;
 /// This is super basic, and lots of things I didn't need or want to use yet haven't been
 /// implemented. Notable missing features include:
 ///
-/// - MIDI SysEx and MIDI2 for CLAP, note expressions and MIDI1 are already supported
+/// - MPE input, note expressions, MIDI1, MIDI SysEx, and MIDI2 for CLAP are already supported
 #[allow(unused_variables)]
 pub trait Plugin: Default + Send + Sync + 'static {
     const NAME: &'static str;
@@ -61,11 +66,64 @@ pub trait Plugin: Default + Send + Sync + 'static {
     /// the plugin will consume all note and MIDI CC input. If you don't want that, then you will
     /// need to forward those events yourself.
     const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
+    /// If set, then the wrapper will interpret incoming MIDI as MPE (MIDI Polyphonic Expression).
+    /// Pitch bend, channel pressure, and CC 74 messages sent on an MPE zone's member channels are
+    /// then converted to [`NoteEvent::PolyTuning`], [`NoteEvent::PolyPressure`], and
+    /// [`NoteEvent::PolyBrightness`] events for the notes playing on those channels. This way the
+    /// plugin receives the same polyphonic expression events it would get from CLAP note
+    /// expressions or VST3 note expressions. Zones are configured through MPE Configuration
+    /// Messages, and a lower zone spanning all 15 member channels is assumed until the first
+    /// configuration message is received. Messages on the master channels are passed through as
+    /// is.
+    ///
+    /// This requires [`MIDI_INPUT`][Self::MIDI_INPUT] to be set to [`MidiConfig::MidiCCs`] since
+    /// the wrappers would otherwise not receive the pitch bend and CC messages.
+    ///
+    /// [`NoteEvent::PolyTuning`]: crate::midi::NoteEvent::PolyTuning
+    /// [`NoteEvent::PolyPressure`]: crate::midi::NoteEvent::PolyPressure
+    /// [`NoteEvent::PolyBrightness`]: crate::midi::NoteEvent::PolyBrightness
+    const MPE_INPUT: bool = false;
     /// If enabled, the audio processing cycle may be split up into multiple smaller chunks if
     /// parameter values change occur in the middle of the buffer. Depending on the host these
     /// blocks may be as small as a single sample. Bitwig Studio sends at most one parameter change
     /// every 64 samples.
     const SAMPLE_ACCURATE_AUTOMATION: bool = false;
+    /// An alternative to [`SAMPLE_ACCURATE_AUTOMATION`][Self::SAMPLE_ACCURATE_AUTOMATION] that
+    /// keeps the buffer whole. Parameter changes that occur after the first sample of the buffer
+    /// are not applied to the parameters right away. Instead, they're collected in per-parameter
+    /// queues that can be read through
+    /// [`ProcessContext::param_changes()`][crate::prelude::ProcessContext::param_changes()] and fed
+    /// to [`Smoother::next_block_with_changes()`][crate::prelude::Smoother::next_block_with_changes()].
+    /// This avoids tiny blocks under dense automation. The last queued value is applied to the
+    /// parameter after the process call. Takes precedence over `SAMPLE_ACCURATE_AUTOMATION` when
+    /// both are enabled.
+    ///
+    /// Queued changes are not sent as [`NoteEvent::MonoAutomation`] events, and monophonic
+    /// modulation is still applied at the start of the buffer.
+    ///
+    /// [`NoteEvent::MonoAutomation`]: crate::midi::NoteEvent::MonoAutomation
+    const QUEUED_AUTOMATION: bool = false;
+    /// The encoding used when the host saves the plugin's state. JSON is the default, but plugins
+    /// that persist large amounts of data using `#[persist]` fields may want to use
+    /// [`StateFormat::CompressedBinary`] instead. State saved using any of the formats can always be loaded, so this
+    /// can be changed without breaking existing projects.
+    const STATE_FORMAT: StateFormat = StateFormat::Json;
+    /// The maximum number of steps kept in the undo history for changes made from the plugin's
+    /// editor. Setting this to a non-zero value enables the undo history, which can then be used
+    /// through [`GuiContext::undo()`][crate::prelude::GuiContext::undo()] and
+    /// [`GuiContext::redo()`][crate::prelude::GuiContext::redo()]. This is disabled by default
+    /// since most hosts already keep track of parameter changes in their own undo history.
+    const UNDO_HISTORY_SIZE: usize = 0;
+
+    /// The type of the background tasks this plugin can run, or `()` if the plugin doesn't need
+    /// any background tasks. This is usually an enum containing all of the different kinds of work
+    /// the plugin wants to offload from the audio or GUI threads, like loading samples or
+    /// recomputing filter kernels. Tasks are sent from the audio thread, so they should not own any
+    /// heap allocated data that would then be deallocated on the audio thread. Tasks can be
+    /// scheduled using [`ProcessContext::execute_background()`] and
+    /// the [`AsyncExecutor`] passed to [`editor()`][Self::editor()], and they are run using
+    /// the function returned by [`task_executor()`][Self::task_executor()].
+    type BackgroundTask: Send + 'static;
 
     /// The plugin's parameters. The host will update the parameter values before calling
     /// `process()`. These parameters are identified by strings that should never change when the
@@ -77,17 +135,75 @@ pub trait Plugin: Default + Send + Sync + 'static {
     /// and other shared data, so you'll need to move [`Arc`] pointing to any data you want to
     /// access into the editor. You can later modify the parameters through the
     /// [`GuiContext`][crate::prelude::GuiContext] and [`ParamSetter`][crate::prelude::ParamSetter] after the editor
-    /// GUI has been created.
-    fn editor(&self) -> Option<Box<dyn Editor>> {
+    /// GUI has been created. `async_executor` can be used to run
+    /// [background tasks][Self::BackgroundTask] from the editor, so it should be moved into the
+    /// editor if the GUI needs to do so.
+    fn editor(&self, async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
         None
     }
 
+    /// Presets that ship with the plugin. These are queried once when the plugin instance gets
+    /// created, and hosts can then browse and load them without the plugin needing its own preset
+    /// browser. For VST3 plugins these are exposed as a program list, and the preset's category is
+    /// reported as the program's instrument attribute. The version of CLAP NIH-plug currently
+    /// targets does not have a way for hosts to discover presets, so CLAP hosts cannot see these.
+    /// There they can only be loaded from the plugin's own GUI using
+    /// [`GuiContext::set_state()`][crate::prelude::GuiContext::set_state()].
+    ///
+    /// Loading a preset works the same way as restoring the plugin's state, so
+    /// [`initialize()`][Self::initialize()] will be called again afterwards. A convenient way to
+    /// create these presets is to save the plugin's state from the GUI using
+    /// [`GuiContext::get_state()`][crate::prelude::GuiContext::get_state()] and to then include the
+    /// serialized JSON in the plugin using `include_str!()`.
+    fn factory_presets(&self) -> Vec<FactoryPreset> {
+        Vec::new()
+    }
+
+    /// A function that executes the plugin's [background tasks][Self::BackgroundTask]. This is
+    /// called once when the plugin gets instantiated, and the returned function is then moved to a
+    /// dedicated worker thread. Since this function cannot access `self` after it has been
+    /// created, you'll need to move [`Arc`]s pointing to any data the tasks need to access into
+    /// the closure. Tasks are executed in the order they were scheduled, and executing a task may
+    /// block the worker thread for as long as it needs to.
+    fn task_executor(&self) -> TaskExecutor<Self> {
+        Box::new(|_| ())
+    }
+
+    /// Modify a plugin's state before it gets restored. This is called for every state restore,
+    /// including presets and state loaded through
+    /// [`GuiContext::set_state()`][crate::prelude::GuiContext::set_state()]. The state's
+    /// [`version`][PluginState::version] field contains the plugin's [`VERSION`][Self::VERSION] at
+    /// the time the state was saved, so this can be used to upgrade state from older versions of
+    /// the plugin. For instance, when a parameter's `#[id]` gets renamed, its value can be moved to
+    /// the new ID here, and when a parameter's range changes the old plain value can be converted
+    /// to the new range. Parameters with IDs that are not known to the plugin are ignored when the
+    /// state gets restored.
+    ///
+    /// This is called on the GUI or main thread and it may allocate.
+    ///
+    /// ```ignore
+    /// fn filter_state(state: &mut PluginState) {
+    ///     // The `gain` parameter was called `volume` before version 0.2.0
+    ///     if state.version.is_empty() || state.version.starts_with("0.1.") {
+    ///         if let Some(value) = state.params.remove("volume") {
+    ///             state.params.insert(String::from("gain"), value);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    #[allow(unused_variables)]
+    fn filter_state(state: &mut PluginState) {}
+
     //
     // The following functions follow the lifetime of the plugin.
     //
 
     /// Whether the plugin supports a bus config. This only acts as a check, and the plugin
     /// shouldn't do anything beyond returning true or false.
+    ///
+    /// The wrappers will also offer mono, stereo, 5.1, 7.1, and first through third order
+    /// ambisonic layouts for the main busses. Plugins that only care about the number of channels
+    /// can ignore [`BusConfig::input_layout`] and [`BusConfig::output_layout`].
     fn accepts_bus_config(&self, config: &BusConfig) -> bool {
         config.num_input_channels == Self::DEFAULT_NUM_INPUTS
             && config.num_output_channels == Self::DEFAULT_NUM_OUTPUTS
@@ -111,7 +227,7 @@ pub trait Plugin: Default + Send + Sync + 'static {
         &mut self,
         bus_config: &BusConfig,
         buffer_config: &BufferConfig,
-        context: &mut impl ProcessContext,
+        context: &mut impl ProcessContext<Self>,
     ) -> bool {
         true
     }
@@ -139,11 +255,17 @@ pub trait Plugin: Default + Send + Sync + 'static {
     /// [`initialize()`][Self::initialize()] function first to reserve enough capacity in the
     /// smoothers.
     ///
-    /// TODO: Provide a way to access auxiliary input channels if the IO configuration is
-    ///       assymetric
-    /// TODO: Pass transport and other context information to the plugin
+    /// Sidechain inputs and auxiliary outputs are passed separately through `aux`. The buffers
+    /// in there are split up the same way as the main buffer, so they always contain the same
+    /// number of samples. If the plugin does not have any auxiliary IO then these slices are empty.
+    ///
     /// TODO: Create an example plugin that uses block-based processing
-    fn process(&mut self, buffer: &mut Buffer, context: &mut impl ProcessContext) -> ProcessStatus;
+    fn process(
+        &mut self,
+        buffer: &mut Buffer,
+        aux: &mut AuxiliaryBuffers,
+        context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus;
 
     /// Called when the plugin is deactivated. The host will call
     /// [`initialize()`][Self::initialize()] again before the plugin resumes processing audio. These
@@ -190,6 +312,19 @@ pub trait ClapPlugin: Plugin {
     /// processing requirement when the host asks for it. Supported hosts will never ask the plugin
     /// to do offline processing.
     const CLAP_HARD_REALTIME: bool = false;
+
+    /// If this is set to true, then the plugin's note ports will also support CLAP's MIDI2 note
+    /// dialect. MIDI 2.0 channel voice messages are translated to the regular [`NoteEvent`]s, with
+    /// 16-bit note velocities, 32-bit pressure and CC values, registered per-note controllers
+    /// mapped to the polyphonic expression events, and per-note pitch bend mapped to
+    /// [`NoteEvent::PolyTuning`] with a range of 48 semitones. Channel-wide messages like CCs still
+    /// require [`Plugin::MIDI_INPUT`] or [`Plugin::MIDI_OUTPUT`] to be set to
+    /// [`MidiConfig::MidiCCs`], and they will be sent to the host as MIDI2 messages when this is
+    /// enabled.
+    ///
+    /// [`NoteEvent`]: crate::midi::NoteEvent
+    /// [`NoteEvent::PolyTuning`]: crate::midi::NoteEvent::PolyTuning
+    const CLAP_MIDI2: bool = false;
 }
 
 /// Provides auxiliary metadata needed for a VST3 plugin.
@@ -232,7 +367,19 @@ const fn swap_vst3_uid_byte_order(mut uid: [u8; 16]) -> [u8; 16] {
     uid
 }
 
+/// A preset that ships with the plugin. See [`Plugin::factory_presets()`].
+#[derive(Debug, Clone)]
+pub struct FactoryPreset {
+    /// The preset's name as shown in the host's preset browser.
+    pub name: String,
+    /// An optional category for the preset, like `Bass` or `Pad`.
+    pub category: Option<String>,
+    /// The plugin state that will be restored when the preset gets loaded.
+    pub state: PluginState,
+}
+
 /// An editor for a [`Plugin`].
+#[allow(unused_variables)]
 pub trait Editor: Send + Sync {
     /// Create an instance of the plugin's editor and embed it in the parent window. As explained in
     /// [`Plugin::editor()`], you can then read the parameter values directly from your [`Params`]
@@ -242,7 +389,9 @@ pub trait Editor: Send + Sync {
     ///
     /// This function should return a handle to the editor, which will be dropped when the editor
     /// gets closed. Implement the [`Drop`] trait on the returned handle if you need to explicitly
-    /// handle the editor's closing behavior.
+    /// handle the editor's closing behavior. If the editor could not be opened, for instance
+    /// because an OpenGL context could not be created, then this should return `None`. The wrapper
+    /// will then report the failure to the host instead of taking the host down with it.
     ///
     /// If [`set_scale_factor()`][Self::set_scale_factor()] has been called, then any created
     /// windows should have their sizes multiplied by that factor.
@@ -256,13 +405,11 @@ pub trait Editor: Send + Sync {
     //       otherwise be basically impossible to have this still be GUI-framework agnostic. Any
     //       callback that deos involve actual GUI operations will still be spooled to the IRunLoop
     //       instance.
-    // TODO: This function should return an `Option` instead. Right now window opening failures are
-    //       always fatal. This would need to be fixed in basevie first.
     fn spawn(
         &self,
         parent: ParentWindowHandle,
         context: Arc<dyn GuiContext>,
-    ) -> Box<dyn Any + Send + Sync>;
+    ) -> Option<Box<dyn Any + Send + Sync>>;
 
     /// Returns the (currnent) size of the editor in pixels as a `(width, height)` pair. This size
     /// must be reported in _logical pixels_, i.e. the size before being multiplied by the DPI
@@ -278,6 +425,32 @@ pub trait Editor: Send + Sync {
     /// there.
     fn set_scale_factor(&self, factor: f32) -> bool;
 
+    /// Whether the host is allowed to resize the editor, for instance when the user drags the
+    /// edges of the host's plugin window. If this returns `true`, then the wrappers will use
+    /// [`resize_hints()`][Self::resize_hints()] to constrain the host's proposed sizes and
+    /// [`set_size()`][Self::set_size()] to resize the editor.
+    fn can_resize(&self) -> bool {
+        false
+    }
+
+    /// Constraints for host-initiated resizes. This is only used when
+    /// [`can_resize()`][Self::can_resize()] returns `true`.
+    fn resize_hints(&self) -> ResizeHints {
+        ResizeHints::default()
+    }
+
+    /// Resize the editor to a new size requested by the host. Like with
+    /// [`size()`][Self::size()], this size is in _logical pixels_. Hosts are not required to let the
+    /// plugin constrain a size before setting it, so the editor should return `false` without
+    /// resizing if the size does not meet its [`resize_hints()`][Self::resize_hints()]. If the
+    /// editor is open, then the embedded window should be resized to match. Afterwards
+    /// [`size()`][Self::size()] should return the new size.
+    ///
+    /// This is only called when [`can_resize()`][Self::can_resize()] returns `true`.
+    fn set_size(&self, width: u32, height: u32) -> bool {
+        false
+    }
+
     /// A callback that will be called wheneer the parameter values changed while the editor is
     /// open. You don't need to do anything with this, but this can be used to force a redraw when
     /// the host sends a new value for a parameter or when a parameter change sent to the host gets
@@ -291,7 +464,46 @@ pub trait Editor: Send + Sync {
     //       and API agnostic, add a way to ask the GuiContext if the wrapper already provides a
     //       tick function. If it does not, then the Editor implementation must handle this by
     //       itself. This would also need an associated `PREFERRED_FRAME_RATE` constant.
-    // TODO: Host->Plugin resizing
+}
+
+/// Constraints for host-initiated editor resizes. All sizes are in logical pixels.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ResizeHints {
+    /// The minimum `(width, height)` of the editor, if any.
+    pub min_size: Option<(u32, u32)>,
+    /// The maximum `(width, height)` of the editor, if any.
+    pub max_size: Option<(u32, u32)>,
+    /// If set, then the editor's width and height should always have this `(width, height)` ratio.
+    pub aspect_ratio: Option<(u32, u32)>,
+}
+
+impl ResizeHints {
+    /// Adjust a proposed `(width, height)` size so it meets these constraints. If the aspect ratio
+    /// cannot be kept while also staying within the size bounds, then the bounds take precedence.
+    pub fn constrain(&self, width: u32, height: u32) -> (u32, u32) {
+        let (min_width, min_height) = self.min_size.unwrap_or((0, 0));
+        let (max_width, max_height) = self.max_size.unwrap_or((u32::MAX, u32::MAX));
+        let clamp_width = |width: u32| width.max(min_width).min(max_width);
+        let clamp_height = |height: u32| height.max(min_height).min(max_height);
+
+        let mut width = clamp_width(width);
+        let mut height = clamp_height(height);
+        if let Some((ratio_width, ratio_height)) = self.aspect_ratio {
+            if ratio_width > 0 && ratio_height > 0 {
+                // The width is leading, unless that would push the height out of bounds
+                let target_height = (width as u64 * ratio_height as u64 / ratio_width as u64)
+                    .min(u32::MAX as u64) as u32;
+                height = clamp_height(target_height);
+                if height != target_height {
+                    let target_width = (height as u64 * ratio_width as u64 / ratio_height as u64)
+                        .min(u32::MAX as u64) as u32;
+                    width = clamp_width(target_width);
+                }
+            }
+        }
+
+        (width, height)
+    }
 }
 
 /// A raw window handle for platform and GUI framework agnostic editors.
@@ -312,12 +524,75 @@ pub struct BusConfig {
     pub num_input_channels: u32,
     /// The number of output channels for the plugin.
     pub num_output_channels: u32,
+    /// The channel layout for the main input. This always contains `num_input_channels` channels.
+    /// If the plugin does not have a main input, then this is `ChannelLayout::Discrete(0)`.
+    pub input_layout: ChannelLayout,
+    /// The channel layout for the main output. This always contains `num_output_channels`
+    /// channels. If the plugin does not have a main output, then this is
+    /// `ChannelLayout::Discrete(0)`.
+    pub output_layout: ChannelLayout,
     /// Any additional sidechain inputs.
     pub aux_input_busses: AuxiliaryIOConfig,
     /// Any additional outputs.
     pub aux_output_busses: AuxiliaryIOConfig,
 }
 
+/// The speaker layout for a main input or output bus. The wrappers translate these layouts to
+/// CLAP port types and VST3 speaker arrangements. Surround layouts use the usual channel order of
+/// `L, R, C, LFE, Ls, Rs` for 5.1, followed by `Sl, Sr` for 7.1. Ambisonic layouts use ACN channel
+/// ordering with SN3D normalization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelLayout {
+    Mono,
+    Stereo,
+    Surround5_1,
+    Surround7_1,
+    /// Full sphere ambisonics of the specified order. This has `(order + 1)^2` channels. Orders
+    /// above 3 cannot be represented in VST3.
+    Ambisonic {
+        order: u32,
+    },
+    /// A number of channels without any particular speaker assignment.
+    Discrete(u32),
+}
+
+impl ChannelLayout {
+    /// The default layout for a number of channels. This is used for the plugin's default bus
+    /// configuration. Anything other than mono or stereo becomes a discrete layout.
+    pub const fn from_num_channels(num_channels: u32) -> Self {
+        match num_channels {
+            1 => ChannelLayout::Mono,
+            2 => ChannelLayout::Stereo,
+            n => ChannelLayout::Discrete(n),
+        }
+    }
+
+    /// The number of channels in this layout.
+    pub const fn num_channels(&self) -> u32 {
+        match self {
+            ChannelLayout::Mono => 1,
+            ChannelLayout::Stereo => 2,
+            ChannelLayout::Surround5_1 => 6,
+            ChannelLayout::Surround7_1 => 8,
+            ChannelLayout::Ambisonic { order } => (*order + 1) * (*order + 1),
+            ChannelLayout::Discrete(n) => *n,
+        }
+    }
+}
+
+impl std::fmt::Display for ChannelLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChannelLayout::Mono => write!(f, "Mono"),
+            ChannelLayout::Stereo => write!(f, "Stereo"),
+            ChannelLayout::Surround5_1 => write!(f, "5.1"),
+            ChannelLayout::Surround7_1 => write!(f, "7.1"),
+            ChannelLayout::Ambisonic { order } => write!(f, "Order {order} Ambisonic"),
+            ChannelLayout::Discrete(n) => write!(f, "{n} channel"),
+        }
+    }
+}
+
 /// Configuration for auxiliary inputs or outputs on [`BusCofnig`].
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct AuxiliaryIOConfig {
@@ -373,3 +648,78 @@ pub enum ProcessMode {
     /// processed.
     Offline,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resize_hints_unconstrained() {
+        let hints = ResizeHints::default();
+        assert_eq!(hints.constrain(123, 456), (123, 456));
+    }
+
+    #[test]
+    fn resize_hints_min_size() {
+        let hints = ResizeHints {
+            min_size: Some((200, 100)),
+            ..Default::default()
+        };
+        assert_eq!(hints.constrain(150, 50), (200, 100));
+        assert_eq!(hints.constrain(150, 300), (200, 300));
+        assert_eq!(hints.constrain(400, 300), (400, 300));
+    }
+
+    #[test]
+    fn resize_hints_max_size() {
+        let hints = ResizeHints {
+            max_size: Some((800, 600)),
+            ..Default::default()
+        };
+        assert_eq!(hints.constrain(1000, 1000), (800, 600));
+        assert_eq!(hints.constrain(1000, 500), (800, 500));
+        assert_eq!(hints.constrain(400, 300), (400, 300));
+    }
+
+    #[test]
+    fn resize_hints_aspect_ratio() {
+        let hints = ResizeHints {
+            aspect_ratio: Some((4, 3)),
+            ..Default::default()
+        };
+        // The width is leading
+        assert_eq!(hints.constrain(400, 1000), (400, 300));
+        assert_eq!(hints.constrain(800, 1), (800, 600));
+    }
+
+    #[test]
+    fn resize_hints_zero_aspect_ratio() {
+        let hints = ResizeHints {
+            aspect_ratio: Some((0, 3)),
+            ..Default::default()
+        };
+        assert_eq!(hints.constrain(400, 1000), (400, 1000));
+    }
+
+    #[test]
+    fn resize_hints_combined() {
+        let hints = ResizeHints {
+            min_size: Some((200, 100)),
+            max_size: Some((800, 300)),
+            aspect_ratio: Some((2, 1)),
+        };
+        assert_eq!(hints.constrain(400, 400), (400, 200));
+        // Both dimensions get clamped to the minimum size first
+        assert_eq!(hints.constrain(100, 10), (200, 100));
+        // The ratio would push the height above the maximum, so the width follows the height
+        // instead
+        assert_eq!(hints.constrain(1000, 100), (600, 300));
+        // If the ratio can't be kept within the bounds, then the bounds take precedence
+        let hints = ResizeHints {
+            min_size: Some((100, 100)),
+            max_size: Some((150, 150)),
+            aspect_ratio: Some((4, 1)),
+        };
+        assert_eq!(hints.constrain(150, 150), (150, 100));
+    }
+}
@@ -10,17 +10,20 @@ pub use crate::wrapper::standalone::{nih_export_standalone, nih_export_standalon
 pub use crate::formatters;
 pub use crate::util;
 
-pub use crate::buffer::Buffer;
-pub use crate::context::{GuiContext, ParamSetter, PluginApi, ProcessContext};
+pub use crate::buffer::{AuxiliaryBuffers, Buffer};
+pub use crate::context::{
+    AsyncExecutor, GuiContext, ParamChange, ParamSetter, PluginApi, ProcessContext,
+};
 // This also includes the derive macro
-pub use crate::midi::{control_change, MidiConfig, NoteEvent};
+pub use crate::midi::{control_change, MidiConfig, NoteEvent, SysExMessage};
 pub use crate::param::enums::{Enum, EnumParam};
-pub use crate::param::internals::{ParamPtr, Params};
+pub use crate::param::internals::{ParamGroup, ParamPtr, Params};
 pub use crate::param::range::{FloatRange, IntRange};
-pub use crate::param::smoothing::{Smoothable, Smoother, SmoothingStyle};
+pub use crate::param::smoothing::{Crossfade, Smoothable, Smoother, SmoothingStyle};
 pub use crate::param::{BoolParam, FloatParam, IntParam, Param, ParamFlags};
 pub use crate::plugin::{
-    AuxiliaryIOConfig, BufferConfig, BusConfig, ClapPlugin, Editor, ParentWindowHandle, Plugin,
-    ProcessMode, ProcessStatus, Vst3Plugin,
+    AuxiliaryIOConfig, BufferConfig, BusConfig, ChannelLayout, ClapPlugin, Editor, FactoryPreset,
+    ParentWindowHandle, Plugin, ProcessMode, ProcessStatus, ResizeHints, TaskExecutor, Vst3Plugin,
 };
-pub use crate::wrapper::state::PluginState;
+pub use crate::preset::{Preset, PresetEncoding};
+pub use crate::wrapper::state::{ParamValue, PluginState, StateFormat};
@@ -0,0 +1,437 @@
+//! Saving and loading user presets. A preset file wraps a [`PluginState`] together with a header
+//! that identifies the plugin the preset belongs to. These functions can be used together with
+//! [`GuiContext::get_state()`][crate::prelude::GuiContext::get_state()] and
+//! [`GuiContext::set_state()`][crate::prelude::GuiContext::set_state()] to implement preset
+//! management in a plugin's editor, and they work the same way for plugins running in the
+//! standalone target.
+//!
+//! ```ignore
+//! // Saving the current state as a user preset
+//! let preset = Preset::new::<MyPlugin>("Big Bass", context.get_state());
+//! let path = nih_plug::preset::save_user_preset::<MyPlugin>(&preset, PresetEncoding::Json)?;
+//!
+//! // And loading it again later
+//! let preset = nih_plug::preset::load_preset::<MyPlugin>(&path)?;
+//! context.set_state(preset.state);
+//! ```
+//!
+//! Presets can be stored either as human readable JSON or in a more compact binary format. The
+//! binary format starts with [`BINARY_PRESET_MAGIC`], so loading a preset will automatically
+//! detect which encoding was used.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::plugin::Plugin;
+use crate::wrapper::state::{self, PluginState};
+
+/// The version of the preset file format. This is incremented whenever the format changes in a
+/// way that older versions of NIH-plug cannot read. Presets with a newer format version will be
+/// rejected when loading them.
+pub const PRESET_FORMAT_VERSION: u32 = 1;
+
+/// The file extension used for preset files, without the leading period.
+pub const PRESET_FILE_EXTENSION: &str = "nihpreset";
+
+/// The magic bytes at the start of a binary encoded preset file. JSON encoded presets always start
+/// with an opening curly brace, so the two can never be confused.
+pub const BINARY_PRESET_MAGIC: &[u8; 8] = b"NIHPRST\0";
+
+/// A preset file. This contains the plugin's state together with information about the plugin
+/// that created it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    /// Information about the preset and the plugin it belongs to.
+    pub header: PresetHeader,
+    /// The plugin state that will be restored when loading this preset.
+    pub state: PluginState,
+}
+
+/// The header stored at the start of every preset file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresetHeader {
+    /// The version of the preset file format, see [`PRESET_FORMAT_VERSION`].
+    pub format_version: u32,
+    /// An identifier for the plugin this preset was created for. See [`plugin_id()`].
+    pub plugin_id: String,
+    /// The value of [`Plugin::VERSION`] at the time the preset was saved. This is purely
+    /// informational.
+    pub plugin_version: String,
+    /// The preset's name.
+    pub name: String,
+    /// An optional category for the preset, like `Bass` or `Pad`.
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+/// How a preset should be encoded when writing it to a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresetEncoding {
+    /// Pretty printed JSON. This is easy to inspect and to edit by hand.
+    Json,
+    /// A compact binary encoding using MessagePack, prefixed with [`BINARY_PRESET_MAGIC`].
+    Binary,
+}
+
+/// A preset stored in the user preset directory, as returned by [`list_user_presets()`].
+#[derive(Debug, Clone)]
+pub struct PresetInfo {
+    /// The path to the preset file.
+    pub path: PathBuf,
+    /// The preset's header.
+    pub header: PresetHeader,
+}
+
+/// Errors that may arise while saving or loading presets.
+#[derive(Debug)]
+pub enum PresetError {
+    /// The preset file could not be read or written.
+    Io(io::Error),
+    /// The preset could not be encoded or decoded.
+    Serialization(String),
+    /// The preset uses a newer version of the preset format than the one supported by this
+    /// version of NIH-plug.
+    UnsupportedFormatVersion(u32),
+    /// The preset was created for a different plugin.
+    WrongPlugin { expected: String, found: String },
+    /// The platform's data directory could not be determined.
+    NoPresetDirectory,
+}
+
+impl Display for PresetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PresetError::Io(err) => write!(f, "I/O error: {err}"),
+            PresetError::Serialization(err) => write!(f, "Invalid preset data: {err}"),
+            PresetError::UnsupportedFormatVersion(version) => write!(
+                f,
+                "The preset uses format version {version}, but only versions up to \
+                 {PRESET_FORMAT_VERSION} are supported"
+            ),
+            PresetError::WrongPlugin { expected, found } => write!(
+                f,
+                "The preset was created for '{found}', but this plugin is '{expected}'"
+            ),
+            PresetError::NoPresetDirectory => {
+                write!(f, "Could not determine the user preset directory")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PresetError {}
+
+impl From<io::Error> for PresetError {
+    fn from(err: io::Error) -> Self {
+        PresetError::Io(err)
+    }
+}
+
+impl Preset {
+    /// Create a new preset for plugin `P` from a state object. This state object can be obtained
+    /// through [`GuiContext::get_state()`][crate::prelude::GuiContext::get_state()].
+    pub fn new<P: Plugin>(name: impl Into<String>, state: PluginState) -> Self {
+        Self {
+            header: PresetHeader {
+                format_version: PRESET_FORMAT_VERSION,
+                plugin_id: plugin_id::<P>(),
+                plugin_version: P::VERSION.to_string(),
+                name: name.into(),
+                category: None,
+            },
+            state,
+        }
+    }
+
+    /// Set the preset's category.
+    pub fn with_category(mut self, category: impl Into<String>) -> Self {
+        self.header.category = Some(category.into());
+        self
+    }
+
+    /// Encode the preset using the specified encoding.
+    pub fn serialize(&self, encoding: PresetEncoding) -> Result<Vec<u8>, PresetError> {
+        match encoding {
+            PresetEncoding::Json => serde_json::to_vec_pretty(self)
+                .map_err(|err| PresetError::Serialization(err.to_string())),
+            PresetEncoding::Binary => {
+                let mut data = BINARY_PRESET_MAGIC.to_vec();
+                rmp_serde::encode::write_named(&mut data, self)
+                    .map_err(|err| PresetError::Serialization(err.to_string()))?;
+
+                Ok(data)
+            }
+        }
+    }
+
+    /// Decode a preset that was encoded using [`serialize()`][Self::serialize()]. The encoding is
+    /// detected automatically. This does not check whether the preset belongs to a specific plugin,
+    /// use [`validate()`][Self::validate()] for that.
+    pub fn deserialize(data: &[u8]) -> Result<Self, PresetError> {
+        let preset: Preset = match data.strip_prefix(BINARY_PRESET_MAGIC) {
+            Some(data) => rmp_serde::from_slice(data)
+                .map_err(|err| PresetError::Serialization(err.to_string()))?,
+            None => serde_json::from_slice(data)
+                .map_err(|err| PresetError::Serialization(err.to_string()))?,
+        };
+
+        if preset.header.format_version > PRESET_FORMAT_VERSION {
+            return Err(PresetError::UnsupportedFormatVersion(
+                preset.header.format_version,
+            ));
+        }
+
+        Ok(preset)
+    }
+
+    /// Check whether this preset can be loaded by plugin `P`.
+    pub fn validate<P: Plugin>(&self) -> Result<(), PresetError> {
+        if self.header.format_version > PRESET_FORMAT_VERSION {
+            return Err(PresetError::UnsupportedFormatVersion(
+                self.header.format_version,
+            ));
+        }
+
+        let expected = plugin_id::<P>();
+        if self.header.plugin_id != expected {
+            return Err(PresetError::WrongPlugin {
+                expected,
+                found: self.header.plugin_id.clone(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// The identifier stored in a preset's header. This is based on the plugin's vendor and name, so
+/// presets can be shared between the plugin's CLAP, VST3, and standalone versions.
+pub fn plugin_id<P: Plugin>() -> String {
+    format!("{}/{}", P::VENDOR, P::NAME)
+}
+
+/// The directory user presets for plugin `P` are stored in. This is a `Presets` directory inside
+/// of a directory for the plugin in the platform's user data directory:
+///
+/// - Linux: `$XDG_DATA_HOME/<vendor>/<plugin>/Presets`, or `~/.local/share/...` if
+///   `$XDG_DATA_HOME` is not set
+/// - macOS: `~/Library/Application Support/<vendor>/<plugin>/Presets`
+/// - Windows: `%APPDATA%\<vendor>\<plugin>\Presets`
+///
+/// Returns `None` if the data directory could not be determined. The directory may not exist yet.
+pub fn user_preset_dir<P: Plugin>() -> Option<PathBuf> {
+    Some(
+        user_data_dir()?
+            .join(sanitize_file_name(P::VENDOR))
+            .join(sanitize_file_name(P::NAME))
+            .join("Presets"),
+    )
+}
+
+/// Write a preset to a file, overwriting the file if it already exists.
+pub fn save_preset(
+    path: impl AsRef<Path>,
+    preset: &Preset,
+    encoding: PresetEncoding,
+) -> Result<(), PresetError> {
+    let data = preset.serialize(encoding)?;
+    fs::write(path, data)?;
+
+    Ok(())
+}
+
+/// Save a preset to the [user preset directory][user_preset_dir()]. The file name is derived from
+/// the preset's name, and an existing preset with the same name will be overwritten. Returns the
+/// path the preset was saved to.
+pub fn save_user_preset<P: Plugin>(
+    preset: &Preset,
+    encoding: PresetEncoding,
+) -> Result<PathBuf, PresetError> {
+    preset.validate::<P>()?;
+
+    let preset_dir = user_preset_dir::<P>().ok_or(PresetError::NoPresetDirectory)?;
+    fs::create_dir_all(&preset_dir)?;
+
+    let path = preset_dir.join(format!(
+        "{}.{}",
+        sanitize_file_name(&preset.header.name),
+        PRESET_FILE_EXTENSION
+    ));
+    save_preset(&path, preset, encoding)?;
+
+    Ok(path)
+}
+
+/// Read a preset file and check whether it can be loaded by plugin `P`. The preset's state can
+/// then be restored using [`GuiContext::set_state()`][crate::prelude::GuiContext::set_state()].
+pub fn load_preset<P: Plugin>(path: impl AsRef<Path>) -> Result<Preset, PresetError> {
+    let preset = Preset::deserialize(&fs::read(path)?)?;
+    preset.validate::<P>()?;
+
+    Ok(preset)
+}
+
+/// Read either a preset file or a file containing only a serialized [`PluginState`] object.
+/// Used for the CLAP preset-load extension.
+pub(crate) fn load_state_file<P: Plugin>(
+    path: impl AsRef<Path>,
+) -> Result<PluginState, PresetError> {
+    let data = fs::read(path)?;
+    match Preset::deserialize(&data) {
+        Ok(preset) => {
+            preset.validate::<P>()?;
+            Ok(preset.state)
+        }
+        Err(err @ PresetError::UnsupportedFormatVersion(_)) => Err(err),
+        Err(_) => state::decode_state(&data).map_err(PresetError::Serialization),
+    }
+}
+
+/// List all presets for plugin `P` in the [user preset directory][user_preset_dir()], sorted by
+/// name. Files that cannot be read or that belong to other plugins are skipped. Returns an empty
+/// list if the directory does not exist yet.
+pub fn list_user_presets<P: Plugin>() -> Result<Vec<PresetInfo>, PresetError> {
+    let preset_dir = user_preset_dir::<P>().ok_or(PresetError::NoPresetDirectory)?;
+    let entries = match fs::read_dir(&preset_dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut presets = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(PRESET_FILE_EXTENSION) {
+            continue;
+        }
+
+        match load_preset::<P>(&path) {
+            Ok(preset) => presets.push(PresetInfo {
+                path,
+                header: preset.header,
+            }),
+            Err(err) => nih_debug_assert_failure!("Skipping preset '{}': {}", path.display(), err),
+        }
+    }
+    presets.sort_by(|a, b| a.header.name.cmp(&b.header.name));
+
+    Ok(presets)
+}
+
+/// The platform's per-user data directory.
+fn user_data_dir() -> Option<PathBuf> {
+    let non_empty_var = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+
+    if cfg!(target_os = "windows") {
+        non_empty_var("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        non_empty_var("HOME").map(|home| {
+            PathBuf::from(home)
+                .join("Library")
+                .join("Application Support")
+        })
+    } else {
+        non_empty_var("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                non_empty_var("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+            })
+    }
+}
+
+/// Replace all characters that are not allowed in file names on any of the supported platforms
+/// with underscores.
+fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    // Windows also doesn't like trailing periods, and an empty name would result in a hidden file
+    let sanitized = sanitized.trim_end_matches('.');
+    if sanitized.is_empty() {
+        String::from("_")
+    } else {
+        sanitized.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wrapper::state::ParamValue;
+    use std::collections::HashMap;
+
+    fn make_preset() -> Preset {
+        Preset {
+            header: PresetHeader {
+                format_version: PRESET_FORMAT_VERSION,
+                plugin_id: String::from("Vendor/Plugin"),
+                plugin_version: String::from("0.1.0"),
+                name: String::from("Test"),
+                category: Some(String::from("Bass")),
+            },
+            state: PluginState {
+                version: String::from("0.1.0"),
+                params: HashMap::from([(String::from("gain"), ParamValue::F32(-6.0))]),
+                fields: HashMap::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        let preset = make_preset();
+        let data = preset.serialize(PresetEncoding::Json).unwrap();
+        assert_eq!(data[0], b'{');
+
+        let deserialized = Preset::deserialize(&data).unwrap();
+        assert_eq!(deserialized.header.plugin_id, preset.header.plugin_id);
+        assert_eq!(deserialized.header.category.as_deref(), Some("Bass"));
+        assert!(matches!(
+            deserialized.state.params["gain"],
+            ParamValue::F32(value) if value == -6.0
+        ));
+    }
+
+    #[test]
+    fn binary_roundtrip() {
+        let preset = make_preset();
+        let data = preset.serialize(PresetEncoding::Binary).unwrap();
+        assert!(data.starts_with(BINARY_PRESET_MAGIC));
+
+        let deserialized = Preset::deserialize(&data).unwrap();
+        assert_eq!(deserialized.header.name, preset.header.name);
+        assert!(matches!(
+            deserialized.state.params["gain"],
+            ParamValue::F32(value) if value == -6.0
+        ));
+    }
+
+    #[test]
+    fn reject_newer_format() {
+        let mut preset = make_preset();
+        preset.header.format_version = PRESET_FORMAT_VERSION + 1;
+        let data = preset.serialize(PresetEncoding::Json).unwrap();
+
+        assert!(matches!(
+            Preset::deserialize(&data),
+            Err(PresetError::UnsupportedFormatVersion(_))
+        ));
+    }
+
+    #[test]
+    fn sanitize_file_names() {
+        assert_eq!(sanitize_file_name("Bass: Big/Wide"), "Bass_ Big_Wide");
+        assert_eq!(sanitize_file_name("  Lead.  "), "Lead");
+        assert_eq!(sanitize_file_name("..."), "_");
+    }
+}
@@ -5,6 +5,8 @@ pub mod window;
 
 pub use stft::StftHelper;
 
+pub use crate::wrapper::util::hash_param_id;
+
 pub const MINUS_INFINITY_DB: f32 = -100.0;
 pub const MINUS_INFINITY_GAIN: f32 = 1e-5; // 10f32.powf(MINUS_INFINITY_DB / 20)
 pub const NOTES: [&str; 12] = [
@@ -2,7 +2,10 @@
 //! name of a type that implements `Plugin` to. The macro will handle the rest.
 
 pub mod clap;
+pub(crate) mod mpe;
+pub(crate) mod param_changes;
 pub mod state;
+pub(crate) mod undo;
 pub(crate) mod util;
 
 #[cfg(feature = "standalone")]
@@ -4,6 +4,8 @@ mod util;
 mod context;
 mod descriptor;
 mod factory;
+mod midi2;
+mod poly_modulation;
 mod wrapper;
 
 /// Re-export for the wrapper.
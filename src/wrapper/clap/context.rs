@@ -1,14 +1,16 @@
-use atomic_refcell::AtomicRefMut;
+use atomic_refcell::{AtomicRef, AtomicRefMut};
 use std::collections::VecDeque;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use super::wrapper::{OutputParamEvent, Task, Wrapper};
-use crate::context::{GuiContext, PluginApi, ProcessContext, Transport};
+use crate::context::{GuiContext, ParamChange, PluginApi, ProcessContext, Transport};
 use crate::event_loop::EventLoop;
-use crate::midi::NoteEvent;
+use crate::midi::{NoteEvent, SysExMessage, SysExStorage};
 use crate::param::internals::ParamPtr;
 use crate::plugin::ClapPlugin;
+use crate::wrapper::param_changes::ParamChangeQueues;
+use crate::wrapper::util::mark_dirty_if_non_automatable;
 
 /// A [`GuiContext`] implementation for the wrapper. This is passed to the plugin in
 /// [`Editor::spawn()`][crate::prelude::Editor::spawn()] so it can interact with the rest of the plugin and
@@ -24,6 +26,8 @@ pub(crate) struct WrapperProcessContext<'a, P: ClapPlugin> {
     pub(super) wrapper: &'a Wrapper<P>,
     pub(super) input_events_guard: AtomicRefMut<'a, VecDeque<NoteEvent>>,
     pub(super) output_events_guard: AtomicRefMut<'a, VecDeque<NoteEvent>>,
+    pub(super) param_changes_guard: AtomicRef<'a, ParamChangeQueues>,
+    pub(super) sysex_storage_guard: AtomicRefMut<'a, SysExStorage>,
     pub(super) transport: Transport,
 }
 
@@ -41,6 +45,8 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
     unsafe fn raw_begin_set_parameter(&self, param: ParamPtr) {
         match self.wrapper.param_ptr_to_hash.get(&param) {
             Some(hash) => {
+                self.wrapper.undo_history.begin_gesture(param);
+
                 let success = self
                     .wrapper
                     .queue_parameter_event(OutputParamEvent::BeginGesture { param_hash: *hash });
@@ -59,6 +65,8 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
                 // (when the plugin isn't processing audio). The parameter's actual value will only
                 // be changed when the output event is written to prevent changing parameter values
                 // in the middle of processing audio.
+                self.wrapper.undo_history.record_value(param, normalized);
+
                 let clap_plain_value = normalized as f64 * param.step_count().unwrap_or(1) as f64;
                 let success = self
                     .wrapper
@@ -81,6 +89,9 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
                     .queue_parameter_event(OutputParamEvent::EndGesture { param_hash: *hash });
 
                 nih_debug_assert!(success, "Parameter output event queue was full, parameter change will not be sent to the host");
+
+                self.wrapper.undo_history.end_gesture(param);
+                mark_dirty_if_non_automatable(self, param);
             }
             None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
         }
@@ -91,15 +102,57 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
     }
 
     fn set_state(&self, state: crate::wrapper::state::PluginState) {
-        self.wrapper.set_state_object(state)
+        self.wrapper.set_state_object(state);
+        self.mark_dirty();
+    }
+
+    fn mark_dirty(&self) {
+        let task_posted = self.wrapper.do_maybe_async(Task::MarkDirty);
+        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+    }
+
+    fn undo(&self) -> bool {
+        self.wrapper
+            .undo_history
+            .undo(self, self.wrapper.params.as_ref())
+    }
+
+    fn redo(&self) -> bool {
+        self.wrapper
+            .undo_history
+            .redo(self, self.wrapper.params.as_ref())
+    }
+
+    fn can_undo(&self) -> bool {
+        self.wrapper.undo_history.can_undo()
+    }
+
+    fn can_redo(&self) -> bool {
+        self.wrapper.undo_history.can_redo()
+    }
+
+    fn raw_begin_field_changes(&self) {
+        self.wrapper
+            .undo_history
+            .begin_field_changes(self.wrapper.params.as_ref());
+    }
+
+    fn raw_end_field_changes(&self) {
+        self.wrapper
+            .undo_history
+            .end_field_changes(self.wrapper.params.as_ref());
     }
 }
 
-impl<P: ClapPlugin> ProcessContext for WrapperProcessContext<'_, P> {
+impl<P: ClapPlugin> ProcessContext<P> for WrapperProcessContext<'_, P> {
     fn plugin_api(&self) -> PluginApi {
         PluginApi::Clap
     }
 
+    fn execute_background(&self, task: P::BackgroundTask) {
+        self.wrapper.background_thread.schedule(task);
+    }
+
     fn transport(&self) -> &Transport {
         &self.transport
     }
@@ -112,6 +165,14 @@ impl<P: ClapPlugin> ProcessContext for WrapperProcessContext<'_, P> {
         self.output_events_guard.push_back(event);
     }
 
+    fn sysex_data<'a>(&'a self, message: &'a SysExMessage) -> &'a [u8] {
+        self.sysex_storage_guard.get(message)
+    }
+
+    fn new_sysex_message(&mut self, data: &[u8]) -> Option<SysExMessage> {
+        self.sysex_storage_guard.push(data)
+    }
+
     fn set_latency_samples(&self, samples: u32) {
         // Only make a callback if it's actually needed
         // XXX: For CLAP we could move this handling to the Plugin struct, but it may be worthwhile
@@ -122,4 +183,24 @@ impl<P: ClapPlugin> ProcessContext for WrapperProcessContext<'_, P> {
             nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
         }
     }
+
+    unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
+        match self.wrapper.param_ptr_to_hash.get(&param) {
+            Some(hash) => {
+                // Like with the GUI context, the parameter's value will only be changed when the
+                // output events are written to the host's output event queue after the process call
+                let clap_plain_value = normalized as f64 * param.step_count().unwrap_or(1) as f64;
+                let success = self
+                    .wrapper
+                    .queue_parameter_change_gesture(*hash, clap_plain_value);
+
+                nih_debug_assert!(success, "Parameter output event queue was full, parameter change will not be sent to the host");
+            }
+            None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
+        }
+    }
+
+    fn raw_param_changes(&self, param: ParamPtr) -> &[ParamChange] {
+        self.param_changes_guard.get(param)
+    }
 }
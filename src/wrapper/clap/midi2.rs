@@ -0,0 +1,340 @@
+//! Conversions between MIDI 2.0 Universal MIDI Packets (UMP) and NIH-plug's [`NoteEvent`]s for
+//! CLAP's MIDI2 note dialect. Only the 64-bit MIDI 2.0 channel voice messages are handled, and the
+//! UMP group is ignored on input and always set to 0 on output.
+
+use crate::midi::NoteEvent;
+
+/// The UMP message type for 64-bit MIDI 2.0 channel voice messages.
+const MIDI2_CHANNEL_VOICE_MESSAGE_TYPE: u32 = 0x4;
+
+/// A registered per-note controller message.
+const REGISTERED_PER_NOTE_CONTROLLER: u8 = 0x0;
+/// A per-note pitch bend message.
+const PER_NOTE_PITCH_BEND: u8 = 0x6;
+/// A note off message.
+const NOTE_OFF: u8 = 0x8;
+/// A note on message. Unlike with MIDI 1.0, a note on message with zero velocity is not a note off.
+const NOTE_ON: u8 = 0x9;
+/// A polyphonic pressure message.
+const POLYPHONIC_KEY_PRESSURE: u8 = 0xA;
+/// A control change message.
+const CONTROL_CHANGE: u8 = 0xB;
+/// A channel pressure message.
+const CHANNEL_KEY_PRESSURE: u8 = 0xD;
+/// A pitch bend message.
+const PITCH_BEND_CHANGE: u8 = 0xE;
+
+/// The registered per-note controller for modulation, mapped to [`NoteEvent::PolyVibrato`].
+const RPNC_MODULATION: u8 = 1;
+/// The registered per-note controller for volume, mapped to [`NoteEvent::PolyVolume`].
+const RPNC_VOLUME: u8 = 7;
+/// The registered per-note controller for panning, mapped to [`NoteEvent::PolyPan`].
+const RPNC_PAN: u8 = 10;
+/// The registered per-note controller for expression, mapped to [`NoteEvent::PolyExpression`].
+const RPNC_EXPRESSION: u8 = 11;
+/// The registered per-note controller for brightness, mapped to [`NoteEvent::PolyBrightness`].
+const RPNC_BRIGHTNESS: u8 = 74;
+
+/// The per-note pitch bend range in semitones. This matches the default pitch bend range for MPE
+/// member channels.
+const PER_NOTE_PITCH_BEND_RANGE: f32 = 48.0;
+
+/// Convert a MIDI 2.0 UMP packet to a [`NoteEvent`]. Returns `None` if the packet does not contain
+/// a MIDI 2.0 channel voice message or if the message is not supported.
+pub fn translate_event(timing: u32, data: &[u32; 4]) -> Option<NoteEvent> {
+    let message_type = data[0] >> 28;
+    if message_type != MIDI2_CHANNEL_VOICE_MESSAGE_TYPE {
+        return None;
+    }
+
+    let status = ((data[0] >> 20) & 0xF) as u8;
+    let channel = ((data[0] >> 16) & 0xF) as u8;
+    let index = ((data[0] >> 8) & 0x7F) as u8;
+    let sub_index = (data[0] & 0xFF) as u8;
+    let value = normalize_u32(data[1]);
+
+    match status {
+        NOTE_ON => Some(NoteEvent::NoteOn {
+            timing,
+            voice_id: None,
+            channel,
+            note: index,
+            velocity: normalize_u16((data[1] >> 16) as u16),
+        }),
+        NOTE_OFF => Some(NoteEvent::NoteOff {
+            timing,
+            voice_id: None,
+            channel,
+            note: index,
+            velocity: normalize_u16((data[1] >> 16) as u16),
+        }),
+        POLYPHONIC_KEY_PRESSURE => Some(NoteEvent::PolyPressure {
+            timing,
+            voice_id: None,
+            channel,
+            note: index,
+            pressure: value,
+        }),
+        PER_NOTE_PITCH_BEND => Some(NoteEvent::PolyTuning {
+            timing,
+            voice_id: None,
+            channel,
+            note: index,
+            tuning: (value - 0.5) * 2.0 * PER_NOTE_PITCH_BEND_RANGE,
+        }),
+        REGISTERED_PER_NOTE_CONTROLLER => match sub_index {
+            RPNC_MODULATION => Some(NoteEvent::PolyVibrato {
+                timing,
+                voice_id: None,
+                channel,
+                note: index,
+                vibrato: value,
+            }),
+            RPNC_VOLUME => Some(NoteEvent::PolyVolume {
+                timing,
+                voice_id: None,
+                channel,
+                note: index,
+                // This uses the same mapping as VST3's volume note expression, where 0.25 is unity
+                // gain
+                gain: value * 4.0,
+            }),
+            RPNC_PAN => Some(NoteEvent::PolyPan {
+                timing,
+                voice_id: None,
+                channel,
+                note: index,
+                pan: (value * 2.0) - 1.0,
+            }),
+            RPNC_EXPRESSION => Some(NoteEvent::PolyExpression {
+                timing,
+                voice_id: None,
+                channel,
+                note: index,
+                expression: value,
+            }),
+            RPNC_BRIGHTNESS => Some(NoteEvent::PolyBrightness {
+                timing,
+                voice_id: None,
+                channel,
+                note: index,
+                brightness: value,
+            }),
+            _ => None,
+        },
+        CHANNEL_KEY_PRESSURE => Some(NoteEvent::MidiChannelPressure {
+            timing,
+            channel,
+            pressure: value,
+        }),
+        PITCH_BEND_CHANGE => Some(NoteEvent::MidiPitchBend {
+            timing,
+            channel,
+            value,
+        }),
+        CONTROL_CHANGE => Some(NoteEvent::MidiCC {
+            timing,
+            channel,
+            cc: index,
+            value,
+        }),
+        _ => None,
+    }
+}
+
+/// Convert a [`NoteEvent`] to a MIDI 2.0 UMP packet. This is the inverse of
+/// [`translate_event()`]. Returns `None` if the event cannot be represented as a MIDI 2.0 channel
+/// voice message.
+pub fn translate_event_reverse(event: &NoteEvent) -> Option<[u32; 4]> {
+    let (status, channel, index, sub_index, data) = match *event {
+        NoteEvent::NoteOn {
+            channel,
+            note,
+            velocity,
+            ..
+        } => (
+            NOTE_ON,
+            channel,
+            note,
+            0,
+            (denormalize_u16(velocity) as u32) << 16,
+        ),
+        NoteEvent::NoteOff {
+            channel,
+            note,
+            velocity,
+            ..
+        } => (
+            NOTE_OFF,
+            channel,
+            note,
+            0,
+            (denormalize_u16(velocity) as u32) << 16,
+        ),
+        NoteEvent::PolyPressure {
+            channel,
+            note,
+            pressure,
+            ..
+        } => (
+            POLYPHONIC_KEY_PRESSURE,
+            channel,
+            note,
+            0,
+            denormalize_u32(pressure),
+        ),
+        NoteEvent::PolyTuning {
+            channel,
+            note,
+            tuning,
+            ..
+        } => (
+            PER_NOTE_PITCH_BEND,
+            channel,
+            note,
+            0,
+            denormalize_u32((tuning / PER_NOTE_PITCH_BEND_RANGE / 2.0) + 0.5),
+        ),
+        NoteEvent::PolyVibrato {
+            channel,
+            note,
+            vibrato,
+            ..
+        } => (
+            REGISTERED_PER_NOTE_CONTROLLER,
+            channel,
+            note,
+            RPNC_MODULATION,
+            denormalize_u32(vibrato),
+        ),
+        NoteEvent::PolyVolume {
+            channel,
+            note,
+            gain,
+            ..
+        } => (
+            REGISTERED_PER_NOTE_CONTROLLER,
+            channel,
+            note,
+            RPNC_VOLUME,
+            denormalize_u32(gain / 4.0),
+        ),
+        NoteEvent::PolyPan {
+            channel, note, pan, ..
+        } => (
+            REGISTERED_PER_NOTE_CONTROLLER,
+            channel,
+            note,
+            RPNC_PAN,
+            denormalize_u32((pan + 1.0) / 2.0),
+        ),
+        NoteEvent::PolyExpression {
+            channel,
+            note,
+            expression,
+            ..
+        } => (
+            REGISTERED_PER_NOTE_CONTROLLER,
+            channel,
+            note,
+            RPNC_EXPRESSION,
+            denormalize_u32(expression),
+        ),
+        NoteEvent::PolyBrightness {
+            channel,
+            note,
+            brightness,
+            ..
+        } => (
+            REGISTERED_PER_NOTE_CONTROLLER,
+            channel,
+            note,
+            RPNC_BRIGHTNESS,
+            denormalize_u32(brightness),
+        ),
+        NoteEvent::MidiChannelPressure {
+            channel, pressure, ..
+        } => (
+            CHANNEL_KEY_PRESSURE,
+            channel,
+            0,
+            0,
+            denormalize_u32(pressure),
+        ),
+        NoteEvent::MidiPitchBend { channel, value, .. } => {
+            (PITCH_BEND_CHANGE, channel, 0, 0, denormalize_u32(value))
+        }
+        NoteEvent::MidiCC {
+            channel, cc, value, ..
+        } => (CONTROL_CHANGE, channel, cc, 0, denormalize_u32(value)),
+        _ => return None,
+    };
+
+    Some([
+        (MIDI2_CHANNEL_VOICE_MESSAGE_TYPE << 28)
+            | ((status as u32 & 0xF) << 20)
+            | ((channel as u32 & 0xF) << 16)
+            | ((index as u32 & 0x7F) << 8)
+            | sub_index as u32,
+        data,
+        0,
+        0,
+    ])
+}
+
+fn normalize_u16(value: u16) -> f32 {
+    value as f32 / u16::MAX as f32
+}
+
+fn normalize_u32(value: u32) -> f32 {
+    (value as f64 / u32::MAX as f64) as f32
+}
+
+fn denormalize_u16(value: f32) -> u16 {
+    (value.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16
+}
+
+fn denormalize_u32(value: f32) -> u32 {
+    (value.clamp(0.0, 1.0) as f64 * u32::MAX as f64).round() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn note_on_roundtrip() {
+        let event = NoteEvent::NoteOn {
+            timing: 0,
+            voice_id: None,
+            channel: 3,
+            note: 69,
+            velocity: 1.0,
+        };
+
+        let packet = translate_event_reverse(&event).unwrap();
+        assert_eq!(packet[0], 0x4093_4500);
+        assert_eq!(packet[1], 0xFFFF_0000);
+        assert_eq!(translate_event(0, &packet), Some(event));
+    }
+
+    #[test]
+    fn per_note_pitch_bend() {
+        // The center value means no pitch bend
+        let packet = [0x4060_4500, 0x8000_0000, 0, 0];
+        match translate_event(0, &packet) {
+            Some(NoteEvent::PolyTuning { note, tuning, .. }) => {
+                assert_eq!(note, 69);
+                assert!(tuning.abs() < 1e-4);
+            }
+            event => panic!("Unexpected event: {event:?}"),
+        }
+    }
+
+    #[test]
+    fn midi1_packets_are_ignored() {
+        // A MIDI 1.0 channel voice message using the 32-bit message type
+        let packet = [0x2090_4564, 0, 0, 0];
+        assert_eq!(translate_event(0, &packet), None);
+    }
+}
@@ -0,0 +1,127 @@
+//! Routing for CLAP's parameter modulation events. Modulation that targets a single voice of a
+//! parameter with polyphonic modulation enabled is passed to the plugin as a
+//! [`NoteEvent::PolyModulation`], and everything else modulates the parameter monophonically.
+
+use crate::midi::NoteEvent;
+use crate::param::internals::ParamPtr;
+
+/// Convert a `CLAP_EVENT_PARAM_MOD` event to a [`NoteEvent::PolyModulation`] if it targets a single
+/// voice of a parameter that has polyphonic modulation enabled. `amount` is the modulation amount
+/// in CLAP's plain units. Returns `None` if the event should modulate the parameter monophonically
+/// instead. This is also the fallback for modulation that targets all voices for a key or a
+/// channel, since only per note ID modulation is supported.
+pub unsafe fn translate_param_mod(
+    timing: u32,
+    param_hash: u32,
+    param_ptr: ParamPtr,
+    note_id: i32,
+    amount: f64,
+) -> Option<NoteEvent> {
+    if !param_ptr.poly_modulated() || note_id == -1 {
+        return None;
+    }
+
+    Some(NoteEvent::PolyModulation {
+        timing,
+        voice_id: note_id,
+        param_hash,
+        normalized_offset: amount as f32 / param_ptr.step_count().unwrap_or(1) as f32,
+    })
+}
+
+/// Create a [`NoteEvent::MonoAutomation`] event containing a parameter's current normalized value if
+/// the parameter has polyphonic modulation enabled. This should be used after the parameter's value
+/// has been changed through automation or monophonic modulation. Returns `None` for other
+/// parameters.
+pub unsafe fn mono_automation_event(
+    timing: u32,
+    param_hash: u32,
+    param_ptr: ParamPtr,
+) -> Option<NoteEvent> {
+    if !param_ptr.poly_modulated() {
+        return None;
+    }
+
+    Some(NoteEvent::MonoAutomation {
+        timing,
+        param_hash,
+        normalized_value: param_ptr.normalized_value(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::range::FloatRange;
+    use crate::param::{FloatParam, Param};
+
+    fn make_param() -> FloatParam {
+        FloatParam::new(
+            "Foo",
+            5.0,
+            FloatRange::Linear {
+                min: 0.0,
+                max: 10.0,
+            },
+        )
+        .with_poly_modulation()
+    }
+
+    #[test]
+    fn voice_modulation() {
+        let param = make_param();
+        assert_eq!(
+            unsafe { translate_param_mod(3, 42, param.as_ptr(), 7, 0.25) },
+            Some(NoteEvent::PolyModulation {
+                timing: 3,
+                voice_id: 7,
+                param_hash: 42,
+                normalized_offset: 0.25
+            })
+        );
+    }
+
+    #[test]
+    fn key_and_channel_modulation_is_monophonic() {
+        // Modulation for a key or a channel doesn't have a note ID
+        let param = make_param();
+        assert_eq!(
+            unsafe { translate_param_mod(3, 42, param.as_ptr(), -1, 0.25) },
+            None
+        );
+    }
+
+    #[test]
+    fn monophonic_parameters() {
+        let param = FloatParam::new(
+            "Foo",
+            5.0,
+            FloatRange::Linear {
+                min: 0.0,
+                max: 10.0,
+            },
+        );
+        assert_eq!(
+            unsafe { translate_param_mod(3, 42, param.as_ptr(), 7, 0.25) },
+            None
+        );
+        assert_eq!(
+            unsafe { mono_automation_event(3, 42, param.as_ptr()) },
+            None
+        );
+    }
+
+    #[test]
+    fn mono_automation_includes_monophonic_modulation() {
+        let param = make_param();
+        unsafe { param.as_ptr().modulate_value(0.25) };
+        assert_eq!(
+            unsafe { mono_automation_event(3, 42, param.as_ptr()) },
+            Some(NoteEvent::MonoAutomation {
+                timing: 3,
+                param_hash: 42,
+                normalized_value: 0.75
+            })
+        );
+    }
+}
@@ -5,15 +5,16 @@
 use atomic_float::AtomicF32;
 use atomic_refcell::{AtomicRefCell, AtomicRefMut};
 use clap_sys::events::{
-    clap_event_header, clap_event_midi, clap_event_note, clap_event_note_expression,
-    clap_event_param_gesture, clap_event_param_mod, clap_event_param_value, clap_event_transport,
-    clap_event_type, clap_input_events, clap_output_events, CLAP_CORE_EVENT_SPACE_ID,
-    CLAP_EVENT_IS_LIVE, CLAP_EVENT_MIDI, CLAP_EVENT_NOTE_EXPRESSION, CLAP_EVENT_NOTE_OFF,
-    CLAP_EVENT_NOTE_ON, CLAP_EVENT_PARAM_GESTURE_BEGIN, CLAP_EVENT_PARAM_GESTURE_END,
-    CLAP_EVENT_PARAM_MOD, CLAP_EVENT_PARAM_VALUE, CLAP_EVENT_TRANSPORT,
-    CLAP_NOTE_EXPRESSION_BRIGHTNESS, CLAP_NOTE_EXPRESSION_EXPRESSION, CLAP_NOTE_EXPRESSION_PAN,
-    CLAP_NOTE_EXPRESSION_PRESSURE, CLAP_NOTE_EXPRESSION_TUNING, CLAP_NOTE_EXPRESSION_VIBRATO,
-    CLAP_NOTE_EXPRESSION_VOLUME, CLAP_TRANSPORT_HAS_BEATS_TIMELINE,
+    clap_event_header, clap_event_midi, clap_event_midi2, clap_event_midi_sysex, clap_event_note,
+    clap_event_note_expression, clap_event_param_gesture, clap_event_param_mod,
+    clap_event_param_value, clap_event_transport, clap_event_type, clap_input_events,
+    clap_output_events, CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_IS_LIVE, CLAP_EVENT_MIDI,
+    CLAP_EVENT_MIDI2, CLAP_EVENT_MIDI_SYSEX, CLAP_EVENT_NOTE_END, CLAP_EVENT_NOTE_EXPRESSION,
+    CLAP_EVENT_NOTE_OFF, CLAP_EVENT_NOTE_ON, CLAP_EVENT_PARAM_GESTURE_BEGIN,
+    CLAP_EVENT_PARAM_GESTURE_END, CLAP_EVENT_PARAM_MOD, CLAP_EVENT_PARAM_VALUE,
+    CLAP_EVENT_TRANSPORT, CLAP_NOTE_EXPRESSION_BRIGHTNESS, CLAP_NOTE_EXPRESSION_EXPRESSION,
+    CLAP_NOTE_EXPRESSION_PAN, CLAP_NOTE_EXPRESSION_PRESSURE, CLAP_NOTE_EXPRESSION_TUNING,
+    CLAP_NOTE_EXPRESSION_VIBRATO, CLAP_NOTE_EXPRESSION_VOLUME, CLAP_TRANSPORT_HAS_BEATS_TIMELINE,
     CLAP_TRANSPORT_HAS_SECONDS_TIMELINE, CLAP_TRANSPORT_HAS_TEMPO,
     CLAP_TRANSPORT_HAS_TIME_SIGNATURE, CLAP_TRANSPORT_IS_LOOP_ACTIVE, CLAP_TRANSPORT_IS_PLAYING,
     CLAP_TRANSPORT_IS_RECORDING, CLAP_TRANSPORT_IS_WITHIN_PRE_ROLL,
@@ -25,6 +26,16 @@ use clap_sys::ext::audio_ports::{
 use clap_sys::ext::audio_ports_config::{
     clap_audio_ports_config, clap_plugin_audio_ports_config, CLAP_EXT_AUDIO_PORTS_CONFIG,
 };
+use clap_sys::ext::draft::ambisonic::{
+    clap_ambisonic_info, clap_plugin_ambisonic, CLAP_AMBISONIC_ACN,
+    CLAP_AMBISONIC_NORMALIZATION_SN3D, CLAP_EXT_AMBISONIC, CLAP_PORT_AMBISONIC,
+};
+use clap_sys::ext::draft::preset_load::{clap_plugin_preset_load, CLAP_EXT_PRESET_LOAD};
+use clap_sys::ext::draft::surround::{
+    clap_plugin_surround, CLAP_EXT_SURROUND, CLAP_PORT_SURROUND, CLAP_SURROUND_BL,
+    CLAP_SURROUND_BR, CLAP_SURROUND_FC, CLAP_SURROUND_FL, CLAP_SURROUND_FR, CLAP_SURROUND_LFE,
+    CLAP_SURROUND_SL, CLAP_SURROUND_SR,
+};
 use clap_sys::ext::event_filter::{clap_plugin_event_filter, CLAP_EXT_EVENT_FILTER};
 use clap_sys::ext::gui::{
     clap_gui_resize_hints, clap_host_gui, clap_plugin_gui, clap_window, CLAP_EXT_GUI,
@@ -33,18 +44,18 @@ use clap_sys::ext::gui::{
 use clap_sys::ext::latency::{clap_host_latency, clap_plugin_latency, CLAP_EXT_LATENCY};
 use clap_sys::ext::note_ports::{
     clap_note_port_info, clap_plugin_note_ports, CLAP_EXT_NOTE_PORTS, CLAP_NOTE_DIALECT_CLAP,
-    CLAP_NOTE_DIALECT_MIDI,
+    CLAP_NOTE_DIALECT_MIDI, CLAP_NOTE_DIALECT_MIDI2, CLAP_NOTE_DIALECT_MIDI_MPE,
 };
 use clap_sys::ext::params::{
     clap_host_params, clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS,
     CLAP_PARAM_IS_AUTOMATABLE, CLAP_PARAM_IS_BYPASS, CLAP_PARAM_IS_HIDDEN,
-    CLAP_PARAM_IS_MODULATABLE, CLAP_PARAM_IS_READONLY, CLAP_PARAM_IS_STEPPED,
-    CLAP_PARAM_RESCAN_VALUES,
+    CLAP_PARAM_IS_MODULATABLE, CLAP_PARAM_IS_MODULATABLE_PER_NOTE_ID, CLAP_PARAM_IS_READONLY,
+    CLAP_PARAM_IS_STEPPED, CLAP_PARAM_RESCAN_VALUES,
 };
 use clap_sys::ext::render::{
     clap_plugin_render, clap_plugin_render_mode, CLAP_RENDER_OFFLINE, CLAP_RENDER_REALTIME,
 };
-use clap_sys::ext::state::{clap_plugin_state, CLAP_EXT_STATE};
+use clap_sys::ext::state::{clap_host_state, clap_plugin_state, CLAP_EXT_STATE};
 use clap_sys::ext::tail::{clap_plugin_tail, CLAP_EXT_TAIL};
 use clap_sys::ext::thread_check::{clap_host_thread_check, CLAP_EXT_THREAD_CHECK};
 use clap_sys::fixedpoint::{CLAP_BEATTIME_FACTOR, CLAP_SECTIME_FACTOR};
@@ -76,24 +87,53 @@ use std::time::Duration;
 
 use super::context::{WrapperGuiContext, WrapperProcessContext};
 use super::descriptor::PluginDescriptor;
+use super::midi2;
+use super::poly_modulation;
 use super::util::ClapPtr;
-use crate::buffer::Buffer;
-use crate::context::Transport;
-use crate::event_loop::{EventLoop, MainThreadExecutor, TASK_QUEUE_CAPACITY};
-use crate::midi::{MidiConfig, NoteEvent};
+use crate::buffer::{AuxiliaryBuffers, Buffer};
+use crate::context::{ParamChange, Transport};
+use crate::event_loop::{BackgroundThread, EventLoop, MainThreadExecutor, TASK_QUEUE_CAPACITY};
+use crate::midi::{MidiConfig, NoteEvent, SysExStorage};
 use crate::param::internals::{ParamPtr, Params};
 use crate::param::ParamFlags;
 use crate::plugin::{
-    BufferConfig, BusConfig, ClapPlugin, Editor, ParentWindowHandle, ProcessMode, ProcessStatus,
+    BufferConfig, BusConfig, ChannelLayout, ClapPlugin, Editor, ParentWindowHandle, ProcessMode,
+    ProcessStatus,
 };
+use crate::preset;
 use crate::util::permit_alloc;
-use crate::wrapper::state::{self, PluginState};
-use crate::wrapper::util::{hash_param_id, process_wrapper, strlcpy};
+use crate::wrapper::mpe::MpeState;
+use crate::wrapper::param_changes::ParamChangeQueues;
+use crate::wrapper::state::{self, FieldChangeTracker, PluginState};
+use crate::wrapper::undo::UndoHistory;
+use crate::wrapper::util::{hash_param_id, process_wrapper, strlcpy, voice_id_from_note_id};
 
 /// How many output parameter changes we can store in our output parameter change queue. Storing
 /// more than this many parameters at a time will cause changes to get lost.
 const OUTPUT_EVENT_QUEUE_CAPACITY: usize = 2048;
 
+/// The CLAP channel map for [`ChannelLayout::Surround5_1`]. This matches VST3's `k51` arrangement.
+const SURROUND_5_1_CHANNEL_MAP: [u8; 6] = [
+    CLAP_SURROUND_FL as u8,
+    CLAP_SURROUND_FR as u8,
+    CLAP_SURROUND_FC as u8,
+    CLAP_SURROUND_LFE as u8,
+    CLAP_SURROUND_BL as u8,
+    CLAP_SURROUND_BR as u8,
+];
+/// The CLAP channel map for [`ChannelLayout::Surround7_1`]. This matches VST3's `k71Music`
+/// arrangement.
+const SURROUND_7_1_CHANNEL_MAP: [u8; 8] = [
+    CLAP_SURROUND_FL as u8,
+    CLAP_SURROUND_FR as u8,
+    CLAP_SURROUND_FC as u8,
+    CLAP_SURROUND_LFE as u8,
+    CLAP_SURROUND_BL as u8,
+    CLAP_SURROUND_BR as u8,
+    CLAP_SURROUND_SL as u8,
+    CLAP_SURROUND_SR as u8,
+];
+
 #[repr(C)]
 pub struct Wrapper<P: ClapPlugin> {
     // Keep the vtable as the first field so we can do a simple pointer cast
@@ -107,11 +147,14 @@ pub struct Wrapper<P: ClapPlugin> {
     /// The plugin's parameters. These are fetched once during initialization. That way the
     /// `ParamPtr`s are guaranteed to live at least as long as this object and we can interact with
     /// the `Params` object without having to acquire a lock on `plugin`.
-    params: Arc<dyn Params>,
+    pub params: Arc<dyn Params>,
     /// The plugin's editor, if it has one. This object does not do anything on its own, but we need
     /// to instantiate this in advance so we don't need to lock the entire [`Plugin`] object when
     /// creating an editor.
     editor: Option<Box<dyn Editor>>,
+    /// A worker thread that runs the plugin's background tasks using the executor returned by
+    /// [`Plugin::task_executor()`][crate::prelude::Plugin::task_executor()].
+    pub background_thread: BackgroundThread<P::BackgroundTask>,
     /// A handle for the currently active editor instance. The plugin should implement `Drop` on
     /// this handle for its closing behavior.
     editor_handle: RwLock<Option<Box<dyn Any + Send + Sync>>>,
@@ -139,6 +182,18 @@ pub struct Wrapper<P: ClapPlugin> {
     /// Stores any events the plugin has output during the current processing cycle, analogous to
     /// `input_events`.
     output_events: AtomicRefCell<VecDeque<NoteEvent>>,
+    /// Parameter value changes that occur after the start of the current block, if
+    /// `P::QUEUED_AUTOMATION` is enabled. The last change for each parameter is applied after the
+    /// block has been processed.
+    param_changes: AtomicRefCell<ParamChangeQueues>,
+    /// Storage for the long SysEx messages received and sent during the current processing cycle
+    /// or parameter flush. CLAP SysEx events only contain a pointer to the message, so outgoing
+    /// messages are also copied here and kept alive until the host has read the event. This is
+    /// cleared at the start of the next process or flush call.
+    sysex_storage: AtomicRefCell<SysExStorage>,
+    /// Converts MPE member channel messages to polyphonic expression events when
+    /// `P::MPE_INPUT` is enabled.
+    mpe_state: AtomicRefCell<MpeState>,
     /// The last process status returned by the plugin. This is used for tail handling.
     last_process_status: AtomicCell<ProcessStatus>,
     /// The current latency in samples, as set by the plugin through the [`ProcessContext`]. uses
@@ -149,6 +204,22 @@ pub struct Wrapper<P: ClapPlugin> {
     /// between process calls. This buffer owns the vector, because otherwise it would need to store
     /// a mutable reference to the data contained in this mutex.
     output_buffer: AtomicRefCell<Buffer<'static>>,
+    /// Stores sample data for every sidechain input the plugin has. Indexed by
+    /// `[sidechain_input][channel][sample]` We'll copy the data to these buffers since modifying
+    /// the host's sidechain input buffers may not be safe, and the plugin may want to be able to
+    /// modify the buffers.
+    aux_input_storage: AtomicRefCell<Vec<Vec<Vec<f32>>>>,
+    /// Accompanying buffers for `aux_input_storage`. There is no way to do this in safe Rust, so
+    /// the process function needs to make sure all channel pointers stored in these buffers are
+    /// still accurate before passing it to the plugin.
+    aux_input_buffers: AtomicRefCell<Vec<Buffer<'static>>>,
+    /// Scratch sample data for auxiliary outputs the host did not connect. Indexed the same way as
+    /// `aux_input_storage`. The plugin always gets a buffer with the expected number of channels,
+    /// even if the host doesn't care about the output.
+    aux_output_storage: AtomicRefCell<Vec<Vec<Vec<f32>>>>,
+    /// Buffers for the plugin's auxiliary outputs. Like `output_buffer`, these point to the host's
+    /// output buffers when the host provides them, and to `aux_output_storage` otherwise.
+    aux_output_buffers: AtomicRefCell<Vec<Buffer<'static>>>,
     /// The plugin is able to restore state through a method on the `GuiContext`. To avoid changing
     /// parameters mid-processing and running into garbled data if the host also tries to load state
     /// at the same time the restoring happens at the end of each processing call. If this zero
@@ -168,14 +239,14 @@ pub struct Wrapper<P: ClapPlugin> {
     // We'll query all of the host's extensions upfront
     host_callback: ClapPtr<clap_host>,
 
+    clap_plugin_ambisonic: clap_plugin_ambisonic,
+
     clap_plugin_audio_ports_config: clap_plugin_audio_ports_config,
     /// During initialization we'll ask `P` which bus configurations it supports. The host can then
-    /// use the audio ports config extension to choose a configuration. Right now we only query mono
-    /// and stereo configurations, with and without inputs, as well as the plugin's default input
-    /// and output channel counts if that does not match one of those configurations (to do the
-    /// least surprising thing).
-    ///
-    /// TODO: Support surround setups once a plugin needs that
+    /// use the audio ports config extension to choose a configuration. Right now we query mono,
+    /// stereo, 5.1, 7.1, and first through third order ambisonic configurations, with and without
+    /// inputs, as well as the plugin's default input and output channel counts if that does not
+    /// match one of those configurations (to do the least surprising thing).
     supported_bus_configs: Vec<BusConfig>,
 
     clap_plugin_audio_ports: clap_plugin_audio_ports,
@@ -204,6 +275,10 @@ pub struct Wrapper<P: ClapPlugin> {
     /// by slashes, and they're only used to allow the DAW to display parameters in a tree
     /// structure.
     param_group_by_hash: HashMap<u32, String>,
+    /// Hashes of the parameters that belong to a group marked as hidden through
+    /// [`ParamGroup::hidden`][crate::param::internals::ParamGroup::hidden]. These parameters are
+    /// treated the same way as parameters with the [`ParamFlags::HIDDEN`] flag.
+    hidden_param_hashes: HashSet<u32>,
     /// Mappings from string parameter indentifiers to parameter hashes. Useful for debug logging
     /// and when storing and restoring plugin state.
     param_id_to_hash: HashMap<String, u32>,
@@ -212,6 +287,10 @@ pub struct Wrapper<P: ClapPlugin> {
     /// having to add a setter function to the parameter (or even worse, have it be completely
     /// untyped).
     pub param_ptr_to_hash: HashMap<ParamPtr, u32>,
+    /// The undo history for parameter gestures and persistent field changes made from the
+    /// plugin's editor. This only records anything if the plugin has set
+    /// [`Plugin::UNDO_HISTORY_SIZE`][crate::prelude::Plugin::UNDO_HISTORY_SIZE].
+    pub undo_history: UndoHistory,
     /// A queue of parameter changes and gestures that should be output in either the next process
     /// call or in the next parameter flush.
     ///
@@ -219,12 +298,31 @@ pub struct Wrapper<P: ClapPlugin> {
     ///      even if it does then that should still not be a problem because the host also reads it
     ///      in the same order, right?
     output_parameter_events: ArrayQueue<OutputParamEvent>,
+    /// Parameter changes made from the audio thread through
+    /// [`ProcessContext::set_parameter()`][crate::prelude::ProcessContext::set_parameter()] as
+    /// `(param_hash, clap_plain_value)` pairs. These are sent to the host as complete gestures. This
+    /// is separate from `output_parameter_events` so the GUI thread can't fill up the queue in the
+    /// middle of a gesture. If the editor is currently in a gesture for the same parameter, then
+    /// only the value change is sent.
+    output_parameter_gestures: ArrayQueue<(u32, f64)>,
+    /// The hashes of the parameters the editor has started a gesture for that has not yet ended.
+    /// This is updated while writing `output_parameter_events` to the host's output event queue.
+    /// The set's capacity is reserved for every parameter up front so this never allocates.
+    active_gui_gestures: AtomicRefCell<HashSet<u32>>,
 
     host_thread_check: AtomicRefCell<Option<ClapPtr<clap_host_thread_check>>>,
 
+    clap_plugin_preset_load: clap_plugin_preset_load,
+
     clap_plugin_render: clap_plugin_render,
 
     clap_plugin_state: clap_plugin_state,
+    host_state: AtomicRefCell<Option<ClapPtr<clap_host_state>>>,
+    /// Used to mark the plugin's state as dirty when the plugin sets one of its persistent fields.
+    /// This is polled at the end of every process call.
+    field_change_tracker: FieldChangeTracker,
+
+    clap_plugin_surround: clap_plugin_surround,
 
     clap_plugin_tail: clap_plugin_tail,
 
@@ -250,6 +348,11 @@ pub enum Task {
     LatencyChanged,
     /// Tell the host that it should rescan the current parameter values.
     RescanParamValues,
+    /// Tell the host that the plugin's state has changed and that the project should be saved.
+    MarkDirty,
+    /// Check whether the plugin's persistent fields have changed, and mark the plugin's state as
+    /// dirty if they have. See [`FieldChangeTracker`].
+    CheckFieldChanges,
 }
 
 /// The types of CLAP parameter updates for events.
@@ -342,6 +445,15 @@ impl<P: ClapPlugin> MainThreadExecutor<Task> for Wrapper<P> {
                 }
                 None => nih_debug_assert_failure!("The host does not support parameters? What?"),
             },
+            Task::MarkDirty => match &*self.host_state.borrow() {
+                Some(host_state) => (host_state.mark_dirty)(&*self.host_callback),
+                None => nih_debug_assert_failure!("The host does not support the state extension"),
+            },
+            Task::CheckFieldChanges => {
+                if self.field_change_tracker.check() {
+                    self.execute(Task::MarkDirty);
+                }
+            }
         };
     }
 }
@@ -349,7 +461,8 @@ impl<P: ClapPlugin> MainThreadExecutor<Task> for Wrapper<P> {
 impl<P: ClapPlugin> Wrapper<P> {
     pub fn new(host_callback: *const clap_host) -> Arc<Self> {
         let plugin = P::default();
-        let editor = plugin.editor();
+        let background_thread = BackgroundThread::new_and_spawn(plugin.task_executor());
+        let editor = plugin.editor(background_thread.async_executor());
 
         // This is used to allow the plugin to restore preset data from its editor, see the comment
         // on `Self::updated_state_sender`
@@ -416,6 +529,12 @@ impl<P: ClapPlugin> Wrapper<P> {
             .iter()
             .map(|(_, hash, _, group)| (*hash, group.clone()))
             .collect();
+        let param_groups = params.param_groups();
+        let hidden_param_hashes = param_id_hashes_ptrs_groups
+            .iter()
+            .filter(|(_, _, _, group)| param_groups.iter().any(|g| g.hides(group)))
+            .map(|(_, hash, _, _)| *hash)
+            .collect();
         let param_id_to_hash = param_id_hashes_ptrs_groups
             .iter()
             .map(|(id, hash, _, _)| (id.clone(), *hash))
@@ -425,16 +544,27 @@ impl<P: ClapPlugin> Wrapper<P> {
             .map(|(_, hash, ptr, _)| (ptr, hash))
             .collect();
 
-        // Query all sensible bus configurations supported by the plugin. We don't do surround or
-        // anything beyond stereo right now.
+        // Query all sensible bus configurations supported by the plugin. The main input, if there
+        // is one, always uses the same layout as the main output.
         let mut supported_bus_configs = Vec::new();
-        for num_output_channels in [1, 2] {
-            for num_input_channels in [0, num_output_channels] {
+        for output_layout in [
+            ChannelLayout::Mono,
+            ChannelLayout::Stereo,
+            ChannelLayout::Surround5_1,
+            ChannelLayout::Surround7_1,
+            ChannelLayout::Ambisonic { order: 1 },
+            ChannelLayout::Ambisonic { order: 2 },
+            ChannelLayout::Ambisonic { order: 3 },
+        ] {
+            let num_output_channels = output_layout.num_channels();
+            for input_layout in [ChannelLayout::Discrete(0), output_layout] {
                 #[allow(clippy::single_element_loop)]
                 for num_aux_channels in [num_output_channels] {
                     let bus_config = BusConfig {
-                        num_input_channels,
+                        num_input_channels: input_layout.num_channels(),
                         num_output_channels,
+                        input_layout,
+                        output_layout,
                         // We won't support a variable number of busses until that's required, so
                         // we'll always use the number of auxiliary busses specified by the plugin
                         aux_input_busses: P::DEFAULT_AUX_INPUTS
@@ -462,6 +592,8 @@ impl<P: ClapPlugin> Wrapper<P> {
         let default_bus_config = BusConfig {
             num_input_channels: P::DEFAULT_NUM_INPUTS,
             num_output_channels: P::DEFAULT_NUM_OUTPUTS,
+            input_layout: ChannelLayout::from_num_channels(P::DEFAULT_NUM_INPUTS),
+            output_layout: ChannelLayout::from_num_channels(P::DEFAULT_NUM_OUTPUTS),
             aux_input_busses: P::DEFAULT_AUX_INPUTS.unwrap_or_default(),
             aux_output_busses: P::DEFAULT_AUX_OUTPUTS.unwrap_or_default(),
         };
@@ -471,6 +603,9 @@ impl<P: ClapPlugin> Wrapper<P> {
             supported_bus_configs.push(default_bus_config);
         }
 
+        let field_change_tracker = FieldChangeTracker::new(params.clone());
+        let active_gui_gestures = HashSet::with_capacity(param_hashes.len());
+
         let wrapper = Self {
             clap_plugin: clap_plugin {
                 // This needs to live on the heap because the plugin object contains a direct
@@ -498,6 +633,7 @@ impl<P: ClapPlugin> Wrapper<P> {
             plugin: RwLock::new(plugin),
             params,
             editor,
+            background_thread,
             editor_handle: RwLock::new(None),
             editor_scaling_factor: AtomicF32::new(1.0),
 
@@ -505,6 +641,8 @@ impl<P: ClapPlugin> Wrapper<P> {
             current_bus_config: AtomicCell::new(BusConfig {
                 num_input_channels: P::DEFAULT_NUM_INPUTS,
                 num_output_channels: P::DEFAULT_NUM_OUTPUTS,
+                input_layout: ChannelLayout::from_num_channels(P::DEFAULT_NUM_INPUTS),
+                output_layout: ChannelLayout::from_num_channels(P::DEFAULT_NUM_OUTPUTS),
                 aux_input_busses: P::DEFAULT_AUX_INPUTS.unwrap_or_default(),
                 aux_output_busses: P::DEFAULT_AUX_OUTPUTS.unwrap_or_default(),
             }),
@@ -512,9 +650,18 @@ impl<P: ClapPlugin> Wrapper<P> {
             current_process_mode: AtomicCell::new(ProcessMode::Realtime),
             input_events: AtomicRefCell::new(VecDeque::with_capacity(512)),
             output_events: AtomicRefCell::new(VecDeque::with_capacity(512)),
+            param_changes: AtomicRefCell::new(ParamChangeQueues::new(
+                param_by_hash.values().copied(),
+            )),
+            sysex_storage: AtomicRefCell::new(SysExStorage::new()),
+            mpe_state: AtomicRefCell::new(MpeState::default()),
             last_process_status: AtomicCell::new(ProcessStatus::Normal),
             current_latency: AtomicU32::new(0),
             output_buffer: AtomicRefCell::new(Buffer::default()),
+            aux_input_storage: AtomicRefCell::new(Vec::new()),
+            aux_input_buffers: AtomicRefCell::new(Vec::new()),
+            aux_output_storage: AtomicRefCell::new(Vec::new()),
+            aux_output_buffers: AtomicRefCell::new(Vec::new()),
             updated_state_sender,
             updated_state_receiver,
 
@@ -522,6 +669,10 @@ impl<P: ClapPlugin> Wrapper<P> {
 
             host_callback,
 
+            clap_plugin_ambisonic: clap_plugin_ambisonic {
+                get_info: Self::ext_ambisonic_get_info,
+            },
+
             clap_plugin_audio_ports_config: clap_plugin_audio_ports_config {
                 count: Self::ext_audio_ports_config_count,
                 get: Self::ext_audio_ports_config_get,
@@ -579,12 +730,20 @@ impl<P: ClapPlugin> Wrapper<P> {
             param_hashes,
             param_by_hash,
             param_group_by_hash,
+            hidden_param_hashes,
             param_id_to_hash,
             param_ptr_to_hash,
+            undo_history: UndoHistory::new(P::UNDO_HISTORY_SIZE),
             output_parameter_events: ArrayQueue::new(OUTPUT_EVENT_QUEUE_CAPACITY),
+            output_parameter_gestures: ArrayQueue::new(OUTPUT_EVENT_QUEUE_CAPACITY),
+            active_gui_gestures: AtomicRefCell::new(active_gui_gestures),
 
             host_thread_check: AtomicRefCell::new(None),
 
+            clap_plugin_preset_load: clap_plugin_preset_load {
+                from_file: Self::ext_preset_load_from_file,
+            },
+
             clap_plugin_render: clap_plugin_render {
                 has_hard_realtime_requirement: Self::ext_render_has_hard_realtime_requirement,
                 set: Self::ext_render_set,
@@ -594,6 +753,13 @@ impl<P: ClapPlugin> Wrapper<P> {
                 save: Self::ext_state_save,
                 load: Self::ext_state_load,
             },
+            host_state: AtomicRefCell::new(None),
+            field_change_tracker,
+
+            clap_plugin_surround: clap_plugin_surround {
+                get_channel_map: Self::ext_surround_get_channel_map,
+                changed: Self::ext_surround_changed,
+            },
 
             clap_plugin_tail: clap_plugin_tail {
                 get: Self::ext_tail_get,
@@ -620,6 +786,8 @@ impl<P: ClapPlugin> Wrapper<P> {
             wrapper: self,
             input_events_guard: self.input_events.borrow_mut(),
             output_events_guard: self.output_events.borrow_mut(),
+            param_changes_guard: self.param_changes.borrow(),
+            sysex_storage_guard: self.sysex_storage.borrow_mut(),
             transport,
         }
     }
@@ -633,6 +801,29 @@ impl<P: ClapPlugin> Wrapper<P> {
     /// host (it will still be set on the plugin either way).
     pub fn queue_parameter_event(&self, event: OutputParamEvent) -> bool {
         let result = self.output_parameter_events.push(event).is_ok();
+        self.request_parameter_flush();
+
+        result
+    }
+
+    /// Queue a complete automation gesture for a parameter change made from the audio thread
+    /// through [`ProcessContext::set_parameter()`][crate::prelude::ProcessContext::set_parameter()].
+    /// The begin gesture, value change, and end gesture events are only created when the events are
+    /// written to the host's output event queue, so the host never receives an unfinished gesture.
+    /// Returns `false` if the queue was full, in which case nothing is sent to the host.
+    pub fn queue_parameter_change_gesture(&self, param_hash: u32, clap_plain_value: f64) -> bool {
+        let result = self
+            .output_parameter_gestures
+            .push((param_hash, clap_plain_value))
+            .is_ok();
+        self.request_parameter_flush();
+
+        result
+    }
+
+    /// Request a parameter flush from the host if the plugin is not currently processing audio so
+    /// the queued parameter output events get sent to the host.
+    fn request_parameter_flush(&self) {
         match &*self.host_params.borrow() {
             Some(host_params) if !self.is_processing.load(Ordering::SeqCst) => {
                 unsafe { (host_params.request_flush)(&*self.host_callback) };
@@ -640,8 +831,6 @@ impl<P: ClapPlugin> Wrapper<P> {
             Some(_) => (),
             None => nih_debug_assert_failure!("The host does not support parameters? What?"),
         }
-
-        result
     }
 
     /// If there's an editor open, let it know that parameter values have changed. This should be
@@ -774,12 +963,14 @@ impl<P: ClapPlugin> Wrapper<P> {
         let mut event: *const clap_event_header = ((*in_).get)(&*in_, start_idx);
         let mut parameter_values_changed = false;
         for next_event_idx in (start_idx + 1)..num_events {
-            parameter_values_changed |= self.handle_in_event(
-                event,
-                &mut input_events,
-                Some(transport_info),
-                current_sample_idx,
-            );
+            if !self.queue_param_change(event, current_sample_idx) {
+                parameter_values_changed |= self.handle_in_event(
+                    event,
+                    &mut input_events,
+                    Some(transport_info),
+                    current_sample_idx,
+                );
+            }
 
             // Stop just before the next parameter change or transport information event at a sample
             // after the current sample
@@ -792,12 +983,14 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
 
         // Don't forget about the last event
-        parameter_values_changed |= self.handle_in_event(
-            event,
-            &mut input_events,
-            Some(transport_info),
-            current_sample_idx,
-        );
+        if !self.queue_param_change(event, current_sample_idx) {
+            parameter_values_changed |= self.handle_in_event(
+                event,
+                &mut input_events,
+                Some(transport_info),
+                current_sample_idx,
+            );
+        }
 
         // NOTE: We explicitly did not do this on a block split because that seems a bit excessive.
         //       When we're performing a block split we're guarenteed that there's still at least one more
@@ -818,7 +1011,7 @@ impl<P: ClapPlugin> Wrapper<P> {
         // shouldn't have to think about interleaving events here
         let sample_rate = self.current_buffer_config.load().map(|c| c.sample_rate);
         let mut parameter_values_changed = false;
-        while let Some(change) = self.output_parameter_events.pop() {
+        let mut write_event = |change: OutputParamEvent| {
             let push_succesful = match change {
                 OutputParamEvent::BeginGesture { param_hash } => {
                     let event = clap_event_param_gesture {
@@ -881,6 +1074,36 @@ impl<P: ClapPlugin> Wrapper<P> {
             };
 
             nih_debug_assert!(push_succesful);
+        };
+
+        // Gestures started from the editor are kept track of so changes made from the audio thread
+        // don't start a second, overlapping gesture for the same parameter
+        let mut active_gui_gestures = self.active_gui_gestures.borrow_mut();
+        while let Some(change) = self.output_parameter_events.pop() {
+            match change {
+                OutputParamEvent::BeginGesture { param_hash } => {
+                    active_gui_gestures.insert(param_hash);
+                }
+                OutputParamEvent::EndGesture { param_hash } => {
+                    active_gui_gestures.remove(&param_hash);
+                }
+                OutputParamEvent::SetValue { .. } => (),
+            }
+
+            write_event(change);
+        }
+        while let Some((param_hash, clap_plain_value)) = self.output_parameter_gestures.pop() {
+            let set_value = OutputParamEvent::SetValue {
+                param_hash,
+                clap_plain_value,
+            };
+            if active_gui_gestures.contains(&param_hash) {
+                write_event(set_value);
+            } else {
+                write_event(OutputParamEvent::BeginGesture { param_hash });
+                write_event(set_value);
+                write_event(OutputParamEvent::EndGesture { param_hash });
+            }
         }
 
         // Allow the editor to react to the new parameter values if the editor uses a reactive data
@@ -897,6 +1120,7 @@ impl<P: ClapPlugin> Wrapper<P> {
             let push_successful = match event {
                 NoteEvent::NoteOn {
                     timing: _,
+                    voice_id,
                     channel,
                     note,
                     velocity,
@@ -910,7 +1134,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                             // We don't have a way to denote live events
                             flags: 0,
                         },
-                        note_id: -1,
+                        note_id: voice_id.unwrap_or(-1),
                         port_index: 0,
                         channel: channel as i16,
                         key: note as i16,
@@ -921,6 +1145,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                 }
                 NoteEvent::NoteOff {
                     timing: _,
+                    voice_id,
                     channel,
                     note,
                     velocity,
@@ -933,7 +1158,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                             type_: CLAP_EVENT_NOTE_OFF,
                             flags: 0,
                         },
-                        note_id: -1,
+                        note_id: voice_id.unwrap_or(-1),
                         port_index: 0,
                         channel: channel as i16,
                         key: note as i16,
@@ -942,8 +1167,32 @@ impl<P: ClapPlugin> Wrapper<P> {
 
                     (out.try_push)(out, &event.header)
                 }
+                NoteEvent::VoiceTerminated {
+                    timing: _,
+                    voice_id,
+                    channel,
+                    note,
+                } if P::MIDI_INPUT >= MidiConfig::Basic => {
+                    let event = clap_event_note {
+                        header: clap_event_header {
+                            size: mem::size_of::<clap_event_note>() as u32,
+                            time,
+                            space_id: CLAP_CORE_EVENT_SPACE_ID,
+                            type_: CLAP_EVENT_NOTE_END,
+                            flags: 0,
+                        },
+                        note_id: voice_id.unwrap_or(-1),
+                        port_index: 0,
+                        channel: channel as i16,
+                        key: note as i16,
+                        velocity: 0.0,
+                    };
+
+                    (out.try_push)(out, &event.header)
+                }
                 NoteEvent::PolyPressure {
                     timing: _,
+                    voice_id,
                     channel,
                     note,
                     pressure,
@@ -957,7 +1206,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                             flags: 0,
                         },
                         expression_id: CLAP_NOTE_EXPRESSION_PRESSURE,
-                        note_id: -1,
+                        note_id: voice_id.unwrap_or(-1),
                         port_index: 0,
                         channel: channel as i16,
                         key: note as i16,
@@ -968,6 +1217,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                 }
                 NoteEvent::PolyVolume {
                     timing: _,
+                    voice_id,
                     channel,
                     note,
                     gain,
@@ -981,7 +1231,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                             flags: 0,
                         },
                         expression_id: CLAP_NOTE_EXPRESSION_VOLUME,
-                        note_id: -1,
+                        note_id: voice_id.unwrap_or(-1),
                         port_index: 0,
                         channel: channel as i16,
                         key: note as i16,
@@ -992,6 +1242,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                 }
                 NoteEvent::PolyPan {
                     timing: _,
+                    voice_id,
                     channel,
                     note,
                     pan,
@@ -1005,7 +1256,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                             flags: 0,
                         },
                         expression_id: CLAP_NOTE_EXPRESSION_PAN,
-                        note_id: -1,
+                        note_id: voice_id.unwrap_or(-1),
                         port_index: 0,
                         channel: channel as i16,
                         key: note as i16,
@@ -1016,6 +1267,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                 }
                 NoteEvent::PolyTuning {
                     timing: _,
+                    voice_id,
                     channel,
                     note,
                     tuning,
@@ -1029,7 +1281,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                             flags: 0,
                         },
                         expression_id: CLAP_NOTE_EXPRESSION_TUNING,
-                        note_id: -1,
+                        note_id: voice_id.unwrap_or(-1),
                         port_index: 0,
                         channel: channel as i16,
                         key: note as i16,
@@ -1040,6 +1292,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                 }
                 NoteEvent::PolyVibrato {
                     timing: _,
+                    voice_id,
                     channel,
                     note,
                     vibrato,
@@ -1053,7 +1306,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                             flags: 0,
                         },
                         expression_id: CLAP_NOTE_EXPRESSION_VIBRATO,
-                        note_id: -1,
+                        note_id: voice_id.unwrap_or(-1),
                         port_index: 0,
                         channel: channel as i16,
                         key: note as i16,
@@ -1064,6 +1317,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                 }
                 NoteEvent::PolyExpression {
                     timing: _,
+                    voice_id,
                     channel,
                     note,
                     expression,
@@ -1077,7 +1331,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                             flags: 0,
                         },
                         expression_id: CLAP_NOTE_EXPRESSION_EXPRESSION,
-                        note_id: -1,
+                        note_id: voice_id.unwrap_or(-1),
                         port_index: 0,
                         channel: channel as i16,
                         key: note as i16,
@@ -1088,6 +1342,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                 }
                 NoteEvent::PolyBrightness {
                     timing: _,
+                    voice_id,
                     channel,
                     note,
                     brightness,
@@ -1101,7 +1356,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                             flags: 0,
                         },
                         expression_id: CLAP_NOTE_EXPRESSION_BRIGHTNESS,
-                        note_id: -1,
+                        note_id: voice_id.unwrap_or(-1),
                         port_index: 0,
                         channel: channel as i16,
                         key: note as i16,
@@ -1110,6 +1365,31 @@ impl<P: ClapPlugin> Wrapper<P> {
 
                     (out.try_push)(out, &event.header)
                 }
+                // With the MIDI2 dialect the channel-wide MIDI messages can be sent with a much
+                // higher precision than with MIDI 1.0
+                NoteEvent::MidiChannelPressure { .. }
+                | NoteEvent::MidiPitchBend { .. }
+                | NoteEvent::MidiCC { .. }
+                    if P::CLAP_MIDI2 && P::MIDI_OUTPUT >= MidiConfig::MidiCCs =>
+                {
+                    let data = match midi2::translate_event_reverse(&event) {
+                        Some(data) => data,
+                        None => continue,
+                    };
+                    let event = clap_event_midi2 {
+                        header: clap_event_header {
+                            size: mem::size_of::<clap_event_midi2>() as u32,
+                            time,
+                            space_id: CLAP_CORE_EVENT_SPACE_ID,
+                            type_: CLAP_EVENT_MIDI2,
+                            flags: 0,
+                        },
+                        port_index: 0,
+                        data,
+                    };
+
+                    (out.try_push)(out, &event.header)
+                }
                 NoteEvent::MidiChannelPressure {
                     timing: _,
                     channel,
@@ -1182,6 +1462,38 @@ impl<P: ClapPlugin> Wrapper<P> {
 
                     (out.try_push)(out, &event.header)
                 }
+                NoteEvent::MidiSysEx { timing: _, message }
+                    if P::MIDI_OUTPUT >= MidiConfig::MidiCCs =>
+                {
+                    // The event only points to the message's data, so the message is stored in
+                    // the wrapper until the host has had a chance to read it
+                    let mut sysex_storage = self.sysex_storage.borrow_mut();
+                    let data = match sysex_storage.get_pinned(&message) {
+                        Some(data) => data,
+                        None => {
+                            nih_debug_assert_failure!(
+                                "The SysEx storage is full, dropping a {} byte message",
+                                message.len()
+                            );
+                            continue;
+                        }
+                    };
+
+                    let event = clap_event_midi_sysex {
+                        header: clap_event_header {
+                            size: mem::size_of::<clap_event_midi_sysex>() as u32,
+                            time,
+                            space_id: CLAP_CORE_EVENT_SPACE_ID,
+                            type_: CLAP_EVENT_MIDI_SYSEX,
+                            flags: 0,
+                        },
+                        port_index: 0,
+                        buffer: data.as_ptr(),
+                        size: data.len() as u32,
+                    };
+
+                    (out.try_push)(out, &event.header)
+                }
                 _ => {
                     nih_debug_assert_failure!(
                         "Invalid output event for the current MIDI_OUTPUT setting"
@@ -1224,18 +1536,59 @@ impl<P: ClapPlugin> Wrapper<P> {
                     ClapParamUpdate::PlainValueSet(event.value),
                     self.current_buffer_config.load().map(|c| c.sample_rate),
                 );
+                self.push_mono_automation_event(
+                    input_events,
+                    event.param_id,
+                    raw_event.time - current_sample_idx as u32,
+                );
 
                 true
             }
             (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_MOD) => {
                 let event = &*(event as *const clap_event_param_mod);
-                self.update_plain_value_by_hash(
-                    event.param_id,
-                    ClapParamUpdate::PlainValueMod(event.amount),
-                    self.current_buffer_config.load().map(|c| c.sample_rate),
-                );
+                let poly_modulation_event =
+                    self.param_by_hash
+                        .get(&event.param_id)
+                        .and_then(|param_ptr| {
+                            poly_modulation::translate_param_mod(
+                                raw_event.time - current_sample_idx as u32,
+                                event.param_id,
+                                *param_ptr,
+                                event.note_id,
+                                event.amount,
+                            )
+                        });
+                match poly_modulation_event {
+                    // Modulation events that target a specific voice are passed to the plugin
+                    // instead of modulating the parameter itself
+                    Some(poly_modulation_event) => {
+                        input_events.push_back(poly_modulation_event);
+
+                        false
+                    }
+                    None => {
+                        // Modulating all voices for a key or a channel is deliberately not
+                        // supported, and we only set `CLAP_PARAM_IS_MODULATABLE_PER_NOTE_ID` so
+                        // hosts shouldn't send these
+                        nih_debug_assert!(
+                            event.note_id == -1 && event.key == -1 && event.channel == -1,
+                            "The host sent a polyphonic modulation event the parameter does not \
+                             support, treating it as monophonic modulation"
+                        );
+                        self.update_plain_value_by_hash(
+                            event.param_id,
+                            ClapParamUpdate::PlainValueMod(event.amount),
+                            self.current_buffer_config.load().map(|c| c.sample_rate),
+                        );
+                        self.push_mono_automation_event(
+                            input_events,
+                            event.param_id,
+                            raw_event.time - current_sample_idx as u32,
+                        );
 
-                true
+                        true
+                    }
+                }
             }
             (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_TRANSPORT) => {
                 let event = &*(event as *const clap_event_transport);
@@ -1248,14 +1601,18 @@ impl<P: ClapPlugin> Wrapper<P> {
             (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_NOTE_ON) => {
                 if P::MIDI_INPUT >= MidiConfig::Basic {
                     let event = &*(event as *const clap_event_note);
-                    input_events.push_back(NoteEvent::NoteOn {
-                        // When splitting up the buffer for sample accurate automation all events
-                        // should be relative to the block
-                        timing: raw_event.time - current_sample_idx as u32,
-                        channel: event.channel as u8,
-                        note: event.key as u8,
-                        velocity: event.velocity as f32,
-                    });
+                    self.push_input_event(
+                        input_events,
+                        NoteEvent::NoteOn {
+                            // When splitting up the buffer for sample accurate automation all events
+                            // should be relative to the block
+                            timing: raw_event.time - current_sample_idx as u32,
+                            voice_id: voice_id_from_note_id(event.note_id),
+                            channel: event.channel as u8,
+                            note: event.key as u8,
+                            velocity: event.velocity as f32,
+                        },
+                    );
                 }
 
                 false
@@ -1263,12 +1620,16 @@ impl<P: ClapPlugin> Wrapper<P> {
             (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_NOTE_OFF) => {
                 if P::MIDI_INPUT >= MidiConfig::Basic {
                     let event = &*(event as *const clap_event_note);
-                    input_events.push_back(NoteEvent::NoteOff {
-                        timing: raw_event.time - current_sample_idx as u32,
-                        channel: event.channel as u8,
-                        note: event.key as u8,
-                        velocity: event.velocity as f32,
-                    });
+                    self.push_input_event(
+                        input_events,
+                        NoteEvent::NoteOff {
+                            timing: raw_event.time - current_sample_idx as u32,
+                            voice_id: voice_id_from_note_id(event.note_id),
+                            channel: event.channel as u8,
+                            note: event.key as u8,
+                            velocity: event.velocity as f32,
+                        },
+                    );
                 }
 
                 false
@@ -1279,61 +1640,89 @@ impl<P: ClapPlugin> Wrapper<P> {
                     let event = &*(event as *const clap_event_note_expression);
                     match event.expression_id {
                         CLAP_NOTE_EXPRESSION_PRESSURE => {
-                            input_events.push_back(NoteEvent::PolyPressure {
-                                timing: raw_event.time - current_sample_idx as u32,
-                                channel: event.channel as u8,
-                                note: event.key as u8,
-                                pressure: event.value as f32,
-                            });
+                            self.push_input_event(
+                                input_events,
+                                NoteEvent::PolyPressure {
+                                    timing: raw_event.time - current_sample_idx as u32,
+                                    voice_id: voice_id_from_note_id(event.note_id),
+                                    channel: event.channel as u8,
+                                    note: event.key as u8,
+                                    pressure: event.value as f32,
+                                },
+                            );
                         }
                         CLAP_NOTE_EXPRESSION_VOLUME => {
-                            input_events.push_back(NoteEvent::PolyVolume {
-                                timing: raw_event.time - current_sample_idx as u32,
-                                channel: event.channel as u8,
-                                note: event.key as u8,
-                                gain: event.value as f32,
-                            });
+                            self.push_input_event(
+                                input_events,
+                                NoteEvent::PolyVolume {
+                                    timing: raw_event.time - current_sample_idx as u32,
+                                    voice_id: voice_id_from_note_id(event.note_id),
+                                    channel: event.channel as u8,
+                                    note: event.key as u8,
+                                    gain: event.value as f32,
+                                },
+                            );
                         }
                         CLAP_NOTE_EXPRESSION_PAN => {
-                            input_events.push_back(NoteEvent::PolyPan {
-                                timing: raw_event.time - current_sample_idx as u32,
-                                channel: event.channel as u8,
-                                note: event.key as u8,
-                                // In CLAP this value goes from [0, 1] instead of [-1, 1]
-                                pan: (event.value as f32 * 2.0) - 1.0,
-                            });
+                            self.push_input_event(
+                                input_events,
+                                NoteEvent::PolyPan {
+                                    timing: raw_event.time - current_sample_idx as u32,
+                                    voice_id: voice_id_from_note_id(event.note_id),
+                                    channel: event.channel as u8,
+                                    note: event.key as u8,
+                                    // In CLAP this value goes from [0, 1] instead of [-1, 1]
+                                    pan: (event.value as f32 * 2.0) - 1.0,
+                                },
+                            );
                         }
                         CLAP_NOTE_EXPRESSION_TUNING => {
-                            input_events.push_back(NoteEvent::PolyTuning {
-                                timing: raw_event.time - current_sample_idx as u32,
-                                channel: event.channel as u8,
-                                note: event.key as u8,
-                                tuning: event.value as f32,
-                            });
+                            self.push_input_event(
+                                input_events,
+                                NoteEvent::PolyTuning {
+                                    timing: raw_event.time - current_sample_idx as u32,
+                                    voice_id: voice_id_from_note_id(event.note_id),
+                                    channel: event.channel as u8,
+                                    note: event.key as u8,
+                                    tuning: event.value as f32,
+                                },
+                            );
                         }
                         CLAP_NOTE_EXPRESSION_VIBRATO => {
-                            input_events.push_back(NoteEvent::PolyVibrato {
-                                timing: raw_event.time - current_sample_idx as u32,
-                                channel: event.channel as u8,
-                                note: event.key as u8,
-                                vibrato: event.value as f32,
-                            });
+                            self.push_input_event(
+                                input_events,
+                                NoteEvent::PolyVibrato {
+                                    timing: raw_event.time - current_sample_idx as u32,
+                                    voice_id: voice_id_from_note_id(event.note_id),
+                                    channel: event.channel as u8,
+                                    note: event.key as u8,
+                                    vibrato: event.value as f32,
+                                },
+                            );
                         }
                         CLAP_NOTE_EXPRESSION_EXPRESSION => {
-                            input_events.push_back(NoteEvent::PolyExpression {
-                                timing: raw_event.time - current_sample_idx as u32,
-                                channel: event.channel as u8,
-                                note: event.key as u8,
-                                expression: event.value as f32,
-                            });
+                            self.push_input_event(
+                                input_events,
+                                NoteEvent::PolyExpression {
+                                    timing: raw_event.time - current_sample_idx as u32,
+                                    voice_id: voice_id_from_note_id(event.note_id),
+                                    channel: event.channel as u8,
+                                    note: event.key as u8,
+                                    expression: event.value as f32,
+                                },
+                            );
                         }
                         CLAP_NOTE_EXPRESSION_BRIGHTNESS => {
-                            input_events.push_back(NoteEvent::PolyBrightness {
-                                timing: raw_event.time - current_sample_idx as u32,
-                                channel: event.channel as u8,
-                                note: event.key as u8,
-                                brightness: event.value as f32,
-                            });
+                            self.push_input_event(
+                                input_events,
+                                NoteEvent::PolyBrightness {
+                                    timing: raw_event.time - current_sample_idx as u32,
+                                    voice_id: voice_id_from_note_id(event.note_id),
+                                    channel: event.channel as u8,
+                                    note: event.key as u8,
+                                    brightness: event.value as f32,
+                                },
+                            );
                         }
                         n => nih_debug_assert_failure!("Unhandled note expression ID {}", n),
                     }
@@ -1355,54 +1744,75 @@ impl<P: ClapPlugin> Wrapper<P> {
                     match event_type {
                         // Hosts shouldn't be sending this, bu twe'll handle it just in case
                         midi::NOTE_ON => {
-                            input_events.push_back(NoteEvent::NoteOn {
-                                timing: raw_event.time - current_sample_idx as u32,
-                                channel,
-                                note: event.data[1],
-                                velocity: event.data[2] as f32 / 127.0,
-                            });
+                            self.push_input_event(
+                                input_events,
+                                NoteEvent::NoteOn {
+                                    timing: raw_event.time - current_sample_idx as u32,
+                                    voice_id: None,
+                                    channel,
+                                    note: event.data[1],
+                                    velocity: event.data[2] as f32 / 127.0,
+                                },
+                            );
                         }
                         // Hosts shouldn't be sending this, bu twe'll handle it just in case
                         midi::NOTE_OFF => {
-                            input_events.push_back(NoteEvent::NoteOff {
-                                timing: raw_event.time - current_sample_idx as u32,
-                                channel,
-                                note: event.data[1],
-                                velocity: event.data[2] as f32 / 127.0,
-                            });
+                            self.push_input_event(
+                                input_events,
+                                NoteEvent::NoteOff {
+                                    timing: raw_event.time - current_sample_idx as u32,
+                                    voice_id: None,
+                                    channel,
+                                    note: event.data[1],
+                                    velocity: event.data[2] as f32 / 127.0,
+                                },
+                            );
                         }
                         // Hosts shouldn't be sending this, bu twe'll handle it just in case
                         midi::POLYPHONIC_KEY_PRESSURE => {
-                            input_events.push_back(NoteEvent::PolyPressure {
-                                timing: raw_event.time - current_sample_idx as u32,
-                                channel,
-                                note: event.data[1],
-                                pressure: event.data[2] as f32 / 127.0,
-                            });
+                            self.push_input_event(
+                                input_events,
+                                NoteEvent::PolyPressure {
+                                    timing: raw_event.time - current_sample_idx as u32,
+                                    voice_id: None,
+                                    channel,
+                                    note: event.data[1],
+                                    pressure: event.data[2] as f32 / 127.0,
+                                },
+                            );
                         }
                         midi::CHANNEL_KEY_PRESSURE => {
-                            input_events.push_back(NoteEvent::MidiChannelPressure {
-                                timing: raw_event.time - current_sample_idx as u32,
-                                channel,
-                                pressure: event.data[1] as f32 / 127.0,
-                            });
+                            self.push_input_event(
+                                input_events,
+                                NoteEvent::MidiChannelPressure {
+                                    timing: raw_event.time - current_sample_idx as u32,
+                                    channel,
+                                    pressure: event.data[1] as f32 / 127.0,
+                                },
+                            );
                         }
                         midi::PITCH_BEND_CHANGE => {
-                            input_events.push_back(NoteEvent::MidiPitchBend {
-                                timing: raw_event.time - current_sample_idx as u32,
-                                channel,
-                                value: (event.data[1] as u16 + ((event.data[2] as u16) << 7))
-                                    as f32
-                                    / ((1 << 14) - 1) as f32,
-                            });
+                            self.push_input_event(
+                                input_events,
+                                NoteEvent::MidiPitchBend {
+                                    timing: raw_event.time - current_sample_idx as u32,
+                                    channel,
+                                    value: (event.data[1] as u16 + ((event.data[2] as u16) << 7))
+                                        as f32
+                                        / ((1 << 14) - 1) as f32,
+                                },
+                            );
                         }
                         midi::CONTROL_CHANGE => {
-                            input_events.push_back(NoteEvent::MidiCC {
-                                timing: raw_event.time - current_sample_idx as u32,
-                                channel,
-                                cc: event.data[1],
-                                value: event.data[2] as f32 / 127.0,
-                            });
+                            self.push_input_event(
+                                input_events,
+                                NoteEvent::MidiCC {
+                                    timing: raw_event.time - current_sample_idx as u32,
+                                    channel,
+                                    cc: event.data[1],
+                                    value: event.data[2] as f32 / 127.0,
+                                },
+                            );
                         }
                         n => nih_debug_assert_failure!("Unhandled MIDI message type {}", n),
                     };
@@ -1410,6 +1820,57 @@ impl<P: ClapPlugin> Wrapper<P> {
 
                 false
             }
+            (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_MIDI2) => {
+                if P::CLAP_MIDI2 && P::MIDI_INPUT >= MidiConfig::Basic {
+                    let event = &*(event as *const clap_event_midi2);
+                    match midi2::translate_event(
+                        raw_event.time - current_sample_idx as u32,
+                        &event.data,
+                    ) {
+                        // Channel-wide messages are only passed through to plugins that asked for
+                        // them, just like with the MIDI 1.0 dialect
+                        Some(
+                            NoteEvent::MidiChannelPressure { .. }
+                            | NoteEvent::MidiPitchBend { .. }
+                            | NoteEvent::MidiCC { .. },
+                        ) if P::MIDI_INPUT < MidiConfig::MidiCCs => (),
+                        Some(event) => self.push_input_event(input_events, event),
+                        None => nih_debug_assert_failure!(
+                            "Unhandled MIDI2 message {:#010x}",
+                            event.data[0]
+                        ),
+                    }
+                }
+
+                false
+            }
+            (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_MIDI_SYSEX) => {
+                if P::MIDI_INPUT >= MidiConfig::MidiCCs {
+                    let event = &*(event as *const clap_event_midi_sysex);
+                    if event.buffer.is_null() {
+                        nih_debug_assert_failure!("The host sent a SysEx event without any data");
+                        return false;
+                    }
+
+                    let data = std::slice::from_raw_parts(event.buffer, event.size as usize);
+                    let message = self.sysex_storage.borrow_mut().push(data);
+                    match message {
+                        Some(message) => self.push_input_event(
+                            input_events,
+                            NoteEvent::MidiSysEx {
+                                timing: raw_event.time - current_sample_idx as u32,
+                                message,
+                            },
+                        ),
+                        None => nih_debug_assert_failure!(
+                            "The SysEx storage is full, dropping a {} byte message",
+                            data.len()
+                        ),
+                    }
+                }
+
+                false
+            }
             // TODO: Make sure this only gets logged in debug mode
             _ => {
                 nih_log!(
@@ -1423,12 +1884,80 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
+    /// If the parameter can be modulated polyphonically, then send the parameter's new normalized
+    /// value to the plugin as a [`NoteEvent::MonoAutomation`] event. This needs to be called after
+    /// the parameter's value has been updated.
+    unsafe fn push_mono_automation_event(
+        &self,
+        input_events: &mut VecDeque<NoteEvent>,
+        param_hash: u32,
+        timing: u32,
+    ) {
+        if let Some(event) = self.param_by_hash.get(&param_hash).and_then(|param_ptr| {
+            poly_modulation::mono_automation_event(timing, param_hash, *param_ptr)
+        }) {
+            input_events.push_back(event);
+        }
+    }
+
+    /// If `P::QUEUED_AUTOMATION` is enabled and `event` is a parameter value change that occurs
+    /// after `current_sample_idx`, then the change is added to that parameter's queue in
+    /// `param_changes` instead of being applied to the parameter. Returns `true` if the event was
+    /// queued, in which case it should not be handled any further.
+    unsafe fn queue_param_change(
+        &self,
+        event: *const clap_event_header,
+        current_sample_idx: usize,
+    ) -> bool {
+        let raw_event = &*event;
+        if !P::QUEUED_AUTOMATION
+            || !matches!(
+                (raw_event.space_id, raw_event.type_),
+                (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE)
+            )
+            || raw_event.time <= current_sample_idx as u32
+        {
+            return false;
+        }
+
+        let event = &*(event as *const clap_event_param_value);
+        match self.param_by_hash.get(&event.param_id) {
+            Some(param_ptr) => {
+                let normalized_value =
+                    event.value as f32 / param_ptr.step_count().unwrap_or(1) as f32;
+                self.param_changes.borrow_mut().push(
+                    *param_ptr,
+                    ParamChange {
+                        timing: raw_event.time - current_sample_idx as u32,
+                        normalized_value,
+                    },
+                );
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Add an event to the plugin's input event queue. If `P::MPE_INPUT` is enabled, then the
+    /// event is first passed through the MPE state tracker, which may convert MIDI messages sent on
+    /// MPE member channels to polyphonic expression events.
+    fn push_input_event(&self, input_events: &mut VecDeque<NoteEvent>, event: NoteEvent) {
+        if P::MPE_INPUT && P::MIDI_INPUT >= MidiConfig::MidiCCs {
+            self.mpe_state
+                .borrow_mut()
+                .process_event(event, |event| input_events.push_back(event));
+        } else {
+            input_events.push_back(event);
+        }
+    }
+
     /// Get the plugin's state object, may be called by the plugin's GUI as part of its own preset
     /// management. The wrapper doesn't use these functions and serializes and deserializes directly
-    /// the JSON in the relevant plugin API methods instead.
+    /// the state in the relevant plugin API methods instead.
     pub fn get_state_object(&self) -> PluginState {
         unsafe {
-            state::serialize_object(
+            state::serialize_object::<P>(
                 self.params.clone(),
                 state::make_params_iter(&self.param_by_hash, &self.param_id_to_hash),
             )
@@ -1439,6 +1968,8 @@ impl<P: ClapPlugin> Wrapper<P> {
     /// prevent corrupting data and changing parameters during processing the actual state is only
     /// updated at the end of the audio processing cycle.
     pub fn set_state_object(&self, mut state: PluginState) {
+        state::prepare_state_restore::<P>(&mut state, &self.undo_history);
+
         // Use a loop and timeouts to handle the super rare edge case when this function gets called
         // between a process call and the host disabling the plugin
         loop {
@@ -1496,6 +2027,8 @@ impl<P: ClapPlugin> Wrapper<P> {
             }
         }
 
+        self.field_change_tracker.reset();
+
         // After the state has been updated, notify the host about the new parameter values
         let task_posted = self.do_maybe_async(Task::RescanParamValues);
         nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
@@ -1512,6 +2045,8 @@ impl<P: ClapPlugin> Wrapper<P> {
             query_host_extension::<clap_host_latency>(&wrapper.host_callback, CLAP_EXT_LATENCY);
         *wrapper.host_params.borrow_mut() =
             query_host_extension::<clap_host_params>(&wrapper.host_callback, CLAP_EXT_PARAMS);
+        *wrapper.host_state.borrow_mut() =
+            query_host_extension::<clap_host_state>(&wrapper.host_callback, CLAP_EXT_STATE);
         *wrapper.host_thread_check.borrow_mut() = query_host_extension::<clap_host_thread_check>(
             &wrapper.host_callback,
             CLAP_EXT_THREAD_CHECK,
@@ -1546,6 +2081,10 @@ impl<P: ClapPlugin> Wrapper<P> {
             param.update_smoother(buffer_config.sample_rate, true);
         }
 
+        if P::QUEUED_AUTOMATION {
+            wrapper.param_changes.borrow_mut().reset(max_frames_count);
+        }
+
         let mut plugin = wrapper.plugin.write();
         if plugin.initialize(
             &bus_config,
@@ -1564,7 +2103,50 @@ impl<P: ClapPlugin> Wrapper<P> {
                     output_slices.resize_with(bus_config.num_output_channels as usize, || &mut [])
                 });
 
-            // TODO: Allocate auxiliary IO buffers
+            // The sidechain inputs are copied to owned buffers before being passed to the plugin,
+            // and disconnected auxiliary outputs are written to scratch buffers
+            let aux_input_storage = vec![
+                vec![
+                    vec![0.0; max_frames_count as usize];
+                    bus_config.aux_input_busses.num_channels as usize
+                ];
+                bus_config.aux_input_busses.num_busses as usize
+            ];
+            let aux_output_storage = vec![
+                vec![
+                    vec![0.0; max_frames_count as usize];
+                    bus_config.aux_output_busses.num_channels as usize
+                ];
+                bus_config.aux_output_busses.num_busses as usize
+            ];
+            *wrapper.aux_input_storage.borrow_mut() = aux_input_storage;
+            *wrapper.aux_output_storage.borrow_mut() = aux_output_storage;
+
+            // The slices in these buffers are updated during every process call
+            let mut aux_input_buffers = wrapper.aux_input_buffers.borrow_mut();
+            aux_input_buffers.clear();
+            for _ in 0..bus_config.aux_input_busses.num_busses {
+                let mut buffer = Buffer::default();
+                buffer.with_raw_vec(|channel_slices| {
+                    channel_slices
+                        .resize_with(bus_config.aux_input_busses.num_channels as usize, || {
+                            &mut []
+                        })
+                });
+                aux_input_buffers.push(buffer);
+            }
+            let mut aux_output_buffers = wrapper.aux_output_buffers.borrow_mut();
+            aux_output_buffers.clear();
+            for _ in 0..bus_config.aux_output_busses.num_busses {
+                let mut buffer = Buffer::default();
+                buffer.with_raw_vec(|channel_slices| {
+                    channel_slices
+                        .resize_with(bus_config.aux_output_busses.num_channels as usize, || {
+                            &mut []
+                        })
+                });
+                aux_output_buffers.push(buffer);
+            }
 
             // Also store this for later, so we can reinitialize the plugin after restoring state
             wrapper.current_buffer_config.store(Some(buffer_config));
@@ -1595,6 +2177,7 @@ impl<P: ClapPlugin> Wrapper<P> {
         // To be consistent with the VST3 wrapper, we'll also reset the buffers here in addition to
         // the dedicated `reset()` function.
         process_wrapper(|| wrapper.plugin.write().reset());
+        wrapper.mpe_state.borrow_mut().reset();
 
         true
     }
@@ -1611,6 +2194,7 @@ impl<P: ClapPlugin> Wrapper<P> {
         let wrapper = &*(plugin as *const Self);
 
         process_wrapper(|| wrapper.plugin.write().reset());
+        wrapper.mpe_state.borrow_mut().reset();
     }
 
     unsafe extern "C" fn process(
@@ -1620,8 +2204,6 @@ impl<P: ClapPlugin> Wrapper<P> {
         check_null_ptr!(CLAP_PROCESS_ERROR, plugin, process);
         let wrapper = &*(plugin as *const Self);
 
-        // TODO: Support auxiliary IO and optional main IO, this is still the old version that assumes main IO and nothing else
-
         // Panic on allocations if the `assert_process_allocs` feature has been enabled, and make
         // sure that FTZ is set up correctly
         process_wrapper(|| {
@@ -1630,6 +2212,9 @@ impl<P: ClapPlugin> Wrapper<P> {
             // we'll process every incoming event.
             let process = &*process;
 
+            // The SysEx messages from the previous cycle are no longer referenced by the host
+            wrapper.sysex_storage.borrow_mut().clear();
+
             // If `P::SAMPLE_ACCURATE_AUTOMATION` is set, then we'll split up the audio buffer into
             // chunks whenever a parameter change occurs
             let mut block_start = 0;
@@ -1652,7 +2237,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                             // signature, or position changes), and also split on parameter value
                             // changes after the current sample if sample accurate automation is
                             // enabled
-                            if P::SAMPLE_ACCURATE_AUTOMATION {
+                            if P::SAMPLE_ACCURATE_AUTOMATION && !P::QUEUED_AUTOMATION {
                                 matches!(
                                     ((*next_event).space_id, (*next_event).type_,),
                                     (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE)
@@ -1681,20 +2266,21 @@ impl<P: ClapPlugin> Wrapper<P> {
                     }
                 }
 
-                // The setups we suppport are:
-                // - 1 input bus
-                // - 1 output bus
-                // - 1 input bus and 1 output bus
-                // - 1 input bus and 1 output bus
-                //
-                // Depending on the host either of these may also be missing if the number of
-                // channels is set to 0.
+                // The main input and output ports always come first if the plugin has them, and
+                // any auxiliary ports follow after that
+                let bus_config = wrapper.current_bus_config.load();
+                let has_main_input = bus_config.num_input_channels > 0;
+                let has_main_output = bus_config.num_output_channels > 0;
+                let aux_input_start_idx = if has_main_input { 1 } else { 0 };
+                let aux_output_start_idx = if has_main_output { 1 } else { 0 };
                 nih_debug_assert!(
-                    process.audio_inputs_count <= 1 && process.audio_outputs_count <= 1,
-                    "The host provides more than one input or output bus"
+                    process.audio_inputs_count
+                        <= aux_input_start_idx + bus_config.aux_input_busses.num_busses
+                        && process.audio_outputs_count
+                            <= aux_output_start_idx + bus_config.aux_output_busses.num_busses,
+                    "The host provides more input or output busses than expected"
                 );
 
-                // Right now we don't handle any auxiliary outputs
                 // This vector has been preallocated to contain enough slices as there are output
                 // channels. If the host does not provide outputs or if it does not provide the
                 // required number of channels (should not happen, but Ableton Live does this for
@@ -1708,7 +2294,9 @@ impl<P: ClapPlugin> Wrapper<P> {
                     // Buffers for zero-channel plugins like note effects should always be allowed
                     buffer_is_valid = output_slices.is_empty();
 
-                    if !process.audio_outputs.is_null()
+                    if has_main_output
+                        && process.audio_outputs_count > 0
+                        && !process.audio_outputs.is_null()
                         && !(*process.audio_outputs).data32.is_null()
                     {
                         let audio_outputs = &*process.audio_outputs;
@@ -1743,12 +2331,15 @@ impl<P: ClapPlugin> Wrapper<P> {
                 // Some hosts process data in place, in which case we don't need to do any copying
                 // ourselves. If the pointers do not alias, then we'll do the copy here and then the
                 // plugin can just do normal in place processing.
-                if !process.audio_outputs.is_null()
+                if has_main_input
+                    && has_main_output
+                    && process.audio_outputs_count > 0
+                    && !process.audio_outputs.is_null()
                     && !(*process.audio_outputs).data32.is_null()
+                    && process.audio_inputs_count > 0
                     && !process.audio_inputs.is_null()
                     && !(*process.audio_inputs).data32.is_null()
                 {
-                    // We currently don't support sidechain inputs
                     let audio_outputs = &*process.audio_outputs;
                     let audio_inputs = &*process.audio_inputs;
                     let num_output_channels = audio_outputs.channel_count as usize;
@@ -1771,6 +2362,99 @@ impl<P: ClapPlugin> Wrapper<P> {
                     }
                 }
 
+                // The sidechain inputs are copied to our own storage so the plugin can freely
+                // modify them. Inputs the host didn't connect are filled with silence.
+                let mut aux_input_storage = wrapper.aux_input_storage.borrow_mut();
+                let mut aux_input_buffers = wrapper.aux_input_buffers.borrow_mut();
+                for (aux_input_idx, (storage, buffer)) in aux_input_storage
+                    .iter_mut()
+                    .zip(aux_input_buffers.iter_mut())
+                    .enumerate()
+                {
+                    let host_input_idx = aux_input_idx as u32 + aux_input_start_idx;
+                    let host_input = if host_input_idx < process.audio_inputs_count
+                        && !process.audio_inputs.is_null()
+                        && !(*process.audio_inputs.add(host_input_idx as usize))
+                            .data32
+                            .is_null()
+                    {
+                        Some(&*process.audio_inputs.add(host_input_idx as usize))
+                    } else {
+                        None
+                    };
+
+                    for (channel_idx, channel_storage) in storage.iter_mut().enumerate() {
+                        let channel_storage = &mut channel_storage[..block_end - block_start];
+                        match host_input {
+                            Some(host_input) if channel_idx < host_input.channel_count as usize => {
+                                let input_channel_ptr = *(host_input.data32).add(channel_idx);
+                                channel_storage.copy_from_slice(std::slice::from_raw_parts(
+                                    input_channel_ptr.add(block_start),
+                                    block_end - block_start,
+                                ));
+                            }
+                            _ => channel_storage.fill(0.0),
+                        }
+                    }
+
+                    buffer.with_raw_vec(|channel_slices| {
+                        for (channel_slice, channel_storage) in
+                            channel_slices.iter_mut().zip(storage.iter_mut())
+                        {
+                            // SAFETY: The storage outlives the process call and it is not resized
+                            //         until the plugin gets reactivated
+                            *channel_slice = std::slice::from_raw_parts_mut(
+                                channel_storage.as_mut_ptr(),
+                                block_end - block_start,
+                            );
+                        }
+                    });
+                }
+
+                // Auxiliary outputs point directly to the host's buffers, or to our scratch
+                // storage if the host didn't connect them
+                let mut aux_output_storage = wrapper.aux_output_storage.borrow_mut();
+                let mut aux_output_buffers = wrapper.aux_output_buffers.borrow_mut();
+                for (aux_output_idx, (storage, buffer)) in aux_output_storage
+                    .iter_mut()
+                    .zip(aux_output_buffers.iter_mut())
+                    .enumerate()
+                {
+                    let host_output_idx = aux_output_idx as u32 + aux_output_start_idx;
+                    let host_output = if host_output_idx < process.audio_outputs_count
+                        && !process.audio_outputs.is_null()
+                        && !(*process.audio_outputs.add(host_output_idx as usize))
+                            .data32
+                            .is_null()
+                    {
+                        Some(&*process.audio_outputs.add(host_output_idx as usize))
+                    } else {
+                        None
+                    };
+
+                    buffer.with_raw_vec(|channel_slices| {
+                        for (channel_idx, (channel_slice, channel_storage)) in channel_slices
+                            .iter_mut()
+                            .zip(storage.iter_mut())
+                            .enumerate()
+                        {
+                            let channel_ptr = match host_output {
+                                Some(host_output)
+                                    if channel_idx < host_output.channel_count as usize =>
+                                {
+                                    (*(host_output.data32 as *mut *mut f32).add(channel_idx))
+                                        .add(block_start)
+                                }
+                                _ => channel_storage.as_mut_ptr(),
+                            };
+                            *channel_slice = std::slice::from_raw_parts_mut(
+                                channel_ptr,
+                                block_end - block_start,
+                            );
+                        }
+                    });
+                }
+
                 // Some of the fields are left empty because CLAP does not provide this information, but
                 // the methods on [`Transport`] can reconstruct these values from the other fields
                 let sample_rate = wrapper
@@ -1865,7 +2549,17 @@ impl<P: ClapPlugin> Wrapper<P> {
                 let result = if buffer_is_valid {
                     let mut plugin = wrapper.plugin.write();
                     let mut context = wrapper.make_process_context(transport);
-                    let result = plugin.process(&mut output_buffer, &mut context);
+                    let mut aux = AuxiliaryBuffers {
+                        // SAFETY: The buffers' lifetimes are only extended to `'static` because
+                        //         they're stored in the wrapper. The slices are only valid for
+                        //         the duration of this process call.
+                        inputs: &mut *(aux_input_buffers.as_mut_slice() as *mut [Buffer<'static>]
+                            as *mut [Buffer]),
+                        outputs: &mut *(aux_output_buffers.as_mut_slice()
+                            as *mut [Buffer<'static>]
+                            as *mut [Buffer]),
+                    };
+                    let result = plugin.process(&mut output_buffer, &mut aux, &mut context);
                     wrapper.last_process_status.store(result);
                     result
                 } else {
@@ -1889,6 +2583,14 @@ impl<P: ClapPlugin> Wrapper<P> {
                     wrapper.handle_out_events(&*process.out_events, block_start);
                 }
 
+                // Parameter changes that were queued during this block only take effect now
+                if P::QUEUED_AUTOMATION {
+                    let sample_rate = wrapper.current_buffer_config.load().map(|c| c.sample_rate);
+                    if wrapper.param_changes.borrow_mut().apply(sample_rate) {
+                        wrapper.notify_param_values_changed();
+                    }
+                }
+
                 // If our block ends at the end of the buffer then that means there are no more
                 // unprocessed (parameter) events. If there are more events, we'll just keep going
                 // through this process until we've processed the entire buffer.
@@ -1899,6 +2601,14 @@ impl<P: ClapPlugin> Wrapper<P> {
                 }
             };
 
+            // Persistent fields may have been set since the last process call. Finding out whether
+            // this instance's fields were the ones that changed requires serializing them, so that
+            // check happens on the main thread.
+            if wrapper.field_change_tracker.should_check() {
+                let task_posted = wrapper.do_maybe_async(Task::CheckFieldChanges);
+                nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+            }
+
             // After processing audio, we'll check if the editor has sent us updated plugin state.
             // We'll restore that here on the audio thread to prevent changing the values during the
             // process call and also to prevent inconsistent state when the host also wants to load
@@ -1956,7 +2666,9 @@ impl<P: ClapPlugin> Wrapper<P> {
 
         let id = CStr::from_ptr(id);
 
-        if id == CStr::from_ptr(CLAP_EXT_AUDIO_PORTS_CONFIG) {
+        if id == CStr::from_ptr(CLAP_EXT_AMBISONIC) {
+            &wrapper.clap_plugin_ambisonic as *const _ as *const c_void
+        } else if id == CStr::from_ptr(CLAP_EXT_AUDIO_PORTS_CONFIG) {
             &wrapper.clap_plugin_audio_ports_config as *const _ as *const c_void
         } else if id == CStr::from_ptr(CLAP_EXT_AUDIO_PORTS) {
             &wrapper.clap_plugin_audio_ports as *const _ as *const c_void
@@ -1973,8 +2685,12 @@ impl<P: ClapPlugin> Wrapper<P> {
             &wrapper.clap_plugin_note_ports as *const _ as *const c_void
         } else if id == CStr::from_ptr(CLAP_EXT_PARAMS) {
             &wrapper.clap_plugin_params as *const _ as *const c_void
+        } else if id == CStr::from_ptr(CLAP_EXT_PRESET_LOAD) {
+            &wrapper.clap_plugin_preset_load as *const _ as *const c_void
         } else if id == CStr::from_ptr(CLAP_EXT_STATE) {
             &wrapper.clap_plugin_state as *const _ as *const c_void
+        } else if id == CStr::from_ptr(CLAP_EXT_SURROUND) {
+            &wrapper.clap_plugin_surround as *const _ as *const c_void
         } else if id == CStr::from_ptr(CLAP_EXT_TAIL) {
             &wrapper.clap_plugin_tail as *const _ as *const c_void
         } else {
@@ -1994,6 +2710,46 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
+    unsafe extern "C" fn ext_ambisonic_get_info(
+        plugin: *const clap_plugin,
+        is_input: bool,
+        port_index: u32,
+        info: *mut clap_ambisonic_info,
+    ) -> bool {
+        check_null_ptr!(false, plugin, info);
+        let wrapper = &*(plugin as *const Self);
+
+        if port_index >= Self::ext_audio_ports_count(plugin, is_input) {
+            nih_debug_assert_failure!(
+                "Host tried to query ambisonic information for out of bounds audio port {} \
+                 (input: {})",
+                port_index,
+                is_input
+            );
+            return false;
+        }
+
+        let layout = audio_port_layout(&wrapper.current_bus_config.load(), is_input, port_index);
+        match layout {
+            // This matches the ordering and normalization documented on `ChannelLayout`
+            ChannelLayout::Ambisonic { .. } => {
+                *info = clap_ambisonic_info {
+                    ordering: CLAP_AMBISONIC_ACN,
+                    normalization: CLAP_AMBISONIC_NORMALIZATION_SN3D,
+                };
+
+                true
+            }
+            _ => {
+                nih_debug_assert_failure!(
+                    "Host tried to query ambisonic information for a {} port",
+                    layout
+                );
+                false
+            }
+        }
+    }
+
     unsafe extern "C" fn ext_audio_ports_config_count(plugin: *const clap_plugin) -> u32 {
         check_null_ptr!(0, plugin);
         let wrapper = &*(plugin as *const Self);
@@ -2013,33 +2769,18 @@ impl<P: ClapPlugin> Wrapper<P> {
             Some(bus_config) => {
                 // We don't support variable auxiliary IO configs right now, so we don't need to
                 // specify sidechain inputs and aux outputs in these descriptions
-                let name = match bus_config {
-                    BusConfig {
-                        num_input_channels: _,
-                        num_output_channels: 1,
-                        ..
-                    } => String::from("Mono"),
-                    BusConfig {
-                        num_input_channels: _,
-                        num_output_channels: 2,
-                        ..
-                    } => String::from("Stereo"),
-                    BusConfig {
-                        num_input_channels,
-                        num_output_channels,
-                        ..
-                    } => format!("{num_input_channels} inputs, {num_output_channels} outputs"),
-                };
-                let input_port_type = match bus_config.num_input_channels {
-                    1 => CLAP_PORT_MONO,
-                    2 => CLAP_PORT_STEREO,
-                    _ => ptr::null(),
-                };
-                let output_port_type = match bus_config.num_output_channels {
-                    1 => CLAP_PORT_MONO,
-                    2 => CLAP_PORT_STEREO,
-                    _ => ptr::null(),
+                let name = if bus_config.num_input_channels == 0
+                    || bus_config.input_layout == bus_config.output_layout
+                {
+                    bus_config.output_layout.to_string()
+                } else {
+                    format!(
+                        "{} in, {} out",
+                        bus_config.input_layout, bus_config.output_layout
+                    )
                 };
+                let input_port_type = clap_port_type(bus_config.input_layout);
+                let output_port_type = clap_port_type(bus_config.output_layout);
 
                 *config = std::mem::zeroed();
 
@@ -2056,8 +2797,8 @@ impl<P: ClapPlugin> Wrapper<P> {
                 } else {
                     0
                 } + bus_config.aux_output_busses.num_busses;
-                config.has_main_input = bus_config.num_output_channels > 0;
-                config.main_input_channel_count = bus_config.num_output_channels;
+                config.has_main_input = bus_config.num_input_channels > 0;
+                config.main_input_channel_count = bus_config.num_input_channels;
                 config.main_input_port_type = input_port_type;
                 config.has_main_output = bus_config.num_output_channels > 0;
                 config.main_output_channel_count = bus_config.num_output_channels;
@@ -2149,12 +2890,7 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
 
         let current_bus_config = wrapper.current_bus_config.load();
-        let has_main_input = current_bus_config.num_input_channels > 0;
-        let has_main_output = current_bus_config.num_output_channels > 0;
-
-        // Whether this port is a main port or an auxiliary (sidechain) port
-        let is_main_port =
-            index == 0 && ((is_input && has_main_input) || (!is_input && has_main_output));
+        let is_main_port = is_main_audio_port(&current_bus_config, is_input, index);
 
         // We'll number the ports in a linear order from `0..num_input_ports` and
         // `num_input_ports..(num_input_ports + num_output_ports)`
@@ -2171,12 +2907,8 @@ impl<P: ClapPlugin> Wrapper<P> {
             (_, false) => CLAP_INVALID_ID,
         };
 
-        let channel_count = match (is_input, is_main_port) {
-            (true, true) => current_bus_config.num_input_channels,
-            (false, true) => current_bus_config.num_output_channels,
-            (true, false) => current_bus_config.aux_input_busses.num_channels,
-            (false, false) => current_bus_config.aux_output_busses.num_channels,
-        };
+        let layout = audio_port_layout(&current_bus_config, is_input, index);
+        let channel_count = layout.num_channels();
 
         let port_type_name = match (is_input, is_main_port) {
             (true, true) => "Input",
@@ -2184,16 +2916,8 @@ impl<P: ClapPlugin> Wrapper<P> {
             (true, false) => "Sidechain Input",
             (false, false) => "Auxiliary Output",
         };
-        let name = match channel_count {
-            1 => format!("Mono {port_type_name}"),
-            2 => format!("Stereo {port_type_name}"),
-            n => format!("{n} channel {port_type_name}"),
-        };
-        let port_type = match channel_count {
-            1 => CLAP_PORT_MONO,
-            2 => CLAP_PORT_STEREO,
-            _ => ptr::null(),
-        };
+        let name = format!("{layout} {port_type_name}");
+        let port_type = clap_port_type(layout);
 
         *info = std::mem::zeroed();
 
@@ -2228,7 +2952,15 @@ impl<P: ClapPlugin> Wrapper<P> {
             {
                 true
             }
-            (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_MIDI) if P::MIDI_INPUT >= MidiConfig::MidiCCs => {
+            (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_MIDI)
+            | (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_MIDI_SYSEX)
+                if P::MIDI_INPUT >= MidiConfig::MidiCCs =>
+            {
+                true
+            }
+            (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_MIDI2)
+                if P::CLAP_MIDI2 && P::MIDI_INPUT >= MidiConfig::Basic =>
+            {
                 true
             }
             _ => false,
@@ -2368,26 +3100,74 @@ impl<P: ClapPlugin> Wrapper<P> {
         true
     }
 
-    unsafe extern "C" fn ext_gui_can_resize(_plugin: *const clap_plugin) -> bool {
-        // TODO: Implement Host->Plugin GUI resizing
-        false
+    unsafe extern "C" fn ext_gui_can_resize(plugin: *const clap_plugin) -> bool {
+        check_null_ptr!(false, plugin);
+        let wrapper = &*(plugin as *const Self);
+
+        wrapper.editor.as_ref().unwrap().can_resize()
     }
 
     unsafe extern "C" fn ext_gui_get_resize_hints(
-        _plugin: *const clap_plugin,
-        _hints: *mut clap_gui_resize_hints,
+        plugin: *const clap_plugin,
+        hints: *mut clap_gui_resize_hints,
     ) -> bool {
-        // TODO: Implement Host->Plugin GUI resizing
-        false
+        check_null_ptr!(false, plugin, hints);
+        let wrapper = &*(plugin as *const Self);
+
+        let editor = wrapper.editor.as_ref().unwrap();
+        if !editor.can_resize() {
+            return false;
+        }
+
+        let resize_hints = editor.resize_hints();
+        let can_resize_horizontally = match (resize_hints.min_size, resize_hints.max_size) {
+            (Some((min_width, _)), Some((max_width, _))) => min_width != max_width,
+            _ => true,
+        };
+        let can_resize_vertically = match (resize_hints.min_size, resize_hints.max_size) {
+            (Some((_, min_height)), Some((_, max_height))) => min_height != max_height,
+            _ => true,
+        };
+
+        *hints = std::mem::zeroed();
+
+        let hints = &mut *hints;
+        hints.can_resize_horizontally = can_resize_horizontally;
+        hints.can_resize_vertically = can_resize_vertically;
+        if let Some((aspect_ratio_width, aspect_ratio_height)) = resize_hints.aspect_ratio {
+            hints.preserve_aspect_ratio = true;
+            hints.aspect_ratio_width = aspect_ratio_width;
+            hints.aspect_ratio_height = aspect_ratio_height;
+        }
+
+        true
     }
 
     unsafe extern "C" fn ext_gui_adjust_size(
-        _plugin: *const clap_plugin,
-        _width: *mut u32,
-        _height: *mut u32,
+        plugin: *const clap_plugin,
+        width: *mut u32,
+        height: *mut u32,
     ) -> bool {
-        // TODO: Implement Host->Plugin GUI resizing
-        false
+        check_null_ptr!(false, plugin, width, height);
+        let wrapper = &*(plugin as *const Self);
+
+        let editor = wrapper.editor.as_ref().unwrap();
+        if !editor.can_resize() {
+            return false;
+        }
+
+        // The host's sizes are in physical pixels, while the resize hints are in logical pixels
+        let scaling_factor = wrapper.editor_scaling_factor.load(Ordering::Relaxed);
+        let (constrained_width, constrained_height) = editor.resize_hints().constrain(
+            (*width as f32 / scaling_factor).round() as u32,
+            (*height as f32 / scaling_factor).round() as u32,
+        );
+        (*width, *height) = (
+            (constrained_width as f32 * scaling_factor).round() as u32,
+            (constrained_height as f32 * scaling_factor).round() as u32,
+        );
+
+        true
     }
 
     unsafe extern "C" fn ext_gui_set_size(
@@ -2395,19 +3175,28 @@ impl<P: ClapPlugin> Wrapper<P> {
         width: u32,
         height: u32,
     ) -> bool {
-        // TODO: Implement Host->Plugin GUI resizing
         // TODO: The host will also call this if an asynchronous (on Linux) resize request fails
         check_null_ptr!(false, plugin);
         let wrapper = &*(plugin as *const Self);
 
-        let (unscaled_width, unscaled_height) = wrapper.editor.as_ref().unwrap().size();
+        let editor = wrapper.editor.as_ref().unwrap();
+        let (unscaled_width, unscaled_height) = editor.size();
         let scaling_factor = wrapper.editor_scaling_factor.load(Ordering::Relaxed);
         let (editor_width, editor_height) = (
             (unscaled_width as f32 * scaling_factor).round() as u32,
             (unscaled_height as f32 * scaling_factor).round() as u32,
         );
 
-        width == editor_width && height == editor_height
+        if width == editor_width && height == editor_height {
+            true
+        } else if editor.can_resize() {
+            editor.set_size(
+                (width as f32 / scaling_factor).round() as u32,
+                (height as f32 / scaling_factor).round() as u32,
+            )
+        } else {
+            false
+        }
     }
 
     unsafe extern "C" fn ext_gui_set_parent(
@@ -2442,12 +3231,13 @@ impl<P: ClapPlugin> Wrapper<P> {
                 };
 
                 // This extension is only exposed when we have an editor
-                *editor_handle = Some(wrapper.editor.as_ref().unwrap().spawn(
+                *editor_handle = wrapper.editor.as_ref().unwrap().spawn(
                     ParentWindowHandle { handle },
                     wrapper.clone().make_gui_context(),
-                ));
+                );
+                nih_debug_assert!(editor_handle.is_some(), "The editor could not be opened");
 
-                true
+                editor_handle.is_some()
             } else {
                 nih_debug_assert_failure!(
                     "Host tried to attach editor while the editor is already attached"
@@ -2513,10 +3303,15 @@ impl<P: ClapPlugin> Wrapper<P> {
 
                 let info = &mut *info;
                 info.id = 0;
-                // TODO: Implement MPE (would just be a toggle for the plugin to expose it) and MIDI2
                 info.supported_dialects = CLAP_NOTE_DIALECT_CLAP;
                 if P::MIDI_INPUT >= MidiConfig::MidiCCs {
                     info.supported_dialects |= CLAP_NOTE_DIALECT_MIDI;
+                    if P::MPE_INPUT {
+                        info.supported_dialects |= CLAP_NOTE_DIALECT_MIDI_MPE;
+                    }
+                }
+                if P::CLAP_MIDI2 {
+                    info.supported_dialects |= CLAP_NOTE_DIALECT_MIDI2;
                 }
                 info.preferred_dialect = CLAP_NOTE_DIALECT_CLAP;
                 strlcpy(&mut info.name, "Note Input");
@@ -2532,6 +3327,9 @@ impl<P: ClapPlugin> Wrapper<P> {
                 if P::MIDI_OUTPUT >= MidiConfig::MidiCCs {
                     info.supported_dialects |= CLAP_NOTE_DIALECT_MIDI;
                 }
+                if P::CLAP_MIDI2 {
+                    info.supported_dialects |= CLAP_NOTE_DIALECT_MIDI2;
+                }
                 info.preferred_dialect = CLAP_NOTE_DIALECT_CLAP;
                 strlcpy(&mut info.name, "Note Output");
 
@@ -2567,7 +3365,8 @@ impl<P: ClapPlugin> Wrapper<P> {
         let step_count = param_ptr.step_count();
         let flags = param_ptr.flags();
         let automatable = !flags.contains(ParamFlags::NON_AUTOMATABLE);
-        let hidden = flags.contains(ParamFlags::HIDDEN);
+        let hidden =
+            flags.contains(ParamFlags::HIDDEN) || wrapper.hidden_param_hashes.contains(param_hash);
         let is_bypass = flags.contains(ParamFlags::BYPASS);
 
         *param_info = std::mem::zeroed();
@@ -2576,10 +3375,14 @@ impl<P: ClapPlugin> Wrapper<P> {
         //       hashmap lookup, but for now we'll stay consistent with the VST3 implementation.
         let param_info = &mut *param_info;
         param_info.id = *param_hash;
-        // TODO: Somehow expose per note/channel/port modulation
+        // Polyphonic modulation is only supported per note ID. Per key and per channel modulation
+        // are not exposed, see `FloatParam::with_poly_modulation()`.
         param_info.flags = 0;
         if automatable && !hidden {
             param_info.flags |= CLAP_PARAM_IS_AUTOMATABLE | CLAP_PARAM_IS_MODULATABLE;
+            if param_ptr.poly_modulated() {
+                param_info.flags |= CLAP_PARAM_IS_MODULATABLE_PER_NOTE_ID;
+            }
         }
         if hidden {
             param_info.flags |= CLAP_PARAM_IS_HIDDEN | CLAP_PARAM_IS_READONLY;
@@ -2616,7 +3419,9 @@ impl<P: ClapPlugin> Wrapper<P> {
 
         match wrapper.param_by_hash.get(&param_id) {
             Some(param_ptr) => {
-                *value = param_ptr.normalized_value() as f64
+                // Modulation is non-destructive in CLAP, so the host should only ever see the
+                // parameter's unmodulated value
+                *value = param_ptr.unmodulated_normalized_value() as f64
                     * param_ptr.step_count().unwrap_or(1) as f64;
 
                 true
@@ -2690,6 +3495,10 @@ impl<P: ClapPlugin> Wrapper<P> {
         check_null_ptr!((), plugin);
         let wrapper = &*(plugin as *const Self);
 
+        // Any SysEx messages sent during the last flush or process call have already been read by
+        // the host at this point
+        wrapper.sysex_storage.borrow_mut().clear();
+
         if !in_.is_null() {
             wrapper.handle_in_events(&*in_, 0);
         }
@@ -2733,7 +3542,7 @@ impl<P: ClapPlugin> Wrapper<P> {
         check_null_ptr!(false, plugin, stream);
         let wrapper = &*(plugin as *const Self);
 
-        let serialized = state::serialize_json(
+        let serialized = state::serialize_bytes::<P>(
             wrapper.params.clone(),
             state::make_params_iter(&wrapper.param_by_hash, &wrapper.param_id_to_hash),
         );
@@ -2764,6 +3573,36 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
+    unsafe extern "C" fn ext_preset_load_from_file(
+        plugin: *const clap_plugin,
+        path: *const c_char,
+    ) -> bool {
+        check_null_ptr!(false, plugin, path);
+        let wrapper = &*(plugin as *const Self);
+
+        // CLAP does not yet have a way for hosts to discover the plugin's factory presets, but hosts
+        // can ask us to load a preset file. This can either be a preset file created through the
+        // `nih_plug::preset` module, or a file containing just the serialized plugin state.
+        let path = match CStr::from_ptr(path).to_str() {
+            Ok(path) => path,
+            Err(_) => {
+                nih_debug_assert_failure!("The host passed a non-UTF-8 preset path");
+                return false;
+            }
+        };
+        let state = match preset::load_state_file::<P>(path) {
+            Ok(state) => state,
+            Err(err) => {
+                nih_debug_assert_failure!("Could not load the preset at '{}': {}", path, err);
+                return false;
+            }
+        };
+
+        wrapper.set_state_object(state);
+
+        true
+    }
+
     unsafe extern "C" fn ext_state_load(
         plugin: *const clap_plugin,
         stream: *const clap_istream,
@@ -2772,7 +3611,7 @@ impl<P: ClapPlugin> Wrapper<P> {
         let wrapper = &*(plugin as *const Self);
 
         // CLAP does not have a way to tell how much data there is left in a stream, so we've
-        // prepended the size in front of our JSON state
+        // prepended the size in front of our state
         let mut length_bytes = [0; 8];
         let num_length_bytes_read = ((*stream).read)(
             stream,
@@ -2791,16 +3630,19 @@ impl<P: ClapPlugin> Wrapper<P> {
         nih_debug_assert_eq!(num_bytes_read as u64, length);
         read_buffer.set_len(length as usize);
 
-        let success = state::deserialize_json(
+        let success = state::deserialize_bytes::<P>(
             &read_buffer,
             wrapper.params.clone(),
             state::make_params_getter(&wrapper.param_by_hash, &wrapper.param_id_to_hash),
             wrapper.current_buffer_config.load().as_ref(),
+            &wrapper.undo_history,
         );
         if !success {
             return false;
         }
 
+        wrapper.field_change_tracker.reset();
+
         // Reinitialize the plugin after loading state so it can respond to the new parameter values
         wrapper.notify_param_values_changed();
 
@@ -2821,6 +3663,50 @@ impl<P: ClapPlugin> Wrapper<P> {
         true
     }
 
+    unsafe extern "C" fn ext_surround_get_channel_map(
+        plugin: *const clap_plugin,
+        is_input: bool,
+        port_index: u32,
+        channel_map: *mut u8,
+        channel_map_capacity: u32,
+    ) -> u32 {
+        check_null_ptr!(0, plugin, channel_map);
+        let wrapper = &*(plugin as *const Self);
+
+        if port_index >= Self::ext_audio_ports_count(plugin, is_input) {
+            nih_debug_assert_failure!(
+                "Host tried to query the channel map for out of bounds audio port {} (input: {})",
+                port_index,
+                is_input
+            );
+            return 0;
+        }
+
+        let layout = audio_port_layout(&wrapper.current_bus_config.load(), is_input, port_index);
+        let layout_channel_map: &[u8] = match layout {
+            ChannelLayout::Surround5_1 => &SURROUND_5_1_CHANNEL_MAP,
+            ChannelLayout::Surround7_1 => &SURROUND_7_1_CHANNEL_MAP,
+            _ => {
+                nih_debug_assert_failure!(
+                    "Host tried to query the channel map for a {} port",
+                    layout
+                );
+                return 0;
+            }
+        };
+
+        let num_channels = layout_channel_map.len().min(channel_map_capacity as usize);
+        std::slice::from_raw_parts_mut(channel_map, num_channels)
+            .copy_from_slice(&layout_channel_map[..num_channels]);
+
+        num_channels as u32
+    }
+
+    unsafe extern "C" fn ext_surround_changed(_plugin: *const clap_plugin) {
+        // We always use the channel order documented on `ChannelLayout`, so the host's preferred
+        // channel map doesn't change anything
+    }
+
     unsafe extern "C" fn ext_tail_get(plugin: *const clap_plugin) -> u32 {
         check_null_ptr!(0, plugin);
         let wrapper = &*(plugin as *const Self);
@@ -2833,6 +3719,39 @@ impl<P: ClapPlugin> Wrapper<P> {
     }
 }
 
+/// Whether an audio port is a main port or an auxiliary (sidechain) port. The port index needs to be
+/// in bounds.
+fn is_main_audio_port(bus_config: &BusConfig, is_input: bool, index: u32) -> bool {
+    let has_main_input = bus_config.num_input_channels > 0;
+    let has_main_output = bus_config.num_output_channels > 0;
+
+    index == 0 && ((is_input && has_main_input) || (!is_input && has_main_output))
+}
+
+/// Get the channel layout for an audio port. The port index needs to be in bounds. Auxiliary busses
+/// don't have a layout, so we'll use the default layout for their channel count.
+fn audio_port_layout(bus_config: &BusConfig, is_input: bool, index: u32) -> ChannelLayout {
+    match (is_input, is_main_audio_port(bus_config, is_input, index)) {
+        (true, true) => bus_config.input_layout,
+        (false, true) => bus_config.output_layout,
+        (true, false) => ChannelLayout::from_num_channels(bus_config.aux_input_busses.num_channels),
+        (false, false) => {
+            ChannelLayout::from_num_channels(bus_config.aux_output_busses.num_channels)
+        }
+    }
+}
+
+/// Get the CLAP port type for a channel layout. Discrete layouts don't have a port type.
+fn clap_port_type(layout: ChannelLayout) -> *const c_char {
+    match layout {
+        ChannelLayout::Mono => CLAP_PORT_MONO,
+        ChannelLayout::Stereo => CLAP_PORT_STEREO,
+        ChannelLayout::Surround5_1 | ChannelLayout::Surround7_1 => CLAP_PORT_SURROUND,
+        ChannelLayout::Ambisonic { .. } => CLAP_PORT_AMBISONIC,
+        ChannelLayout::Discrete(_) => ptr::null(),
+    }
+}
+
 /// Convenience function to query an extennsion from the host.
 ///
 /// # Safety
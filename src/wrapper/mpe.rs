@@ -0,0 +1,387 @@
+//! Conversion from MPE (MIDI Polyphonic Expression) to NIH-plug's polyphonic note events. This is
+//! used by the wrappers when [`Plugin::MPE_INPUT`][crate::prelude::Plugin::MPE_INPUT] is enabled.
+
+use crate::midi::NoteEvent;
+
+/// The pitch bend range for member channels in semitones until the host or controller sets
+/// another value. This is the default from the MPE specification.
+const DEFAULT_MEMBER_PITCH_BEND_RANGE: f32 = 48.0;
+
+/// The master channel for the lower zone.
+const LOWER_ZONE_MASTER_CHANNEL: u8 = 0;
+/// The master channel for the upper zone.
+const UPPER_ZONE_MASTER_CHANNEL: u8 = 15;
+
+/// The CC used for the per-note brightness/timbre dimension.
+const CC_BRIGHTNESS: u8 = 74;
+/// The CC for the most significant byte of a registered parameter number.
+const CC_RPN_MSB: u8 = 101;
+/// The CC for the least significant byte of a registered parameter number.
+const CC_RPN_LSB: u8 = 100;
+/// The CC for the most significant byte of a (registered) parameter's new value.
+const CC_DATA_ENTRY_MSB: u8 = 6;
+/// The CC for the least significant byte of a (registered) parameter's new value.
+const CC_DATA_ENTRY_LSB: u8 = 38;
+
+/// The RPN for the pitch bend range. The data entry MSB contains the range in semitones, and the
+/// LSB contains an additional number of cents.
+const RPN_PITCH_BEND_SENSITIVITY: u16 = 0;
+/// The RPN for the MPE Configuration Message. The data entry MSB contains the number of member
+/// channels for the zone belonging to the master channel the message was sent on.
+const RPN_MPE_CONFIGURATION: u16 = 6;
+/// The null RPN, used to deselect the current RPN.
+const RPN_NULL: u16 = 0x3FFF;
+
+/// Keeps track of the MPE zone configuration, the active notes, and the last expression values
+/// for every MIDI channel. Pitch bend, channel pressure, and CC 74 messages received on a zone's
+/// member channels are converted to [`NoteEvent::PolyTuning`], [`NoteEvent::PolyPressure`], and
+/// [`NoteEvent::PolyBrightness`] events for all notes playing on that channel. All other events,
+/// including messages on the master channels, are passed through as is.
+///
+/// Until an MPE Configuration Message is received this assumes a lower zone with 15 member
+/// channels, which is what most MPE controllers send by default.
+#[derive(Debug)]
+pub struct MpeState {
+    /// The number of member channels for the lower zone. These are channels `1..=n`.
+    lower_zone_members: u8,
+    /// The number of member channels for the upper zone. These are channels `(15 - n)..=14`.
+    upper_zone_members: u8,
+    channels: [ChannelState; 16],
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ChannelState {
+    /// A bit set containing the notes currently playing on this channel.
+    active_notes: u128,
+    /// The pitch bend range in semitones. Only used for member channels.
+    pitch_bend_range: f32,
+    /// The currently selected registered parameter number, or [`RPN_NULL`] if there is none.
+    rpn: u16,
+
+    /// The last tuning in semitones, derived from this channel's pitch bend.
+    tuning: f32,
+    /// The last channel pressure value.
+    pressure: f32,
+    /// The last CC 74 value, if one has been received.
+    brightness: Option<f32>,
+}
+
+impl Default for MpeState {
+    fn default() -> Self {
+        Self {
+            lower_zone_members: 15,
+            upper_zone_members: 0,
+            channels: [ChannelState::default(); 16],
+        }
+    }
+}
+
+impl Default for ChannelState {
+    fn default() -> Self {
+        Self {
+            active_notes: 0,
+            pitch_bend_range: DEFAULT_MEMBER_PITCH_BEND_RANGE,
+            rpn: RPN_NULL,
+
+            tuning: 0.0,
+            pressure: 0.0,
+            brightness: None,
+        }
+    }
+}
+
+impl MpeState {
+    /// Forget about all active notes and the channels' last expression values. The zone
+    /// configuration and pitch bend ranges are kept intact since those are usually only sent once
+    /// when the controller is connected.
+    pub fn reset(&mut self) {
+        for channel in &mut self.channels {
+            *channel = ChannelState {
+                pitch_bend_range: channel.pitch_bend_range,
+                ..ChannelState::default()
+            };
+        }
+    }
+
+    /// Process an incoming event, and pass the resulting event or events to `push`.
+    pub fn process_event(&mut self, event: NoteEvent, mut push: impl FnMut(NoteEvent)) {
+        match event {
+            NoteEvent::NoteOn {
+                timing,
+                channel,
+                note,
+                ..
+            } if self.is_member_channel(channel) => {
+                let state = &mut self.channels[channel as usize];
+                state.active_notes |= 1 << (note & 0x7F);
+                push(event);
+
+                // Expression values sent before the note on message also apply to the new note
+                if state.tuning != 0.0 {
+                    push(NoteEvent::PolyTuning {
+                        timing,
+                        voice_id: None,
+                        channel,
+                        note,
+                        tuning: state.tuning,
+                    });
+                }
+                if state.pressure != 0.0 {
+                    push(NoteEvent::PolyPressure {
+                        timing,
+                        voice_id: None,
+                        channel,
+                        note,
+                        pressure: state.pressure,
+                    });
+                }
+                if let Some(brightness) = state.brightness {
+                    push(NoteEvent::PolyBrightness {
+                        timing,
+                        voice_id: None,
+                        channel,
+                        note,
+                        brightness,
+                    });
+                }
+            }
+            NoteEvent::NoteOff { channel, note, .. } if self.is_member_channel(channel) => {
+                self.channels[channel as usize].active_notes &= !(1 << (note & 0x7F));
+                push(event);
+            }
+            NoteEvent::MidiPitchBend {
+                timing,
+                channel,
+                value,
+            } if self.is_member_channel(channel) => {
+                let state = &mut self.channels[channel as usize];
+                state.tuning = (value - 0.5) * 2.0 * state.pitch_bend_range;
+
+                let tuning = state.tuning;
+                for note in active_notes(state.active_notes) {
+                    push(NoteEvent::PolyTuning {
+                        timing,
+                        voice_id: None,
+                        channel,
+                        note,
+                        tuning,
+                    });
+                }
+            }
+            NoteEvent::MidiChannelPressure {
+                timing,
+                channel,
+                pressure,
+            } if self.is_member_channel(channel) => {
+                let state = &mut self.channels[channel as usize];
+                state.pressure = pressure;
+
+                for note in active_notes(state.active_notes) {
+                    push(NoteEvent::PolyPressure {
+                        timing,
+                        voice_id: None,
+                        channel,
+                        note,
+                        pressure,
+                    });
+                }
+            }
+            NoteEvent::MidiCC {
+                timing,
+                channel,
+                cc: CC_BRIGHTNESS,
+                value,
+            } if self.is_member_channel(channel) => {
+                let state = &mut self.channels[channel as usize];
+                state.brightness = Some(value);
+
+                for note in active_notes(state.active_notes) {
+                    push(NoteEvent::PolyBrightness {
+                        timing,
+                        voice_id: None,
+                        channel,
+                        note,
+                        brightness: value,
+                    });
+                }
+            }
+            NoteEvent::MidiCC {
+                channel, cc, value, ..
+            } if channel < 16 => {
+                self.handle_rpn_cc(channel, cc, (value * 127.0).round() as u8);
+                push(event);
+            }
+            event => push(event),
+        }
+    }
+
+    /// Whether the channel is one of the member channels of either zone. Expression messages on
+    /// these channels only affect the notes playing on that channel.
+    fn is_member_channel(&self, channel: u8) -> bool {
+        self.zone_master_channel(channel)
+            .map(|master_channel| master_channel != channel)
+            .unwrap_or(false)
+    }
+
+    /// Get the master channel for the zone the channel belongs to, if any. The master channels
+    /// themselves are also part of their zones.
+    fn zone_master_channel(&self, channel: u8) -> Option<u8> {
+        if self.lower_zone_members > 0 && channel <= self.lower_zone_members {
+            Some(LOWER_ZONE_MASTER_CHANNEL)
+        } else if self.upper_zone_members > 0
+            && channel <= UPPER_ZONE_MASTER_CHANNEL
+            && channel >= UPPER_ZONE_MASTER_CHANNEL - self.upper_zone_members
+        {
+            Some(UPPER_ZONE_MASTER_CHANNEL)
+        } else {
+            None
+        }
+    }
+
+    /// Keep track of the RPN messages needed to configure MPE zones and their pitch bend ranges.
+    fn handle_rpn_cc(&mut self, channel: u8, cc: u8, value: u8) {
+        let state = &mut self.channels[channel as usize];
+        match cc {
+            CC_RPN_MSB => state.rpn = (state.rpn & 0x7F) | ((value as u16) << 7),
+            CC_RPN_LSB => state.rpn = (state.rpn & !0x7F) | value as u16,
+            CC_DATA_ENTRY_MSB => match state.rpn {
+                RPN_MPE_CONFIGURATION => self.configure_zone(channel, value),
+                RPN_PITCH_BEND_SENSITIVITY => self.set_pitch_bend_range(channel, value as f32),
+                _ => (),
+            },
+            CC_DATA_ENTRY_LSB if state.rpn == RPN_PITCH_BEND_SENSITIVITY => {
+                let semitones = state.pitch_bend_range.trunc();
+                self.set_pitch_bend_range(channel, semitones + (value as f32 / 100.0));
+            }
+            _ => (),
+        }
+    }
+
+    /// Handle an MPE Configuration Message. This resizes the zone, shrinking the other zone if the
+    /// zones would otherwise overlap, and resets the zone's member channels' pitch bend ranges.
+    fn configure_zone(&mut self, master_channel: u8, num_members: u8) {
+        let num_members = num_members.min(15);
+        match master_channel {
+            LOWER_ZONE_MASTER_CHANNEL => {
+                self.lower_zone_members = num_members;
+                self.upper_zone_members = self.upper_zone_members.min(14 - num_members.min(14));
+            }
+            UPPER_ZONE_MASTER_CHANNEL => {
+                self.upper_zone_members = num_members;
+                self.lower_zone_members = self.lower_zone_members.min(14 - num_members.min(14));
+            }
+            _ => return,
+        }
+
+        for channel in 0..16 {
+            if self.zone_master_channel(channel) == Some(master_channel) {
+                self.channels[channel as usize].pitch_bend_range = DEFAULT_MEMBER_PITCH_BEND_RANGE;
+            }
+        }
+    }
+
+    /// Set the pitch bend range for a member channel. Like the MPE specification describes, this
+    /// applies to all member channels in the same zone. The master channels' pitch bend is passed
+    /// through as regular MIDI pitch bend, so their ranges are not tracked.
+    fn set_pitch_bend_range(&mut self, channel: u8, semitones: f32) {
+        let master_channel = match self.zone_master_channel(channel) {
+            Some(master_channel) if master_channel != channel => master_channel,
+            _ => return,
+        };
+
+        for member_channel in 0..16 {
+            if member_channel != master_channel
+                && self.zone_master_channel(member_channel) == Some(master_channel)
+            {
+                self.channels[member_channel as usize].pitch_bend_range = semitones;
+            }
+        }
+    }
+}
+
+/// Iterate over the notes contained in a channel's active note bit set.
+fn active_notes(active_notes: u128) -> impl Iterator<Item = u8> {
+    (0..128u8).filter(move |note| active_notes & (1 << note) != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(state: &mut MpeState, event: NoteEvent) -> Vec<NoteEvent> {
+        let mut events = Vec::new();
+        state.process_event(event, |event| events.push(event));
+        events
+    }
+
+    #[test]
+    fn member_pitch_bend_to_poly_tuning() {
+        let mut state = MpeState::default();
+        process(
+            &mut state,
+            NoteEvent::NoteOn {
+                timing: 0,
+                voice_id: None,
+                channel: 1,
+                note: 60,
+                velocity: 1.0,
+            },
+        );
+
+        let events = process(
+            &mut state,
+            NoteEvent::MidiPitchBend {
+                timing: 10,
+                channel: 1,
+                value: 1.0,
+            },
+        );
+        assert_eq!(
+            events,
+            [NoteEvent::PolyTuning {
+                timing: 10,
+                voice_id: None,
+                channel: 1,
+                note: 60,
+                tuning: 48.0,
+            }]
+        );
+    }
+
+    #[test]
+    fn master_channel_passthrough() {
+        let mut state = MpeState::default();
+        let event = NoteEvent::MidiPitchBend {
+            timing: 0,
+            channel: 0,
+            value: 1.0,
+        };
+
+        assert_eq!(process(&mut state, event), [event]);
+    }
+
+    #[test]
+    fn mpe_configuration_message() {
+        let mut state = MpeState::default();
+        for (cc, value) in [
+            (CC_RPN_LSB, RPN_MPE_CONFIGURATION as u8),
+            (CC_RPN_MSB, 0),
+            (CC_DATA_ENTRY_MSB, 3),
+        ] {
+            process(
+                &mut state,
+                NoteEvent::MidiCC {
+                    timing: 0,
+                    channel: 15,
+                    cc,
+                    value: value as f32 / 127.0,
+                },
+            );
+        }
+
+        assert_eq!(state.upper_zone_members, 3);
+        assert_eq!(state.lower_zone_members, 11);
+        assert!(state.is_member_channel(12));
+        assert!(!state.is_member_channel(15));
+    }
+}
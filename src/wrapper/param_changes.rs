@@ -0,0 +1,141 @@
+//! Per-parameter queues for parameter changes that occur in the middle of a buffer. This is used by
+//! the wrappers when [`Plugin::QUEUED_AUTOMATION`][crate::prelude::Plugin::QUEUED_AUTOMATION] is
+//! enabled.
+
+use std::collections::HashMap;
+
+use crate::context::ParamChange;
+use crate::param::internals::ParamPtr;
+
+/// The changes the host made to each of the plugin's parameters after the start of the current
+/// block. The queues are preallocated in [`reset()`][Self::reset()] so queueing changes on the
+/// audio thread never allocates.
+pub(crate) struct ParamChangeQueues {
+    queues: HashMap<ParamPtr, Vec<ParamChange>>,
+}
+
+impl ParamChangeQueues {
+    /// Create an empty queue for each parameter in `params`.
+    pub fn new(params: impl IntoIterator<Item = ParamPtr>) -> Self {
+        Self {
+            queues: params
+                .into_iter()
+                .map(|param_ptr| (param_ptr, Vec::new()))
+                .collect(),
+        }
+    }
+
+    /// Clear the queues and make sure they can hold a change for every sample in a block of up to
+    /// `max_buffer_size` samples. This should be called when the plugin gets activated.
+    pub fn reset(&mut self, max_buffer_size: u32) {
+        for changes in self.queues.values_mut() {
+            changes.clear();
+            changes.reserve_exact(max_buffer_size as usize);
+        }
+    }
+
+    /// Queue a change for `param`. The changes for a parameter need to be added in order. A
+    /// parameter can only have a single value for each sample, so a change that occurs at the same
+    /// sample as the previous change replaces that change. This keeps the queue from growing past
+    /// the capacity reserved in [`reset()`][Self::reset()].
+    pub fn push(&mut self, param: ParamPtr, change: ParamChange) {
+        let changes = match self.queues.get_mut(&param) {
+            Some(changes) => changes,
+            None => {
+                nih_debug_assert_failure!("Unknown parameter: {:?}", param);
+                return;
+            }
+        };
+
+        let is_full = changes.len() == changes.capacity();
+        match changes.last_mut() {
+            Some(last_change) if last_change.timing == change.timing => *last_change = change,
+            Some(last_change) if is_full => {
+                nih_debug_assert_failure!(
+                    "The parameter change queue is full, replacing the last change"
+                );
+                *last_change = change;
+            }
+            _ => changes.push(change),
+        }
+    }
+
+    /// Get the changes queued for `param`.
+    pub fn get(&self, param: ParamPtr) -> &[ParamChange] {
+        self.queues
+            .get(&param)
+            .map(|changes| changes.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Set every parameter to the last value in its queue and clear the queues. The smoothers are
+    /// updated if the sample rate is known. If the plugin already consumed the changes through
+    /// [`Smoother::next_block_with_changes()`][crate::prelude::Smoother::next_block_with_changes()]
+    /// then the smoother's target won't change. Returns `true` if any parameter's value was
+    /// changed, in which case the wrapper should notify the plugin about the new values.
+    pub fn apply(&mut self, sample_rate: Option<f32>) -> bool {
+        let mut parameter_values_changed = false;
+        for (param_ptr, changes) in self.queues.iter_mut() {
+            if let Some(change) = changes.last() {
+                unsafe { param_ptr.set_normalized_value(change.normalized_value) };
+                if let Some(sample_rate) = sample_rate {
+                    unsafe { param_ptr.update_smoother(sample_rate, false) };
+                }
+
+                changes.clear();
+                parameter_values_changed = true;
+            }
+        }
+
+        parameter_values_changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::range::FloatRange;
+    use crate::param::{FloatParam, Param};
+
+    fn change(timing: u32, normalized_value: f32) -> ParamChange {
+        ParamChange {
+            timing,
+            normalized_value,
+        }
+    }
+
+    #[test]
+    fn same_sample_changes_replace_each_other() {
+        let param = FloatParam::new("Foo", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 });
+        let mut queues = ParamChangeQueues::new([param.as_ptr()]);
+        queues.reset(4);
+        let capacity = queues.queues[&param.as_ptr()].capacity();
+
+        queues.push(param.as_ptr(), change(1, 0.25));
+        queues.push(param.as_ptr(), change(1, 0.5));
+        queues.push(param.as_ptr(), change(1, 0.75));
+        queues.push(param.as_ptr(), change(3, 1.0));
+        assert_eq!(
+            queues.get(param.as_ptr()),
+            [change(1, 0.75), change(3, 1.0)]
+        );
+        assert_eq!(queues.queues[&param.as_ptr()].capacity(), capacity);
+    }
+
+    #[test]
+    fn apply_sets_last_value() {
+        let param = FloatParam::new("Foo", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 });
+        let mut queues = ParamChangeQueues::new([param.as_ptr()]);
+        queues.reset(4);
+
+        assert!(!queues.apply(None));
+        queues.push(param.as_ptr(), change(1, 0.25));
+        queues.push(param.as_ptr(), change(2, 0.5));
+        assert!(queues.apply(None));
+        assert_eq!(
+            unsafe { param.as_ptr().unmodulated_normalized_value() },
+            0.5
+        );
+        assert!(queues.get(param.as_ptr()).is_empty());
+    }
+}
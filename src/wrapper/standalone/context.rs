@@ -3,8 +3,8 @@ use std::sync::Arc;
 
 use super::backend::Backend;
 use super::wrapper::{GuiTask, Wrapper};
-use crate::context::{GuiContext, PluginApi, ProcessContext, Transport};
-use crate::midi::NoteEvent;
+use crate::context::{GuiContext, ParamChange, PluginApi, ProcessContext, Transport};
+use crate::midi::{NoteEvent, SysExMessage};
 use crate::param::internals::ParamPtr;
 use crate::plugin::Plugin;
 
@@ -23,7 +23,6 @@ pub(crate) struct WrapperGuiContext<P: Plugin, B: Backend> {
 /// can hold on to lock guards for event queues. Otherwise reading these events would require
 /// constant unnecessary atomic operations to lock the uncontested RwLocks.
 pub(crate) struct WrapperProcessContext<'a, P: Plugin, B: Backend> {
-    #[allow(dead_code)]
     pub(super) wrapper: &'a Wrapper<P, B>,
     // TODO: Events
     // pub(super) input_events_guard: AtomicRefMut<'a, VecDeque<NoteEvent>>,
@@ -53,15 +52,20 @@ impl<P: Plugin, B: Backend> GuiContext for WrapperGuiContext<P, B> {
         true
     }
 
-    unsafe fn raw_begin_set_parameter(&self, _param: ParamPtr) {
-        // Since there's no autmoation being recorded here, gestures don't mean anything
+    unsafe fn raw_begin_set_parameter(&self, param: ParamPtr) {
+        // Since there's no autmoation being recorded here, gestures are only used for the undo
+        // history
+        self.wrapper.undo_history.begin_gesture(param);
     }
 
     unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
+        self.wrapper.undo_history.record_value(param, normalized);
         self.wrapper.set_parameter(param, normalized);
     }
 
-    unsafe fn raw_end_set_parameter(&self, _param: ParamPtr) {}
+    unsafe fn raw_end_set_parameter(&self, param: ParamPtr) {
+        self.wrapper.undo_history.end_gesture(param);
+    }
 
     fn get_state(&self) -> crate::wrapper::state::PluginState {
         self.wrapper.get_state_object()
@@ -70,13 +74,53 @@ impl<P: Plugin, B: Backend> GuiContext for WrapperGuiContext<P, B> {
     fn set_state(&self, state: crate::wrapper::state::PluginState) {
         self.wrapper.set_state_object(state)
     }
+
+    fn mark_dirty(&self) {
+        // There's no host or project file to notify here
+    }
+
+    fn undo(&self) -> bool {
+        self.wrapper
+            .undo_history
+            .undo(self, self.wrapper.params.as_ref())
+    }
+
+    fn redo(&self) -> bool {
+        self.wrapper
+            .undo_history
+            .redo(self, self.wrapper.params.as_ref())
+    }
+
+    fn can_undo(&self) -> bool {
+        self.wrapper.undo_history.can_undo()
+    }
+
+    fn can_redo(&self) -> bool {
+        self.wrapper.undo_history.can_redo()
+    }
+
+    fn raw_begin_field_changes(&self) {
+        self.wrapper
+            .undo_history
+            .begin_field_changes(self.wrapper.params.as_ref());
+    }
+
+    fn raw_end_field_changes(&self) {
+        self.wrapper
+            .undo_history
+            .end_field_changes(self.wrapper.params.as_ref());
+    }
 }
 
-impl<P: Plugin, B: Backend> ProcessContext for WrapperProcessContext<'_, P, B> {
+impl<P: Plugin, B: Backend> ProcessContext<P> for WrapperProcessContext<'_, P, B> {
     fn plugin_api(&self) -> PluginApi {
         PluginApi::Standalone
     }
 
+    fn execute_background(&self, task: P::BackgroundTask) {
+        self.wrapper.background_thread.schedule(task);
+    }
+
     fn transport(&self) -> &Transport {
         &self.transport
     }
@@ -94,7 +138,29 @@ impl<P: Plugin, B: Backend> ProcessContext for WrapperProcessContext<'_, P, B> {
         // self.output_events_guard.push_back(event);
     }
 
+    fn sysex_data<'a>(&'a self, _message: &'a SysExMessage) -> &'a [u8] {
+        nih_debug_assert_failure!("TODO: WrapperProcessContext::sysex_data()");
+
+        &[]
+    }
+
+    fn new_sysex_message(&mut self, _data: &[u8]) -> Option<SysExMessage> {
+        nih_debug_assert_failure!("TODO: WrapperProcessContext::new_sysex_message()");
+
+        None
+    }
+
     fn set_latency_samples(&self, _samples: u32) {
         nih_debug_assert_failure!("TODO: WrapperProcessContext::set_latency_samples()");
     }
+
+    unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
+        // There's no host to inform, so the value is set directly like with the GUI context
+        self.wrapper.set_parameter(param, normalized);
+    }
+
+    fn raw_param_changes(&self, _param: ParamPtr) -> &[ParamChange] {
+        // The standalone wrapper doesn't have any automation
+        &[]
+    }
 }
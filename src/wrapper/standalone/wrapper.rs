@@ -12,15 +12,18 @@ use std::thread;
 
 use super::backend::Backend;
 use super::context::{WrapperGuiContext, WrapperProcessContext};
+use crate::buffer::AuxiliaryBuffers;
 use crate::context::Transport;
+use crate::event_loop::BackgroundThread;
 use crate::param::internals::{ParamPtr, Params};
 use crate::param::ParamFlags;
 use crate::plugin::{
-    AuxiliaryIOConfig, BufferConfig, BusConfig, Editor, ParentWindowHandle, Plugin, ProcessMode,
-    ProcessStatus,
+    AuxiliaryIOConfig, BufferConfig, BusConfig, ChannelLayout, Editor, ParentWindowHandle, Plugin,
+    ProcessMode, ProcessStatus,
 };
 use crate::util::permit_alloc;
 use crate::wrapper::state::{self, PluginState};
+use crate::wrapper::undo::UndoHistory;
 
 /// How many parameter changes we can store in our unprocessed parameter change queue. Storing more
 /// than this many parameters at a time will cause changes to get lost.
@@ -60,17 +63,24 @@ pub struct Wrapper<P: Plugin, B: Backend> {
     /// The plugin's parameters. These are fetched once during initialization. That way the
     /// `ParamPtr`s are guaranteed to live at least as long as this object and we can interact with
     /// the `Params` object without having to acquire a lock on `plugin`.
-    params: Arc<dyn Params>,
+    pub params: Arc<dyn Params>,
     /// The set of parameter pointers in `params`. This is technically not necessary, but for
     /// consistency with the plugin wrappers we'll check whether the `ParamPtr` for an incoming
     /// parameter change actually belongs to a registered parameter.
     known_parameters: HashSet<ParamPtr>,
     /// A mapping from parameter string IDs to parameter pointers.
     param_map: HashMap<String, ParamPtr>,
+    /// The undo history for parameter gestures and persistent field changes made from the
+    /// plugin's editor. This only records anything if the plugin has set
+    /// [`Plugin::UNDO_HISTORY_SIZE`][crate::prelude::Plugin::UNDO_HISTORY_SIZE].
+    pub undo_history: UndoHistory,
     /// The plugin's editor, if it has one. This object does not do anything on its own, but we need
     /// to instantiate this in advance so we don't need to lock the entire [`Plugin`] object when
     /// creating an editor.
     pub editor: Option<Arc<dyn Editor>>,
+    /// A worker thread that runs the plugin's background tasks using the executor returned by
+    /// [`Plugin::task_executor()`].
+    pub background_thread: BackgroundThread<P::BackgroundTask>,
 
     config: WrapperConfig,
 
@@ -106,8 +116,9 @@ pub enum WrapperError {
 
 struct WrapperWindowHandler {
     /// The editor handle for the plugin's open editor. The editor should clean itself up when it
-    /// gets dropped.
-    _editor_handle: Box<dyn Any>,
+    /// gets dropped. If the editor could not be spawned, then this is `None` and the window will
+    /// be closed immediately so the wrapper can continue in headless mode.
+    editor_handle: Option<Box<dyn Any>>,
 
     /// This is used to communicate with the wrapper from the audio thread and from within the
     /// baseview window handler on the GUI thread.
@@ -124,6 +135,11 @@ pub enum GuiTask {
 
 impl WindowHandler for WrapperWindowHandler {
     fn on_frame(&mut self, window: &mut Window) {
+        if self.editor_handle.is_none() {
+            window.close();
+            return;
+        }
+
         while let Ok(task) = self.gui_task_receiver.try_recv() {
             match task {
                 GuiTask::Resize(new_width, new_height) => {
@@ -152,7 +168,10 @@ impl<P: Plugin, B: Backend> Wrapper<P, B> {
     pub fn new(backend: B, config: WrapperConfig) -> Result<Arc<Self>, WrapperError> {
         let plugin = P::default();
         let params = plugin.params();
-        let editor = plugin.editor().map(Arc::from);
+        let background_thread = BackgroundThread::new_and_spawn(plugin.task_executor());
+        let editor = plugin
+            .editor(background_thread.async_executor())
+            .map(Arc::from);
 
         // This is used to allow the plugin to restore preset data from its editor, see the comment
         // on `Self::updated_state_sender`
@@ -195,11 +214,15 @@ impl<P: Plugin, B: Backend> Wrapper<P, B> {
                 .into_iter()
                 .map(|(param_id, param_ptr, _)| (param_id, param_ptr))
                 .collect(),
+            undo_history: UndoHistory::new(P::UNDO_HISTORY_SIZE),
             editor,
+            background_thread,
 
             bus_config: BusConfig {
                 num_input_channels: config.input_channels,
                 num_output_channels: config.output_channels,
+                input_layout: ChannelLayout::from_num_channels(config.input_channels),
+                output_layout: ChannelLayout::from_num_channels(config.output_channels),
                 // TODO: Expose additional sidechain IO in the JACK backend
                 aux_input_busses: AuxiliaryIOConfig::default(),
                 aux_output_busses: AuxiliaryIOConfig::default(),
@@ -239,10 +262,11 @@ impl<P: Plugin, B: Backend> Wrapper<P, B> {
     }
 
     /// Open the editor, start processing audio, and block this thread until the editor is closed.
-    /// If the plugin does not have an editor, then this will block until SIGINT is received.
+    /// If the plugin does not have an editor or if the editor could not be opened, then this will
+    /// run in headless mode and block until the process is interrupted or until the plugin
+    /// returns an error.
     ///
-    /// Will return an error if the plugin threw an error during audio processing or if the editor
-    /// could not be opened.
+    /// Will return an error if the plugin threw an error during audio processing.
     pub fn run(self: Arc<Self>) -> Result<(), WrapperError> {
         let (gui_task_sender, gui_task_receiver) = channel::bounded(512);
 
@@ -256,7 +280,7 @@ impl<P: Plugin, B: Backend> Wrapper<P, B> {
             thread::spawn(move || this.run_audio_thread(terminate_audio_thread, gui_task_sender))
         };
 
-        match self.editor.clone() {
+        let run_headless = match self.editor.clone() {
             Some(editor) => {
                 let context = self.clone().make_gui_context(gui_task_sender);
 
@@ -269,7 +293,12 @@ impl<P: Plugin, B: Backend> Wrapper<P, B> {
                     baseview::WindowScalePolicy::ScaleFactor(self.config.dpi_scale as f64)
                 };
 
+                // If the editor cannot be spawned, then the window will close itself right away and
+                // we'll continue without a GUI
+                let editor_failed = Arc::new(AtomicBool::new(false));
                 let (width, height) = editor.size();
+                let window_gui_task_receiver = gui_task_receiver.clone();
+                let window_editor_failed = editor_failed.clone();
                 Window::open_blocking(
                     WindowOpenOptions {
                         title: String::from(P::NAME),
@@ -281,28 +310,36 @@ impl<P: Plugin, B: Backend> Wrapper<P, B> {
                         gl_config: None,
                     },
                     move |window| {
-                        // TODO: This spawn function should be able to fail and return an error, but
-                        //       baseview does not support this yet. Once this is added, we should
-                        //       immediately close the parent window when this happens so the loop
-                        //       can exit.
                         let editor_handle = editor.spawn(
                             ParentWindowHandle {
                                 handle: window.raw_window_handle(),
                             },
                             context,
                         );
+                        if editor_handle.is_none() {
+                            eprintln!("The editor could not be opened, continuing without a GUI");
+                            window_editor_failed.store(true, Ordering::SeqCst);
+                        }
 
                         WrapperWindowHandler {
-                            _editor_handle: editor_handle,
-                            gui_task_receiver,
+                            editor_handle,
+                            gui_task_receiver: window_gui_task_receiver,
                         }
                     },
-                )
+                );
+
+                editor_failed.load(Ordering::SeqCst)
             }
-            None => {
-                // TODO: Block until SIGINT is received if the plugin does not have an editor
-                // TODO: Make sure to handle `GuiTask::Close` here as well
-                todo!("Support standalone plugins without editors");
+            None => true,
+        };
+
+        if run_headless {
+            // Without a GUI we'll keep processing audio until the audio thread tells us to stop.
+            // Interrupting the process will still terminate it as usual.
+            while let Ok(task) = gui_task_receiver.recv() {
+                if let GuiTask::Close = task {
+                    break;
+                }
             }
         }
 
@@ -347,10 +384,10 @@ impl<P: Plugin, B: Backend> Wrapper<P, B> {
 
     /// Get the plugin's state object, may be called by the plugin's GUI as part of its own preset
     /// management. The wrapper doesn't use these functions and serializes and deserializes directly
-    /// the JSON in the relevant plugin API methods instead.
+    /// the state in the relevant plugin API methods instead.
     pub fn get_state_object(&self) -> PluginState {
         unsafe {
-            state::serialize_object(
+            state::serialize_object::<P>(
                 self.params.clone(),
                 self.param_map
                     .iter()
@@ -362,7 +399,9 @@ impl<P: Plugin, B: Backend> Wrapper<P, B> {
     /// Update the plugin's internal state, called by the plugin itself from the GUI thread. To
     /// prevent corrupting data and changing parameters during processing the actual state is only
     /// updated at the end of the audio processing cycle.
-    pub fn set_state_object(&self, state: PluginState) {
+    pub fn set_state_object(&self, mut state: PluginState) {
+        state::prepare_state_restore::<P>(&mut state, &self.undo_history);
+
         match self.updated_state_sender.send(state) {
             Ok(_) => {
                 // As mentioned above, the state object will be passed back to this thread
@@ -404,11 +443,16 @@ impl<P: Plugin, B: Backend> Wrapper<P, B> {
             transport.time_sig_denominator = Some(self.config.timesig_denom as i32);
             transport.playing = true;
 
-            if let ProcessStatus::Error(err) = self
-                .plugin
-                .write()
-                .process(buffer, &mut self.make_process_context(transport))
-            {
+            // The standalone target does not expose any auxiliary IO yet
+            let mut aux = AuxiliaryBuffers {
+                inputs: &mut [],
+                outputs: &mut [],
+            };
+            if let ProcessStatus::Error(err) = self.plugin.write().process(
+                buffer,
+                &mut aux,
+                &mut self.make_process_context(transport),
+            ) {
                 eprintln!("The plugin returned an error while processing:");
                 eprintln!("{}", err);
 
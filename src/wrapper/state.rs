@@ -1,19 +1,53 @@
 //! Utilities for saving a [crate::plugin::Plugin]'s state. The actual state object is also exposed
 //! to plugins through the [`GuiContext`][crate::prelude::GuiContext].
 
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use crate::param::internals::{ParamPtr, Params};
+use crate::param::internals::{field_generation, restore_fields, ParamPtr, Params};
 use crate::param::{Param, ParamMut};
-use crate::plugin::BufferConfig;
+use crate::plugin::{BufferConfig, Plugin};
+use crate::wrapper::undo::UndoHistory;
+
+/// The magic bytes at the start of state serialized using [`StateFormat::Binary`]. JSON state
+/// always starts with an opening curly brace, so these can never be confused.
+const BINARY_STATE_MAGIC: &[u8; 8] = b"NIHSTBIN";
+/// The magic bytes at the start of state serialized using [`StateFormat::CompressedBinary`].
+const COMPRESSED_BINARY_STATE_MAGIC: &[u8; 8] = b"NIHSTZLB";
+
+/// The encoding used when the wrappers save a plugin's state. See [`Plugin::STATE_FORMAT`]. State
+/// in any of these formats can always be loaded regardless of the plugin's current setting, so
+/// switching between formats or loading state saved by older versions of the plugin is always
+/// possible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateFormat {
+    /// Plain JSON. This is the most portable option and it's easy to inspect when debugging.
+    Json,
+    /// A self-describing binary encoding based on MessagePack. This is more compact than JSON for
+    /// the parameter values, but persistent fields are still serialized to JSON first and then
+    /// converted to MessagePack values. Their structure is thus limited to what JSON can represent:
+    /// byte buffers become arrays of numbers and all floating point numbers are stored as 64-bit
+    /// floats, so large persistent fields may not end up much smaller than with
+    /// [`StateFormat::Json`].
+    Binary,
+    /// The same binary encoding as [`StateFormat::Binary`], but compressed using zlib. This is
+    /// the best option for plugins that persist large amounts of data, like wavetables, since the
+    /// compression makes up for most of the overhead of the persistent fields' JSON conversion.
+    CompressedBinary,
+}
 
 // These state objects are also exposed directly to the plugin so it can do its own internal preset
 // management
 
 /// A plain, unnormalized value for a parameter.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ParamValue {
     F32(f32),
@@ -23,8 +57,14 @@ pub enum ParamValue {
 
 /// A plugin's state so it can be restored at a later point. This object can be serialized and
 /// deserialized using serde.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PluginState {
+    /// The plugin's version at the time this state was saved. This can be used in
+    /// [`Plugin::filter_state()`] to upgrade state from older versions of the plugin. This is an
+    /// empty string for state that was saved before this field was added.
+    #[serde(default)]
+    pub version: String,
+
     /// The plugin's parameter values. These are stored unnormalized. This mean sthe old values will
     /// be recalled when when the parameter's range gets increased. Doing so may still mess with
     /// parameter automation though, depending on how the host impelments that.
@@ -38,6 +78,60 @@ pub struct PluginState {
     pub fields: HashMap<String, String>,
 }
 
+/// The representation of a [`PluginState`] used by the binary state formats. The persistent fields
+/// are stored as structured values rather than as JSON strings so they don't end up as escaped
+/// strings inside of the MessagePack data. Since [`PluginState`] stores the fields as JSON, this
+/// does involve an additional conversion through [`serde_json::Value`].
+#[derive(Serialize)]
+struct BinaryPluginStateRef<'a> {
+    version: &'a str,
+    params: &'a HashMap<String, ParamValue>,
+    fields: HashMap<&'a str, serde_json::Value>,
+}
+
+/// The owned counterpart to [`BinaryPluginStateRef`] used when decoding binary state.
+#[derive(Deserialize)]
+struct BinaryPluginState {
+    #[serde(default)]
+    version: String,
+    params: HashMap<String, ParamValue>,
+    fields: HashMap<String, serde_json::Value>,
+}
+
+impl<'a> BinaryPluginStateRef<'a> {
+    fn new(state: &'a PluginState) -> Result<Self, String> {
+        let fields = state
+            .fields
+            .iter()
+            .map(|(name, value)| {
+                serde_json::from_str(value)
+                    .map(|value| (name.as_str(), value))
+                    .map_err(|err| format!("Invalid JSON in persistent field '{name}': {err}"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            version: &state.version,
+            params: &state.params,
+            fields,
+        })
+    }
+}
+
+impl From<BinaryPluginState> for PluginState {
+    fn from(state: BinaryPluginState) -> Self {
+        PluginState {
+            version: state.version,
+            params: state.params,
+            fields: state
+                .fields
+                .into_iter()
+                .map(|(name, value)| (name, value.to_string()))
+                .collect(),
+        }
+    }
+}
+
 /// Create a parameters iterator from the hashtables stored in the plugin wrappers. This avoids
 /// having to call `.param_map()` again, which may include expensive user written code.
 pub(crate) fn make_params_iter<'a>(
@@ -64,17 +158,69 @@ pub(crate) fn make_params_getter<'a>(
     }
 }
 
-/// Serialize a plugin's state to a state object. This is separate from [`serialize_json()`] to
+/// Finds out whether a plugin's persistent fields have been modified using
+/// [`PersistentField::set()`][crate::param::internals::PersistentField::set()]. Those fields don't
+/// know which plugin instance they belong to, so setting a field only increments a shared
+/// generation counter. The wrappers poll that counter on the audio thread using
+/// [`should_check()`][Self::should_check()], and then call [`check()`][Self::check()] on the main
+/// thread to compare the plugin's serialized fields to the values the tracker has last seen.
+pub(crate) struct FieldChangeTracker {
+    plugin_params: Arc<dyn Params>,
+    /// The field generation as of the last [`should_check()`][Self::should_check()] call.
+    last_generation: AtomicUsize,
+    /// The plugin's serialized persistent fields as of the last time they were checked.
+    last_fields: Mutex<HashMap<String, String>>,
+}
+
+impl FieldChangeTracker {
+    /// Start tracking changes made to `plugin_params`'s persistent fields. This serializes the
+    /// fields, so it should be called from the main thread.
+    pub fn new(plugin_params: Arc<dyn Params>) -> Self {
+        Self {
+            last_generation: AtomicUsize::new(field_generation()),
+            last_fields: Mutex::new(plugin_params.serialize_fields()),
+            plugin_params,
+        }
+    }
+
+    /// Whether any plugin instance's persistent fields have been set since the last time this
+    /// function was called. If this returns `true`, then [`check()`][Self::check()] should be
+    /// called on the main thread. This is realtime safe.
+    pub fn should_check(&self) -> bool {
+        let generation = field_generation();
+        self.last_generation.swap(generation, Ordering::Relaxed) != generation
+    }
+
+    /// Check whether this plugin's persistent fields have changed since the last check. This
+    /// serializes the fields, so it should only be called from the main thread.
+    pub fn check(&self) -> bool {
+        let fields = self.plugin_params.serialize_fields();
+        let mut last_fields = self.last_fields.lock();
+        let changed = *last_fields != fields;
+        *last_fields = fields;
+
+        changed
+    }
+
+    /// Forget about the changes made to the persistent fields so far. This should be called after
+    /// the host restores the plugin's state, since restoring state should not count as a change.
+    pub fn reset(&self) {
+        *self.last_fields.lock() = self.plugin_params.serialize_fields();
+    }
+}
+
+/// Serialize a plugin's state to a state object. This is separate from [`serialize_bytes()`] to
 /// allow passing the raw object directly to the plugin. The parameters are not pulled directly from
 /// `plugin_params` by default to avoid unnecessary allocations in the `.param_map()` method, as the
 /// plugin wrappers will already have a list of parameters handy. See [`make_params_iter()`].
-pub(crate) unsafe fn serialize_object<'a>(
+pub(crate) unsafe fn serialize_object<'a, P: Plugin>(
     plugin_params: Arc<dyn Params>,
     params_iter: impl IntoIterator<Item = (&'a String, ParamPtr)>,
 ) -> PluginState {
-    // We'll serialize parameter values as a simple `string_param_id: display_value` map.
-    // NOTE: If the plugin is being modulated (and the plugin is a CLAP plugin in Bitwig Studio),
-    //       then this should save the values without any modulation applied to it
+    // We'll serialize parameter values as a simple `string_param_id: display_value` map. The
+    // host's modulation is stored separately from the parameter's value, so if the plugin is being
+    // modulated (and the plugin is a CLAP plugin in Bitwig Studio) we'll only save the unmodulated
+    // values. The modulation is not part of the plugin's state.
     let params: HashMap<_, _> = params_iter
         .into_iter()
         .map(|(param_id_str, param_ptr)| match param_ptr {
@@ -103,17 +249,78 @@ pub(crate) unsafe fn serialize_object<'a>(
     // storing things like sample data.
     let fields = plugin_params.serialize_fields();
 
-    PluginState { params, fields }
+    PluginState {
+        version: String::from(P::VERSION),
+        params,
+        fields,
+    }
 }
 
-/// Serialize a plugin's state to a vector containing JSON data. This can (and should) be shared
-/// across plugin formats.
-pub(crate) unsafe fn serialize_json<'a>(
+/// Serialize a plugin's state to a vector of bytes using the plugin's
+/// [`STATE_FORMAT`][Plugin::STATE_FORMAT]. This can (and should) be shared across plugin formats.
+pub(crate) unsafe fn serialize_bytes<'a, P: Plugin>(
     plugin_params: Arc<dyn Params>,
     params_iter: impl IntoIterator<Item = (&'a String, ParamPtr)>,
-) -> serde_json::Result<Vec<u8>> {
-    let plugin_state = serialize_object(plugin_params, params_iter);
-    serde_json::to_vec(&plugin_state)
+) -> Result<Vec<u8>, String> {
+    let plugin_state = serialize_object::<P>(plugin_params, params_iter);
+    encode_state(&plugin_state, P::STATE_FORMAT)
+}
+
+/// Encode a state object using one of the supported state formats.
+pub(crate) fn encode_state(state: &PluginState, format: StateFormat) -> Result<Vec<u8>, String> {
+    match format {
+        StateFormat::Json => serde_json::to_vec(state).map_err(|err| err.to_string()),
+        StateFormat::Binary => {
+            let state = BinaryPluginStateRef::new(state)?;
+            let mut data = BINARY_STATE_MAGIC.to_vec();
+            rmp_serde::encode::write_named(&mut data, &state).map_err(|err| err.to_string())?;
+
+            Ok(data)
+        }
+        StateFormat::CompressedBinary => {
+            let state = BinaryPluginStateRef::new(state)?;
+            let mut encoder = ZlibEncoder::new(
+                COMPRESSED_BINARY_STATE_MAGIC.to_vec(),
+                Compression::default(),
+            );
+            rmp_serde::encode::write_named(&mut encoder, &state).map_err(|err| err.to_string())?;
+
+            encoder.finish().map_err(|err| err.to_string())
+        }
+    }
+}
+
+/// Decode a state object that was encoded using [`encode_state()`]. The format is detected
+/// automatically, and legacy JSON state without any header can always be loaded.
+pub(crate) fn decode_state(data: &[u8]) -> Result<PluginState, String> {
+    if let Some(data) = data.strip_prefix(COMPRESSED_BINARY_STATE_MAGIC) {
+        let mut decompressed = Vec::new();
+        ZlibDecoder::new(data)
+            .read_to_end(&mut decompressed)
+            .map_err(|err| err.to_string())?;
+
+        rmp_serde::from_slice::<BinaryPluginState>(&decompressed)
+            .map(PluginState::from)
+            .map_err(|err| err.to_string())
+    } else if let Some(data) = data.strip_prefix(BINARY_STATE_MAGIC) {
+        rmp_serde::from_slice::<BinaryPluginState>(data)
+            .map(PluginState::from)
+            .map_err(|err| err.to_string())
+    } else {
+        serde_json::from_slice(data).map_err(|err| err.to_string())
+    }
+}
+
+/// Prepare a state object for being restored with [`deserialize_object()`]. The state is upgraded
+/// using [`Plugin::filter_state()`], which should happen on the GUI thread so the audio thread
+/// doesn't have to do this. The undo history is cleared since its steps no longer apply to the
+/// restored state.
+pub(crate) fn prepare_state_restore<P: Plugin>(
+    state: &mut PluginState,
+    undo_history: &UndoHistory,
+) {
+    P::filter_state(state);
+    undo_history.clear();
 }
 
 /// Deserialize a plugin's state from a [`PluginState`] object. This is used to allow the plugin to
@@ -123,6 +330,9 @@ pub(crate) unsafe fn serialize_json<'a>(
 /// This uses a parameter getter function to avoid having to rebuild the parameter map, which may
 /// include expensive user written code. See [`make_params_getter()`].
 ///
+/// The state should already have been passed through [`prepare_state_restore()`]. This is done
+/// separately so the state can be upgraded on the GUI thread before it's sent to the audio thread.
+///
 /// Make sure to reinitialize plugin after deserializing the state so it can react to the new
 /// parameter values. The smoothers have already been reset by this function.
 pub(crate) unsafe fn deserialize_object(
@@ -168,29 +378,129 @@ pub(crate) unsafe fn deserialize_object(
 
     // The plugin can also persist arbitrary fields alongside its parameters. This is useful for
     // storing things like sample data.
-    plugin_params.deserialize_fields(&state.fields);
+    restore_fields(plugin_params.as_ref(), &state.fields);
 
     true
 }
 
-/// Deserialize a plugin's state from a vector containing JSON data. This can (and should) be shared
-/// across plugin formats. Returns `false` and logs an error if the state could not be deserialized.
+/// Deserialize a plugin's state from a vector of bytes. The state may be stored in any of the
+/// supported [`StateFormat`]s. This can (and should) be shared across plugin formats. Returns
+/// `false` and logs an error if the state could not be deserialized. The state is passed through
+/// [`prepare_state_restore()`] before it gets restored, so this also clears the undo history.
 ///
 /// Make sure to reinitialize plugin after deserializing the state so it can react to the new
 /// parameter values. The smoothers have already been reset by this function.
-pub(crate) unsafe fn deserialize_json(
+pub(crate) unsafe fn deserialize_bytes<P: Plugin>(
     state: &[u8],
     plugin_params: Arc<dyn Params>,
     params_getter: impl Fn(&str) -> Option<ParamPtr>,
     current_buffer_config: Option<&BufferConfig>,
+    undo_history: &UndoHistory,
 ) -> bool {
-    let state: PluginState = match serde_json::from_slice(state) {
+    let mut state = match decode_state(state) {
         Ok(s) => s,
         Err(err) => {
             nih_debug_assert_failure!("Error while deserializing state: {}", err);
             return false;
         }
     };
+    prepare_state_restore::<P>(&mut state, undo_history);
 
     deserialize_object(&state, plugin_params, params_getter, current_buffer_config)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::internals::{deserialize_field, serialize_field, PersistentField};
+
+    fn make_state() -> PluginState {
+        PluginState {
+            version: String::from("1.0.0"),
+            params: HashMap::from([
+                (String::from("gain"), ParamValue::F32(-6.0)),
+                (String::from("mode"), ParamValue::I32(2)),
+                (String::from("bypass"), ParamValue::Bool(true)),
+            ]),
+            fields: HashMap::from([(String::from("table"), String::from("[0.0,0.5,1.0]"))]),
+        }
+    }
+
+    #[test]
+    fn roundtrip_all_formats() {
+        let state = make_state();
+        for format in [
+            StateFormat::Json,
+            StateFormat::Binary,
+            StateFormat::CompressedBinary,
+        ] {
+            let encoded = encode_state(&state, format).unwrap();
+            let decoded = decode_state(&encoded).unwrap();
+            assert_eq!(decoded, state);
+        }
+    }
+
+    #[test]
+    fn binary_fields_are_not_json_strings() {
+        let state = make_state();
+        let encoded = encode_state(&state, StateFormat::Binary).unwrap();
+        assert!(!encoded
+            .windows(state.fields["table"].len())
+            .any(|window| window == state.fields["table"].as_bytes()));
+    }
+
+    #[test]
+    fn load_legacy_json() {
+        let decoded = decode_state(br#"{"params":{"gain":{"f32":-6.0}},"fields":{}}"#).unwrap();
+        assert_eq!(decoded.version, "");
+        assert_eq!(decoded.params["gain"], ParamValue::F32(-6.0));
+    }
+
+    /// A single persistent field, serialized the same way the derive macro would.
+    #[derive(Default)]
+    struct TrackedParams {
+        value: parking_lot::RwLock<f32>,
+    }
+
+    unsafe impl Params for TrackedParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            Vec::new()
+        }
+
+        fn serialize_fields(&self) -> HashMap<String, String> {
+            let value = PersistentField::map(&self.value, serialize_field).unwrap();
+            HashMap::from([(String::from("value"), value)])
+        }
+
+        fn deserialize_fields(&self, serialized: &HashMap<String, String>) {
+            if let Some(data) = serialized.get("value") {
+                PersistentField::set(&self.value, deserialize_field(data).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn field_change_tracker() {
+        let params = Arc::new(TrackedParams::default());
+        let tracker = FieldChangeTracker::new(params.clone());
+
+        params.value.set(1.0);
+        assert!(tracker.should_check());
+        assert!(tracker.check());
+
+        // Setting the same value again or setting some other instance's fields is not a change
+        params.value.set(1.0);
+        TrackedParams::default().value.set(2.0);
+        assert!(tracker.should_check());
+        assert!(!tracker.check());
+
+        // Restoring state also doesn't count as a change
+        restore_fields(
+            params.as_ref(),
+            &HashMap::from([(String::from("value"), String::from("3.0"))]),
+        );
+        tracker.reset();
+        assert_eq!(*params.value.read(), 3.0);
+        assert!(!tracker.check());
+    }
+}
@@ -0,0 +1,467 @@
+//! An undo history for parameter gestures and persistent field changes made from the plugin's
+//! editor. This is shared between the wrappers and it's used to implement
+//! [`GuiContext::undo()`][crate::prelude::GuiContext::undo()] and friends. The history is only
+//! enabled when [`Plugin::UNDO_HISTORY_SIZE`][crate::prelude::Plugin::UNDO_HISTORY_SIZE] is set to
+//! a non-zero value.
+
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+
+use crate::context::GuiContext;
+use crate::param::internals::{ParamPtr, Params};
+
+/// Records the parameter gestures and persistent field changes made through the
+/// [`GuiContext`]. All of these functions should be called from the GUI thread.
+pub(crate) struct UndoHistory {
+    /// The maximum number of undo steps. The history is disabled if this is zero.
+    max_size: usize,
+    inner: Mutex<UndoHistoryInner>,
+}
+
+#[derive(Default)]
+struct UndoHistoryInner {
+    /// The steps that can be undone, with the most recent step at the back.
+    undo_stack: VecDeque<UndoStep>,
+    /// The steps that have been undone and that can be redone, with the most recently undone step
+    /// at the back. This is cleared whenever a new step is recorded.
+    redo_stack: Vec<UndoStep>,
+
+    /// The parameters that are currently being changed as part of a gesture. The first value is
+    /// the parameter's normalized value at the start of the gesture, and the second value is the
+    /// last normalized value that was set during the gesture, if any.
+    active_gestures: HashMap<ParamPtr, (f32, Option<f32>)>,
+    /// A snapshot of the persistent fields taken at the start of
+    /// [`UndoHistory::begin_field_changes()`].
+    field_snapshot: Option<HashMap<String, String>>,
+    /// Set while undoing or redoing a step. The parameter changes resulting from that should not
+    /// be recorded as new steps.
+    replaying: bool,
+}
+
+/// A single step in the undo history.
+enum UndoStep {
+    /// A parameter was changed from one normalized value to another as part of a gesture.
+    Parameter {
+        param: ParamPtr,
+        old_normalized: f32,
+        new_normalized: f32,
+    },
+    /// One or more persistent fields were changed. These contain the JSON-serialized values for
+    /// only the fields that were changed.
+    Fields {
+        old: HashMap<String, String>,
+        new: HashMap<String, String>,
+    },
+}
+
+impl UndoHistory {
+    /// Create a new undo history. If `max_size` is zero then nothing will be recorded.
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            max_size,
+            inner: Mutex::new(UndoHistoryInner::default()),
+        }
+    }
+
+    /// Whether the undo history is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.max_size > 0
+    }
+
+    /// Remove all steps from the history. Called when the plugin's state gets replaced.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock();
+        inner.undo_stack.clear();
+        inner.redo_stack.clear();
+        inner.active_gestures.clear();
+        inner.field_snapshot = None;
+    }
+
+    /// Called at the start of a parameter gesture.
+    pub fn begin_gesture(&self, param: ParamPtr) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut inner = self.inner.lock();
+        if inner.replaying {
+            return;
+        }
+
+        let old_normalized = unsafe { param.unmodulated_normalized_value() };
+        inner
+            .active_gestures
+            .entry(param)
+            .or_insert((old_normalized, None));
+    }
+
+    /// Called whenever a new value is set during a parameter gesture. Some wrappers only update the
+    /// parameter's value at a later point, so we can't rely on the parameter's value when the
+    /// gesture ends.
+    pub fn record_value(&self, param: ParamPtr, normalized: f32) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut inner = self.inner.lock();
+        if let Some((_, new_normalized)) = inner.active_gestures.get_mut(&param) {
+            *new_normalized = Some(normalized);
+        }
+    }
+
+    /// Called at the end of a parameter gesture. If the parameter's value changed during the
+    /// gesture then this adds a new step to the history.
+    pub fn end_gesture(&self, param: ParamPtr) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut inner = self.inner.lock();
+        if let Some((old_normalized, Some(new_normalized))) = inner.active_gestures.remove(&param) {
+            if new_normalized != old_normalized {
+                self.push_step(
+                    &mut inner,
+                    UndoStep::Parameter {
+                        param,
+                        old_normalized,
+                        new_normalized,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Take a snapshot of the plugin's persistent fields before the plugin modifies them.
+    pub fn begin_field_changes(&self, params: &dyn Params) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let snapshot = params.serialize_fields();
+        let mut inner = self.inner.lock();
+        nih_debug_assert!(
+            inner.field_snapshot.is_none(),
+            "begin_field_changes() was called twice without a matching end_field_changes() call"
+        );
+        inner.field_snapshot = Some(snapshot);
+    }
+
+    /// Compare the plugin's persistent fields to the snapshot taken in
+    /// [`begin_field_changes()`][Self::begin_field_changes()], and add a step to the history if
+    /// any of them changed.
+    pub fn end_field_changes(&self, params: &dyn Params) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut old_fields = match self.inner.lock().field_snapshot.take() {
+            Some(snapshot) => snapshot,
+            None => {
+                nih_debug_assert_failure!(
+                    "end_field_changes() was called without a matching begin_field_changes() call"
+                );
+                return;
+            }
+        };
+        let mut new_fields = params.serialize_fields();
+
+        // Only the fields that were actually changed are stored in the history
+        old_fields.retain(|key, value| new_fields.get(key) != Some(value));
+        new_fields.retain(|key, _| old_fields.contains_key(key));
+        if old_fields.is_empty() {
+            return;
+        }
+
+        let mut inner = self.inner.lock();
+        self.push_step(
+            &mut inner,
+            UndoStep::Fields {
+                old: old_fields,
+                new: new_fields,
+            },
+        );
+    }
+
+    /// Whether there's a step that can be undone.
+    pub fn can_undo(&self) -> bool {
+        !self.inner.lock().undo_stack.is_empty()
+    }
+
+    /// Whether there's a step that can be redone.
+    pub fn can_redo(&self) -> bool {
+        !self.inner.lock().redo_stack.is_empty()
+    }
+
+    /// Undo the most recent step. Parameter changes are sent to the host through `context` like
+    /// any other parameter change. Returns `false` if there was nothing to undo.
+    pub fn undo(&self, context: &dyn GuiContext, params: &dyn Params) -> bool {
+        let step = {
+            let mut inner = self.inner.lock();
+            match inner.undo_stack.pop_back() {
+                Some(step) => {
+                    inner.replaying = true;
+                    step
+                }
+                None => return false,
+            }
+        };
+
+        // The lock can't be held here since applying the step calls back into this object
+        step.apply(context, params, true);
+
+        let mut inner = self.inner.lock();
+        inner.replaying = false;
+        inner.redo_stack.push(step);
+
+        true
+    }
+
+    /// Redo the most recently undone step. Returns `false` if there was nothing to redo.
+    pub fn redo(&self, context: &dyn GuiContext, params: &dyn Params) -> bool {
+        let step = {
+            let mut inner = self.inner.lock();
+            match inner.redo_stack.pop() {
+                Some(step) => {
+                    inner.replaying = true;
+                    step
+                }
+                None => return false,
+            }
+        };
+
+        step.apply(context, params, false);
+
+        let mut inner = self.inner.lock();
+        inner.replaying = false;
+        inner.undo_stack.push_back(step);
+
+        true
+    }
+
+    fn push_step(&self, inner: &mut UndoHistoryInner, step: UndoStep) {
+        inner.redo_stack.clear();
+        inner.undo_stack.push_back(step);
+        while inner.undo_stack.len() > self.max_size {
+            inner.undo_stack.pop_front();
+        }
+    }
+}
+
+impl UndoStep {
+    /// Restore either the old or the new values stored in this step.
+    fn apply(&self, context: &dyn GuiContext, params: &dyn Params, undo: bool) {
+        match self {
+            UndoStep::Parameter {
+                param,
+                old_normalized,
+                new_normalized,
+            } => {
+                let normalized = if undo {
+                    *old_normalized
+                } else {
+                    *new_normalized
+                };
+
+                unsafe {
+                    context.raw_begin_set_parameter(*param);
+                    context.raw_set_parameter_normalized(*param, normalized);
+                    context.raw_end_set_parameter(*param);
+                }
+            }
+            UndoStep::Fields { old, new } => {
+                params.deserialize_fields(if undo { old } else { new });
+                context.mark_dirty();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::context::PluginApi;
+    use crate::param::range::FloatRange;
+    use crate::param::{FloatParam, Param};
+    use crate::wrapper::state::PluginState;
+
+    /// Persistent fields for testing, stored as already serialized values.
+    #[derive(Default)]
+    struct TestParams {
+        fields: Mutex<HashMap<String, String>>,
+    }
+
+    unsafe impl Params for TestParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            Vec::new()
+        }
+
+        fn serialize_fields(&self) -> HashMap<String, String> {
+            self.fields.lock().clone()
+        }
+
+        fn deserialize_fields(&self, serialized: &HashMap<String, String>) {
+            self.fields.lock().extend(serialized.clone());
+        }
+    }
+
+    /// A context that directly applies parameter changes, and that routes gestures to the history
+    /// like the wrappers do.
+    struct TestContext {
+        history: Arc<UndoHistory>,
+    }
+
+    impl GuiContext for TestContext {
+        fn plugin_api(&self) -> PluginApi {
+            PluginApi::Standalone
+        }
+
+        fn request_resize(&self) -> bool {
+            false
+        }
+
+        unsafe fn raw_begin_set_parameter(&self, param: ParamPtr) {
+            self.history.begin_gesture(param);
+        }
+
+        unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
+            self.history.record_value(param, normalized);
+            param.set_normalized_value(normalized);
+        }
+
+        unsafe fn raw_end_set_parameter(&self, param: ParamPtr) {
+            self.history.end_gesture(param);
+        }
+
+        fn get_state(&self) -> PluginState {
+            PluginState {
+                version: String::new(),
+                params: HashMap::new(),
+                fields: HashMap::new(),
+            }
+        }
+
+        fn set_state(&self, _state: PluginState) {}
+
+        fn mark_dirty(&self) {}
+
+        fn undo(&self) -> bool {
+            false
+        }
+
+        fn redo(&self) -> bool {
+            false
+        }
+
+        fn can_undo(&self) -> bool {
+            self.history.can_undo()
+        }
+
+        fn can_redo(&self) -> bool {
+            self.history.can_redo()
+        }
+
+        fn raw_begin_field_changes(&self) {}
+
+        fn raw_end_field_changes(&self) {}
+    }
+
+    /// Read the parameter's value the same way the wrappers do.
+    fn normalized_value(param: &FloatParam) -> f32 {
+        unsafe { param.as_ptr().unmodulated_normalized_value() }
+    }
+
+    fn set_param(context: &TestContext, param: &FloatParam, normalized: f32) {
+        unsafe {
+            context.raw_begin_set_parameter(param.as_ptr());
+            context.raw_set_parameter_normalized(param.as_ptr(), normalized);
+            context.raw_end_set_parameter(param.as_ptr());
+        }
+    }
+
+    #[test]
+    fn undo_redo_parameter() {
+        let history = Arc::new(UndoHistory::new(10));
+        let context = TestContext {
+            history: history.clone(),
+        };
+        let params = TestParams::default();
+        let param = FloatParam::new("Test", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 });
+
+        set_param(&context, &param, 0.25);
+        set_param(&context, &param, 0.75);
+        assert!(history.can_undo());
+        assert!(!history.can_redo());
+
+        assert!(history.undo(&context, &params));
+        assert_eq!(normalized_value(&param), 0.25);
+        assert!(history.undo(&context, &params));
+        assert_eq!(normalized_value(&param), 0.0);
+        assert!(!history.undo(&context, &params));
+
+        assert!(history.redo(&context, &params));
+        assert_eq!(normalized_value(&param), 0.25);
+        assert!(history.can_redo());
+
+        // A new change discards the redo steps
+        set_param(&context, &param, 0.5);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn undo_redo_fields() {
+        let history = Arc::new(UndoHistory::new(10));
+        let context = TestContext {
+            history: history.clone(),
+        };
+        let params = TestParams::default();
+        params
+            .fields
+            .lock()
+            .insert(String::from("table"), String::from("[1]"));
+
+        history.begin_field_changes(&params);
+        params
+            .fields
+            .lock()
+            .insert(String::from("table"), String::from("[2]"));
+        history.end_field_changes(&params);
+
+        assert!(history.undo(&context, &params));
+        assert_eq!(params.fields.lock()["table"], "[1]");
+        assert!(history.redo(&context, &params));
+        assert_eq!(params.fields.lock()["table"], "[2]");
+    }
+
+    #[test]
+    fn history_size() {
+        let history = Arc::new(UndoHistory::new(2));
+        let context = TestContext {
+            history: history.clone(),
+        };
+        let params = TestParams::default();
+        let param = FloatParam::new("Test", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 });
+
+        for value in [0.25, 0.5, 0.75] {
+            set_param(&context, &param, value);
+        }
+
+        assert!(history.undo(&context, &params));
+        assert!(history.undo(&context, &params));
+        assert!(!history.undo(&context, &params));
+        assert_eq!(normalized_value(&param), 0.25);
+    }
+
+    #[test]
+    fn disabled() {
+        let history = Arc::new(UndoHistory::new(0));
+        let context = TestContext {
+            history: history.clone(),
+        };
+        let param = FloatParam::new("Test", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 });
+
+        set_param(&context, &param, 0.5);
+        assert!(!history.can_undo());
+    }
+}
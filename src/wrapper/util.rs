@@ -4,6 +4,10 @@ use std::marker::PhantomData;
 use std::os::raw::c_char;
 use std::sync::atomic::{AtomicBool, Ordering};
 
+use crate::context::GuiContext;
+use crate::param::internals::ParamPtr;
+use crate::param::ParamFlags;
+
 /// The environment variable for controlling the logging behavior.
 const NIH_LOG_ENV: &str = "NIH_LOG";
 
@@ -32,6 +36,28 @@ pub fn hash_param_id(id: &str) -> u32 {
     hash
 }
 
+/// Convert a CLAP or VST3 note ID to a voice ID. Both APIs use -1 to indicate that the host did
+/// not assign an ID to a note.
+pub fn voice_id_from_note_id(note_id: i32) -> Option<i32> {
+    if note_id != -1 {
+        Some(note_id)
+    } else {
+        None
+    }
+}
+
+/// Mark the plugin's state as dirty at the end of a parameter gesture if the parameter is not
+/// automatable. The host won't save changes to non-automatable parameters on its own.
+///
+/// # Safety
+///
+/// `param` needs to point to a parameter that's still alive.
+pub unsafe fn mark_dirty_if_non_automatable(context: &dyn GuiContext, param: ParamPtr) {
+    if param.flags().contains(ParamFlags::NON_AUTOMATABLE) {
+        context.mark_dirty();
+    }
+}
+
 /// The equivalent of the `strlcpy()` C function. Copy `src` to `dest` as a null-terminated
 /// C-string. If `dest` does not have enough capacity, add a null terminator at the end to prevent
 /// buffer overflows.
@@ -1,15 +1,17 @@
-use atomic_refcell::AtomicRefMut;
+use atomic_refcell::{AtomicRef, AtomicRefMut};
 use std::collections::VecDeque;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use vst3_sys::vst::{IComponentHandler, RestartFlags};
 
-use super::inner::{Task, WrapperInner};
-use crate::context::{GuiContext, PluginApi, ProcessContext, Transport};
-use crate::midi::NoteEvent;
+use super::inner::{OutputParamChange, Task, WrapperInner};
+use crate::context::{GuiContext, ParamChange, PluginApi, ProcessContext, Transport};
+use crate::midi::{NoteEvent, SysExMessage, SysExStorage};
 use crate::param::internals::ParamPtr;
 use crate::plugin::Vst3Plugin;
+use crate::wrapper::param_changes::ParamChangeQueues;
 use crate::wrapper::state::PluginState;
+use crate::wrapper::util::mark_dirty_if_non_automatable;
 
 /// A [`GuiContext`] implementation for the wrapper. This is passed to the plugin in
 /// [`Editor::spawn()`][crate::prelude::Editor::spawn()] so it can interact with the rest of the plugin and
@@ -25,6 +27,8 @@ pub(crate) struct WrapperProcessContext<'a, P: Vst3Plugin> {
     pub(super) inner: &'a WrapperInner<P>,
     pub(super) input_events_guard: AtomicRefMut<'a, VecDeque<NoteEvent>>,
     pub(super) output_events_guard: AtomicRefMut<'a, VecDeque<NoteEvent>>,
+    pub(super) param_changes_guard: AtomicRef<'a, ParamChangeQueues>,
+    pub(super) sysex_storage_guard: AtomicRefMut<'a, SysExStorage>,
     pub(super) transport: Transport,
 }
 
@@ -48,6 +52,7 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
         match &*self.inner.component_handler.borrow() {
             Some(handler) => match self.inner.param_ptr_to_hash.get(&param) {
                 Some(hash) => {
+                    self.inner.undo_history.begin_gesture(param);
                     handler.begin_edit(*hash);
                 }
                 None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
@@ -79,6 +84,7 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
                         self.inner.notify_param_values_changed();
                     }
 
+                    self.inner.undo_history.record_value(param, normalized);
                     handler.perform_edit(*hash, normalized as f64);
                 }
                 None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
@@ -92,11 +98,14 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
             Some(handler) => match self.inner.param_ptr_to_hash.get(&param) {
                 Some(hash) => {
                     handler.end_edit(*hash);
+                    self.inner.undo_history.end_gesture(param);
                 }
                 None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
             },
             None => nih_debug_assert_failure!("Component handler not yet set"),
         }
+
+        mark_dirty_if_non_automatable(self, param);
     }
 
     fn get_state(&self) -> PluginState {
@@ -104,15 +113,57 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
     }
 
     fn set_state(&self, state: PluginState) {
-        self.inner.set_state_object(state)
+        self.inner.set_state_object(state);
+        self.mark_dirty();
+    }
+
+    fn mark_dirty(&self) {
+        let task_posted = self.inner.do_maybe_async(Task::MarkDirty);
+        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+    }
+
+    fn undo(&self) -> bool {
+        self.inner
+            .undo_history
+            .undo(self, self.inner.params.as_ref())
+    }
+
+    fn redo(&self) -> bool {
+        self.inner
+            .undo_history
+            .redo(self, self.inner.params.as_ref())
+    }
+
+    fn can_undo(&self) -> bool {
+        self.inner.undo_history.can_undo()
+    }
+
+    fn can_redo(&self) -> bool {
+        self.inner.undo_history.can_redo()
+    }
+
+    fn raw_begin_field_changes(&self) {
+        self.inner
+            .undo_history
+            .begin_field_changes(self.inner.params.as_ref());
+    }
+
+    fn raw_end_field_changes(&self) {
+        self.inner
+            .undo_history
+            .end_field_changes(self.inner.params.as_ref());
     }
 }
 
-impl<P: Vst3Plugin> ProcessContext for WrapperProcessContext<'_, P> {
+impl<P: Vst3Plugin> ProcessContext<P> for WrapperProcessContext<'_, P> {
     fn plugin_api(&self) -> PluginApi {
         PluginApi::Vst3
     }
 
+    fn execute_background(&self, task: P::BackgroundTask) {
+        self.inner.background_thread.schedule(task);
+    }
+
     fn transport(&self) -> &Transport {
         &self.transport
     }
@@ -125,6 +176,14 @@ impl<P: Vst3Plugin> ProcessContext for WrapperProcessContext<'_, P> {
         self.output_events_guard.push_back(event);
     }
 
+    fn sysex_data<'a>(&'a self, message: &'a SysExMessage) -> &'a [u8] {
+        self.sysex_storage_guard.get(message)
+    }
+
+    fn new_sysex_message(&mut self, data: &[u8]) -> Option<SysExMessage> {
+        self.sysex_storage_guard.push(data)
+    }
+
     fn set_latency_samples(&self, samples: u32) {
         // Only trigger a restart if it's actually needed
         let old_latency = self.inner.current_latency.swap(samples, Ordering::SeqCst);
@@ -135,4 +194,28 @@ impl<P: Vst3Plugin> ProcessContext for WrapperProcessContext<'_, P> {
             nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
         }
     }
+
+    unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
+        match self.inner.param_ptr_to_hash.get(&param) {
+            Some(hash) => {
+                // The parameter's value will be updated and the change will be sent to the host at
+                // the end of the process call
+                let success = self
+                    .inner
+                    .output_param_changes
+                    .push(OutputParamChange {
+                        param_hash: *hash,
+                        normalized_value: normalized,
+                    })
+                    .is_ok();
+
+                nih_debug_assert!(success, "Parameter output queue was full, parameter change will not be sent to the host");
+            }
+            None => nih_debug_assert_failure!("Unknown parameter: {:?}", param),
+        }
+    }
+
+    fn raw_param_changes(&self, param: ParamPtr) -> &[ParamChange] {
+        self.param_changes_guard.get(param)
+    }
 }
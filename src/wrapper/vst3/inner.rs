@@ -1,30 +1,43 @@
 use atomic_refcell::AtomicRefCell;
 use crossbeam::atomic::AtomicCell;
 use crossbeam::channel::{self, SendTimeoutError};
+use crossbeam::queue::ArrayQueue;
 use parking_lot::RwLock;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::mem::MaybeUninit;
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use vst3_sys::base::{kInvalidArgument, kResultOk, tresult};
-use vst3_sys::vst::{IComponentHandler, RestartFlags};
+use vst3_sys::vst::{IComponentHandler, IComponentHandler2, RestartFlags};
 
 use super::context::{WrapperGuiContext, WrapperProcessContext};
 use super::note_expressions::NoteExpressionController;
 use super::param_units::ParamUnits;
-use super::util::{ObjectPtr, VstPtr, VST3_MIDI_PARAMS_END, VST3_MIDI_PARAMS_START};
+use super::util::{
+    ObjectPtr, VstPtr, VST3_MIDI_PARAMS_END, VST3_MIDI_PARAMS_START, VST3_PROGRAM_CHANGE_PARAM_ID,
+};
 use super::view::WrapperView;
 use crate::buffer::Buffer;
-use crate::context::Transport;
-use crate::event_loop::{EventLoop, MainThreadExecutor, OsEventLoop};
-use crate::midi::{MidiConfig, NoteEvent};
+use crate::context::{ParamChange, Transport};
+use crate::event_loop::{BackgroundThread, EventLoop, MainThreadExecutor, OsEventLoop};
+use crate::midi::{MidiConfig, NoteEvent, SysExStorage};
 use crate::param::internals::{ParamPtr, Params};
 use crate::param::ParamFlags;
-use crate::plugin::{BufferConfig, BusConfig, Editor, ProcessMode, ProcessStatus, Vst3Plugin};
-use crate::wrapper::state::{self, PluginState};
+use crate::plugin::{
+    BufferConfig, BusConfig, ChannelLayout, Editor, FactoryPreset, ProcessMode, ProcessStatus,
+    Vst3Plugin,
+};
+use crate::wrapper::mpe::MpeState;
+use crate::wrapper::param_changes::ParamChangeQueues;
+use crate::wrapper::state::{self, FieldChangeTracker, PluginState};
+use crate::wrapper::undo::UndoHistory;
 use crate::wrapper::util::{hash_param_id, process_wrapper};
 
+/// The maximum number of parameter changes the plugin can make from the audio thread during a
+/// single process call.
+const OUTPUT_PARAM_CHANGES_CAPACITY: usize = 2048;
+
 /// The actual wrapper bits. We need this as an `Arc<T>` so we can safely use our event loop API.
 /// Since we can't combine that with VST3's interior reference counting this just has to be moved to
 /// its own struct.
@@ -39,6 +52,9 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// to instantiate this in advance so we don't need to lock the entire [`Plugin`] object when
     /// creating an editor.
     pub editor: Option<Arc<dyn Editor>>,
+    /// A worker thread that runs the plugin's background tasks using the executor returned by
+    /// [`Plugin::task_executor()`][crate::prelude::Plugin::task_executor()].
+    pub background_thread: BackgroundThread<P::BackgroundTask>,
 
     /// The host's [`IComponentHandler`] instance, if passed through
     /// [`IEditController::set_component_handler`].
@@ -78,6 +94,21 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// between process calls. This buffer owns the vector, because otherwise it would need to store
     /// a mutable reference to the data contained in this mutex.
     pub output_buffer: AtomicRefCell<Buffer<'static>>,
+    /// Stores sample data for every sidechain input the plugin has. Indexed by
+    /// `[sidechain_input][channel][sample]` We'll copy the data to these buffers since modifying
+    /// the host's sidechain input buffers may not be safe, and the plugin may want to be able to
+    /// modify the buffers.
+    pub aux_input_storage: AtomicRefCell<Vec<Vec<Vec<f32>>>>,
+    /// Accompanying buffers for `aux_input_storage`. There is no way to do this in safe Rust, so
+    /// the process function needs to make sure all channel pointers stored in these buffers are
+    /// still accurate before passing it to the plugin.
+    pub aux_input_buffers: AtomicRefCell<Vec<Buffer<'static>>>,
+    /// Scratch sample data for auxiliary outputs the host did not provide buffers for. Indexed the
+    /// same way as `aux_input_storage`.
+    pub aux_output_storage: AtomicRefCell<Vec<Vec<Vec<f32>>>>,
+    /// Buffers for the plugin's auxiliary outputs. Like `output_buffer`, these point to the host's
+    /// output buffers when the host provides them, and to `aux_output_storage` otherwise.
+    pub aux_output_buffers: AtomicRefCell<Vec<Buffer<'static>>>,
     /// The incoming events for the plugin, if `P::ACCEPTS_MIDI` is set. If
     /// `P::SAMPLE_ACCURATE_AUTOMATION`, this is also read in lockstep with the parameter change
     /// block splitting.
@@ -89,11 +120,19 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// Stores any events the plugin has output during the current processing cycle, analogous to
     /// `input_events`.
     pub output_events: AtomicRefCell<VecDeque<NoteEvent>>,
+    /// Storage for the long SysEx messages received and sent during the current processing cycle.
+    /// VST3 data events only contain a pointer to the message, so outgoing messages are also copied
+    /// here and kept alive until the host has read the event. This is cleared at the start of
+    /// every process call.
+    pub sysex_storage: AtomicRefCell<SysExStorage>,
     /// VST3 has several useful predefined note expressions, but for some reason they are the only
     /// note event type that don't have MIDI note ID and channel fields. So we need to keep track of
     /// the msot recent VST3 note IDs we've seen, and then map those back to MIDI note IDs and
     /// channels as needed.
     pub note_expression_controller: AtomicRefCell<NoteExpressionController>,
+    /// Converts MPE member channel messages to polyphonic expression events when
+    /// `P::MPE_INPUT` is enabled.
+    pub mpe_state: AtomicRefCell<MpeState>,
     /// Unprocessed parameter changes and note events sent by the host during a process call.
     /// Parameter changes are sent as separate queues for each parameter, and note events are in
     /// another queue on top of that. And if `P::MIDI_INPUT >= MidiConfig::MidiCCs`, then we can
@@ -104,6 +143,15 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// then do the block splitting based on that. Note events need to have their timing adjusted to
     /// match the block start, since they're all read upfront.
     pub process_events: AtomicRefCell<Vec<ProcessEvent>>,
+    /// Parameter value changes that occur after the first sample of the current buffer, if
+    /// `P::QUEUED_AUTOMATION` is enabled. The last change for each parameter is applied after the
+    /// buffer has been processed.
+    pub param_changes: AtomicRefCell<ParamChangeQueues>,
+    /// Parameter changes made by the plugin from the audio thread through
+    /// [`ProcessContext::set_parameter()`][crate::prelude::ProcessContext::set_parameter()]. These
+    /// are applied and written to the host's output parameter changes at the end of the process
+    /// call.
+    pub output_param_changes: ArrayQueue<OutputParamChange>,
     /// The plugin is able to restore state through a method on the `GuiContext`. To avoid changing
     /// parameters mid-processing and running into garbled data if the host also tries to load state
     /// at the same time the restoring happens at the end of each processing call. If this zero
@@ -123,6 +171,10 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// `params` object does not get deallocated.
     pub param_by_hash: HashMap<u32, ParamPtr>,
     pub param_units: ParamUnits,
+    /// Hashes of the parameters that belong to a group marked as hidden through
+    /// [`ParamGroup::hidden`][crate::param::internals::ParamGroup::hidden]. These parameters are
+    /// treated the same way as parameters with the [`ParamFlags::HIDDEN`] flag.
+    pub hidden_param_hashes: HashSet<u32>,
     /// Mappings from string parameter indentifiers to parameter hashes. Useful for debug logging
     /// and when storing and restorign plugin state.
     pub param_id_to_hash: HashMap<String, u32>,
@@ -131,6 +183,20 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// having to add a setter function to the parameter (or even worse, have it be completely
     /// untyped).
     pub param_ptr_to_hash: HashMap<ParamPtr, u32>,
+    /// The undo history for parameter gestures and persistent field changes made from the
+    /// plugin's editor. This only records anything if the plugin has set
+    /// [`Plugin::UNDO_HISTORY_SIZE`][crate::prelude::Plugin::UNDO_HISTORY_SIZE].
+    pub undo_history: UndoHistory,
+    /// Used to mark the plugin's state as dirty when the plugin sets one of its persistent fields.
+    /// This is polled at the end of every process call.
+    pub field_change_tracker: FieldChangeTracker,
+
+    /// The plugin's factory presets. These are exposed to the host as a program list on the root
+    /// unit, and they can be selected using the parameter with the
+    /// [`VST3_PROGRAM_CHANGE_PARAM_ID`] ID.
+    pub factory_presets: Vec<FactoryPreset>,
+    /// The index of the factory preset that was last loaded through a program change.
+    pub current_factory_preset: AtomicUsize,
 }
 
 /// Tasks that can be sent from the plugin to be executed on the main thread in a non-blocking
@@ -144,6 +210,21 @@ pub enum Task {
     /// Request the editor to be resized according to its current size. Right now there is no way to
     /// handle denied resize requestsyet.
     RequestResize,
+    /// Tell the host that the plugin's state has changed and that the project should be saved.
+    MarkDirty,
+    /// Check whether the plugin's persistent fields have changed, and mark the plugin's state as
+    /// dirty if they have. See [`FieldChangeTracker`].
+    CheckFieldChanges,
+}
+
+/// A parameter change made by the plugin from the audio thread. See
+/// [`WrapperInner::output_param_changes`].
+#[derive(Debug, Clone, Copy)]
+pub struct OutputParamChange {
+    /// The parameter's hash, as used everywhere else.
+    pub param_hash: u32,
+    /// The parameter's new normalized value.
+    pub normalized_value: f32,
 }
 
 /// VST3 makes audio processing pretty complicated. In order to support both block splitting for
@@ -179,7 +260,11 @@ impl<P: Vst3Plugin> WrapperInner<P> {
     #[allow(unused_unsafe)]
     pub fn new() -> Arc<Self> {
         let plugin = P::default();
-        let editor = plugin.editor().map(Arc::from);
+        let background_thread = BackgroundThread::new_and_spawn(plugin.task_executor());
+        let editor = plugin
+            .editor(background_thread.async_executor())
+            .map(Arc::from);
+        let factory_presets = plugin.factory_presets();
 
         // This is used to allow the plugin to restore preset data from its editor, see the comment
         // on `Self::updated_state_sender`
@@ -232,6 +317,11 @@ impl<P: Vst3Plugin> WrapperInner<P> {
                         "Parameter '{}' collides with an automatically generated MIDI CC parameter, consider giving it a different ID", id
                     );
                 }
+                if *hash == VST3_PROGRAM_CHANGE_PARAM_ID {
+                    nih_debug_assert_failure!(
+                        "Parameter '{}' collides with the program change parameter, consider giving it a different ID", id
+                    );
+                }
             }
         }
 
@@ -243,12 +333,19 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             .iter()
             .map(|(_, hash, ptr, _)| (*hash, *ptr))
             .collect();
+        let param_groups = params.param_groups();
         let param_units = ParamUnits::from_param_groups(
             param_id_hashes_ptrs_groups
                 .iter()
                 .map(|(_, hash, _, group_name)| (*hash, group_name.as_str())),
+            &param_groups,
         )
-        .expect("Inconsistent parameter groups");
+        .unwrap_or_else(|err| panic!("Inconsistent parameter groups: {err}"));
+        let hidden_param_hashes = param_id_hashes_ptrs_groups
+            .iter()
+            .filter(|(_, _, _, group)| param_groups.iter().any(|g| g.hides(group)))
+            .map(|(_, hash, _, _)| *hash)
+            .collect();
         let param_id_to_hash = param_id_hashes_ptrs_groups
             .iter()
             .map(|(id, hash, _, _)| (id.clone(), *hash))
@@ -258,10 +355,13 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             .map(|(_, hash, ptr, _)| (ptr, hash))
             .collect();
 
+        let field_change_tracker = FieldChangeTracker::new(params.clone());
+
         let wrapper = Self {
             plugin: RwLock::new(plugin),
             params,
             editor,
+            background_thread,
 
             component_handler: AtomicRefCell::new(None),
 
@@ -277,6 +377,8 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             current_bus_config: AtomicCell::new(BusConfig {
                 num_input_channels: P::DEFAULT_NUM_INPUTS,
                 num_output_channels: P::DEFAULT_NUM_OUTPUTS,
+                input_layout: ChannelLayout::from_num_channels(P::DEFAULT_NUM_INPUTS),
+                output_layout: ChannelLayout::from_num_channels(P::DEFAULT_NUM_OUTPUTS),
                 aux_input_busses: P::DEFAULT_AUX_INPUTS.unwrap_or_default(),
                 aux_output_busses: P::DEFAULT_AUX_OUTPUTS.unwrap_or_default(),
             }),
@@ -285,18 +387,34 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             last_process_status: AtomicCell::new(ProcessStatus::Normal),
             current_latency: AtomicU32::new(0),
             output_buffer: AtomicRefCell::new(Buffer::default()),
+            aux_input_storage: AtomicRefCell::new(Vec::new()),
+            aux_input_buffers: AtomicRefCell::new(Vec::new()),
+            aux_output_storage: AtomicRefCell::new(Vec::new()),
+            aux_output_buffers: AtomicRefCell::new(Vec::new()),
             input_events: AtomicRefCell::new(VecDeque::with_capacity(1024)),
             output_events: AtomicRefCell::new(VecDeque::with_capacity(1024)),
+            sysex_storage: AtomicRefCell::new(SysExStorage::new()),
             note_expression_controller: AtomicRefCell::new(NoteExpressionController::default()),
+            mpe_state: AtomicRefCell::new(MpeState::default()),
             process_events: AtomicRefCell::new(Vec::with_capacity(4096)),
+            output_param_changes: ArrayQueue::new(OUTPUT_PARAM_CHANGES_CAPACITY),
+            param_changes: AtomicRefCell::new(ParamChangeQueues::new(
+                param_by_hash.values().copied(),
+            )),
             updated_state_sender,
             updated_state_receiver,
 
             param_hashes,
             param_by_hash,
             param_units,
+            hidden_param_hashes,
             param_id_to_hash,
             param_ptr_to_hash,
+            undo_history: UndoHistory::new(P::UNDO_HISTORY_SIZE),
+            field_change_tracker,
+
+            factory_presets,
+            current_factory_preset: AtomicUsize::new(0),
         };
 
         // FIXME: Right now this is safe, but if we are going to have a singleton main thread queue
@@ -318,6 +436,8 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             inner: self,
             input_events_guard: self.input_events.borrow_mut(),
             output_events_guard: self.output_events.borrow_mut(),
+            param_changes_guard: self.param_changes.borrow(),
+            sysex_storage_guard: self.sysex_storage.borrow_mut(),
             transport,
         }
     }
@@ -389,12 +509,47 @@ impl<P: Vst3Plugin> WrapperInner<P> {
         }
     }
 
+    /// Add a parameter change that occurs at `timing` to that parameter's queue in
+    /// `param_changes`. Used instead of
+    /// [`set_normalized_value_by_hash()`][Self::set_normalized_value_by_hash()] for changes after
+    /// the first sample of the buffer when `P::QUEUED_AUTOMATION` is enabled.
+    pub fn queue_param_change(&self, hash: u32, timing: u32, normalized_value: f32) -> tresult {
+        match self.param_by_hash.get(&hash) {
+            Some(param_ptr) => {
+                self.param_changes.borrow_mut().push(
+                    *param_ptr,
+                    ParamChange {
+                        timing,
+                        normalized_value,
+                    },
+                );
+
+                kResultOk
+            }
+            _ => kInvalidArgument,
+        }
+    }
+
+    /// Load one of the plugin's factory presets in response to a program change. Returns `false`
+    /// if the index is out of bounds.
+    pub fn load_factory_preset(&self, index: usize) -> bool {
+        match self.factory_presets.get(index) {
+            Some(preset) => {
+                self.current_factory_preset.store(index, Ordering::SeqCst);
+                self.set_state_object(preset.state.clone());
+
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Get the plugin's state object, may be called by the plugin's GUI as part of its own preset
     /// management. The wrapper doesn't use these functions and serializes and deserializes directly
-    /// the JSON in the relevant plugin API methods instead.
+    /// the state in the relevant plugin API methods instead.
     pub fn get_state_object(&self) -> PluginState {
         unsafe {
-            state::serialize_object(
+            state::serialize_object::<P>(
                 self.params.clone(),
                 state::make_params_iter(&self.param_by_hash, &self.param_id_to_hash),
             )
@@ -405,6 +560,8 @@ impl<P: Vst3Plugin> WrapperInner<P> {
     /// prevent corrupting data and changing parameters during processing the actual state is only
     /// updated at the end of the audio processing cycle.
     pub fn set_state_object(&self, mut state: PluginState) {
+        state::prepare_state_restore::<P>(&mut state, &self.undo_history);
+
         // Use a loop and timeouts to handle the super rare edge case when this function gets called
         // between a process call and the host disabling the plugin
         loop {
@@ -462,6 +619,8 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             }
         }
 
+        self.field_change_tracker.reset();
+
         // After the state has been updated, notify the host about the new parameter values
         let task_posted = unsafe { self.event_loop.borrow().assume_init_ref() }.do_maybe_async(
             Task::TriggerRestart(RestartFlags::kParamValuesChanged as i32),
@@ -491,6 +650,22 @@ impl<P: Vst3Plugin> MainThreadExecutor<Task> for WrapperInner<P> {
                 }
                 None => nih_debug_assert_failure!("Can't resize a closed editor"),
             },
+            Task::MarkDirty => match &*self.component_handler.borrow() {
+                Some(handler) => match handler.cast::<dyn IComponentHandler2>() {
+                    Some(handler) => {
+                        handler.set_dirty(1);
+                    }
+                    None => {
+                        nih_debug_assert_failure!("The host does not support IComponentHandler2")
+                    }
+                },
+                None => nih_debug_assert_failure!("Component handler not yet set"),
+            },
+            Task::CheckFieldChanges => {
+                if self.field_change_tracker.check() {
+                    self.execute(Task::MarkDirty);
+                }
+            }
         }
     }
 }
@@ -113,6 +113,7 @@ impl NoteExpressionController {
         match event.type_id {
             VOLUME_EXPRESSION_ID => Some(NoteEvent::PolyVolume {
                 timing,
+                voice_id: Some(event.note_id),
                 channel,
                 note,
                 // Because expression values in VST3 are always in the `[0, 1]` range, they added a
@@ -121,6 +122,7 @@ impl NoteExpressionController {
             }),
             PAN_EXPRESSION_ID => Some(NoteEvent::PolyPan {
                 timing,
+                voice_id: Some(event.note_id),
                 channel,
                 note,
                 // Our panning expressions are symmetrical around 0
@@ -128,6 +130,7 @@ impl NoteExpressionController {
             }),
             TUNING_EXPRESSION_ID => Some(NoteEvent::PolyTuning {
                 timing,
+                voice_id: Some(event.note_id),
                 channel,
                 note,
                 // This denormalized to the same [-120, 120] range used by CLAP and our expression
@@ -136,18 +139,21 @@ impl NoteExpressionController {
             }),
             VIBRATO_EXPRESSION_ID => Some(NoteEvent::PolyVibrato {
                 timing,
+                voice_id: Some(event.note_id),
                 channel,
                 note,
                 vibrato: event.value as f32,
             }),
             EXPRESSION_EXPRESSION_ID => Some(NoteEvent::PolyBrightness {
                 timing,
+                voice_id: Some(event.note_id),
                 channel,
                 note,
                 brightness: event.value as f32,
             }),
             BRIGHTNESS_EXPRESSION_ID => Some(NoteEvent::PolyExpression {
                 timing,
+                voice_id: Some(event.note_id),
                 channel,
                 note,
                 expression: event.value as f32,
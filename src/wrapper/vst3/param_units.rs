@@ -1,7 +1,8 @@
 //! Parameter hierarchies in VST3 requires you to define units, which are linearly indexed logical
 //! units that have a name, a parent, and then a whole bunch of other data like note numbers and
 //! MIDI program state. We'll need to implement some of that to conver our list of slash-separated
-//! parameter group paths to units.
+//! parameter group paths to units. The unit IDs are derived from the groups' stable IDs so they
+//! don't change when groups are added or renamed.
 //!
 //! <https://steinbergmedia.github.io/vst3_doc/vstinterfaces/classSteinberg_1_1Vst_1_1IUnitInfo.html>
 
@@ -9,6 +10,9 @@ use std::collections::{HashMap, HashSet};
 
 use vst3_sys::vst::kRootUnitId;
 
+use crate::param::internals::ParamGroup;
+use crate::wrapper::util::hash_param_id;
+
 /// Transforms a map containing parameter hashes and slash-separated paths to an array of VST3 units
 /// and a mapping for each parameter hash to a unit (or to `None` if they belong to the root unit).
 /// This is conceptually similar to a prefix tree/trie, but since we don't need any of the lookup
@@ -21,10 +25,8 @@ use vst3_sys::vst::kRootUnitId;
 pub struct ParamUnits {
     /// The unique units, with flat indices.
     units: Vec<ParamUnit>,
-    /// The index of the unit a parameter belongs to, or `None` if it belongs to the root unit.
-    ///
-    /// NOTE: The returned unit ID is actually one higher than this index because VST3 uses 0 as a
-    ///       no-unit value
+    /// The ID of the unit a parameter belongs to, or `kRootUnitId`/0 if it belongs to the root
+    /// unit.
     unit_id_by_hash: HashMap<u32, i32>,
 }
 
@@ -33,20 +35,23 @@ pub struct ParamUnits {
 /// <https://steinbergmedia.github.io/vst3_doc/vstinterfaces/structSteinberg_1_1Vst_1_1UnitInfo.html>
 #[derive(Debug)]
 pub struct ParamUnit {
+    /// The unit's ID. This is a hash of the group's stable ID, see [`ParamGroup::id`].
+    pub id: i32,
     /// The name of the unit, without any of the proceeding components.
     pub name: String,
-    /// The ID of the parent unit, or `kRootUnitId`/0 if the parent would be the root node. Because
-    /// 0 is reserved, these IDs are one higher than the actual index in `ParamUnits::units`.
+    /// The ID of the parent unit, or `kRootUnitId`/0 if the parent would be the root node.
     pub parent_id: i32,
 }
 
 impl ParamUnits {
     /// Construct a [`ParamUnits`] object from an iterator over pairs of `(param_hash, param_group)`
     /// where `param_hash` is the integer hash used to represent a parameter in the VST3 wrapper and
-    /// `param_group` is a slash delimited path.
+    /// `param_group` is a slash delimited path. The unit IDs are taken from the stable group IDs in
+    /// `param_groups`, falling back to the group's path for groups not contained in that list.
     ///
-    /// Returns an error if the iterator contains nested groups without a matching parent.
-    pub fn from_param_groups<'a, I>(groups: I) -> Result<Self, &'static str>
+    /// Returns an error if the iterator contains nested groups without a matching parent, or if
+    /// two groups end up with the same unit ID or with the root unit's ID.
+    pub fn from_param_groups<'a, I>(groups: I, param_groups: &[ParamGroup]) -> Result<Self, String>
     where
         I: Iterator<Item = (u32, &'a str)> + Clone,
     {
@@ -63,12 +68,22 @@ impl ParamUnits {
                 }
             })
             .collect();
+        let stable_ids_by_group_name: HashMap<&str, &str> = param_groups
+            .iter()
+            .map(|group| (group.path.as_str(), group.id.as_str()))
+            .collect();
         let mut groups_units: Vec<(&str, ParamUnit)> = unique_group_names
             .into_iter()
             .map(|group_name| {
                 (
                     group_name,
                     ParamUnit {
+                        id: hash_param_id(
+                            stable_ids_by_group_name
+                                .get(group_name)
+                                .copied()
+                                .unwrap_or(group_name),
+                        ) as i32,
                         name: match group_name.rfind('/') {
                             Some(sep_pos) => group_name[sep_pos + 1..].to_string(),
                             None => group_name.to_string(),
@@ -83,15 +98,27 @@ impl ParamUnits {
         // stable.
         groups_units.sort_by(|(group_name_l, _), (group_name_r, _)| group_name_l.cmp(group_name_r));
 
-        // We need to be able to map group names to unit IDs
-        // NOTE: Now it starts getting complicated because VST3 units are one indexed, so the unit
-        //       IDs are one higher than the index in our vector
+        // We need to be able to map group names to unit IDs. The hashes are always positive, but
+        // they may still collide with the root unit's ID or with eachother.
         let vst3_unit_id_by_group_name: HashMap<&str, i32> = groups_units
             .iter()
-            .enumerate()
-            // Note the +1 here
-            .map(|(unit_id, (group_name, _))| (*group_name, unit_id as i32 + 1))
+            .map(|(group_name, unit)| (*group_name, unit.id))
             .collect();
+        let mut group_name_by_unit_id: HashMap<i32, &str> = HashMap::new();
+        for (group_name, unit) in &groups_units {
+            if unit.id == kRootUnitId {
+                return Err(format!(
+                    "The unit ID for parameter group '{group_name}' collides with the root unit's \
+                     ID, use a different ID for the group"
+                ));
+            }
+            if let Some(other_group_name) = group_name_by_unit_id.insert(unit.id, group_name) {
+                return Err(format!(
+                    "Parameter groups '{other_group_name}' and '{group_name}' have colliding unit \
+                     IDs, use a different ID for one of the groups"
+                ));
+            }
+        }
 
         for (group_name, unit) in &mut groups_units {
             // If the group name does not contain any slashes then the unit's parent should stay at
@@ -100,7 +127,7 @@ impl ParamUnits {
                 let parent_group_name = &group_name[..sep_pos];
                 let parent_unit_id = *vst3_unit_id_by_group_name
                     .get(parent_group_name)
-                    .ok_or("Missing parent group")?;
+                    .ok_or_else(|| format!("Missing parent group '{parent_group_name}'"))?;
                 unit.parent_id = parent_unit_id;
             }
         }
@@ -132,8 +159,7 @@ impl ParamUnits {
     pub fn info(&self, index: usize) -> Option<(i32, &ParamUnit)> {
         let info = self.units.get(index)?;
 
-        // NOTE: The VST3 unit indices are off by one because 0 is reserved fro the root unit
-        Some((index as i32 + 1, info))
+        Some((info.id, info))
     }
 
     /// Get the ID of the unit the paramter belongs to or. `kRootUnitId`/0 indicates the root unit.
@@ -141,3 +167,59 @@ impl ParamUnits {
         self.unit_id_by_hash.get(&param_hash).copied()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stable_unit_ids() {
+        let param_groups = [
+            ParamGroup::new("Band 1").with_id("band_1"),
+            ParamGroup::new("Band 1/Filter").with_id("band_1/filter"),
+        ];
+        let units = ParamUnits::from_param_groups(
+            [(1, ""), (2, "Band 1"), (3, "Band 1/Filter"), (4, "Other")].into_iter(),
+            &param_groups,
+        )
+        .unwrap();
+
+        let band_id = hash_param_id("band_1") as i32;
+        let filter_id = hash_param_id("band_1/filter") as i32;
+        assert_eq!(units.len(), 3);
+        assert_eq!(units.get_vst3_unit_id(1), Some(kRootUnitId));
+        assert_eq!(units.get_vst3_unit_id(2), Some(band_id));
+        assert_eq!(units.get_vst3_unit_id(3), Some(filter_id));
+        assert_eq!(
+            units.get_vst3_unit_id(4),
+            Some(hash_param_id("Other") as i32)
+        );
+
+        let (_, filter_unit) = (0..units.len())
+            .filter_map(|index| units.info(index))
+            .find(|(unit_id, _)| *unit_id == filter_id)
+            .unwrap();
+        assert_eq!(filter_unit.name, "Filter");
+        assert_eq!(filter_unit.parent_id, band_id);
+    }
+
+    #[test]
+    fn colliding_unit_ids() {
+        let param_groups = [
+            ParamGroup::new("Band 1").with_id("band"),
+            ParamGroup::new("Band 2").with_id("band"),
+        ];
+        let err = ParamUnits::from_param_groups(
+            [(1, "Band 1"), (2, "Band 2")].into_iter(),
+            &param_groups,
+        )
+        .unwrap_err();
+        assert!(err.contains("'Band 1'"));
+        assert!(err.contains("'Band 2'"));
+    }
+
+    #[test]
+    fn missing_parent_group() {
+        assert!(ParamUnits::from_param_groups([(1, "Band 1/Filter")].into_iter(), &[]).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+//! Conversions between NIH-plug's [`ChannelLayout`]s and VST3 speaker arrangements. vst3-sys
+//! doesn't expose the individual speaker constants, so they're defined here.
+
+use vst3_sys::vst::SpeakerArrangement;
+
+use crate::plugin::ChannelLayout;
+
+/// `kSpeakerL`
+const SPEAKER_L: SpeakerArrangement = 1 << 0;
+/// `kSpeakerR`
+const SPEAKER_R: SpeakerArrangement = 1 << 1;
+/// `kSpeakerC`
+const SPEAKER_C: SpeakerArrangement = 1 << 2;
+/// `kSpeakerLfe`
+const SPEAKER_LFE: SpeakerArrangement = 1 << 3;
+/// `kSpeakerLs`
+const SPEAKER_LS: SpeakerArrangement = 1 << 4;
+/// `kSpeakerRs`
+const SPEAKER_RS: SpeakerArrangement = 1 << 5;
+/// `kSpeakerLc`
+const SPEAKER_LC: SpeakerArrangement = 1 << 6;
+/// `kSpeakerRc`
+const SPEAKER_RC: SpeakerArrangement = 1 << 7;
+/// `kSpeakerSl`
+const SPEAKER_SL: SpeakerArrangement = 1 << 9;
+/// `kSpeakerSr`
+const SPEAKER_SR: SpeakerArrangement = 1 << 10;
+/// `kSpeakerM`
+const SPEAKER_M: SpeakerArrangement = 1 << 19;
+/// `kSpeakerACN0` through `kSpeakerACN3`. The other ACN channels don't follow these.
+const SPEAKER_ACN0_3: SpeakerArrangement = 0b1111 << 20;
+/// `kSpeakerACN4` through `kSpeakerACN15`.
+const SPEAKER_ACN4_15: SpeakerArrangement = 0b1111_1111_1111 << 38;
+
+/// `kEmpty`
+const EMPTY: SpeakerArrangement = 0;
+/// `kMono`
+const MONO: SpeakerArrangement = SPEAKER_M;
+/// `kStereo`
+const STEREO: SpeakerArrangement = SPEAKER_L | SPEAKER_R;
+/// `k51`
+const SURROUND_5_1: SpeakerArrangement =
+    SPEAKER_L | SPEAKER_R | SPEAKER_C | SPEAKER_LFE | SPEAKER_LS | SPEAKER_RS;
+/// `k71Music`, this is the 7.1 layout we'll use for output.
+const SURROUND_7_1: SpeakerArrangement = SURROUND_5_1 | SPEAKER_SL | SPEAKER_SR;
+/// `k71Cine`, this is also accepted as 7.1 input.
+const SURROUND_7_1_CINE: SpeakerArrangement = SURROUND_5_1 | SPEAKER_LC | SPEAKER_RC;
+
+/// The highest ambisonic order VST3 can represent.
+const MAX_AMBISONIC_ORDER: u32 = 3;
+
+/// Convert a channel layout to a VST3 speaker arrangement. Discrete layouts and ambisonic layouts
+/// VST3 cannot represent use the first `n` speakers, so a discrete layout with two or six channels
+/// will look like stereo or 5.1 to the host.
+pub fn layout_to_arrangement(layout: ChannelLayout) -> SpeakerArrangement {
+    match layout {
+        ChannelLayout::Discrete(0) => EMPTY,
+        ChannelLayout::Mono => MONO,
+        ChannelLayout::Stereo => STEREO,
+        ChannelLayout::Surround5_1 => SURROUND_5_1,
+        ChannelLayout::Surround7_1 => SURROUND_7_1,
+        ChannelLayout::Ambisonic { order } if order <= MAX_AMBISONIC_ORDER => {
+            ambisonic_arrangement(order)
+        }
+        layout => {
+            let num_channels = layout.num_channels();
+            nih_debug_assert!(
+                num_channels <= SpeakerArrangement::BITS,
+                "VST3 can't represent {} channels",
+                num_channels
+            );
+
+            SpeakerArrangement::MAX >> (SpeakerArrangement::BITS - num_channels.min(64))
+        }
+    }
+}
+
+/// Convert a VST3 speaker arrangement to a channel layout. Arrangements that don't correspond to
+/// one of the known layouts become [`ChannelLayout::Discrete`].
+pub fn arrangement_to_layout(arrangement: SpeakerArrangement) -> ChannelLayout {
+    match arrangement {
+        MONO => ChannelLayout::Mono,
+        STEREO => ChannelLayout::Stereo,
+        SURROUND_5_1 => ChannelLayout::Surround5_1,
+        SURROUND_7_1 | SURROUND_7_1_CINE => ChannelLayout::Surround7_1,
+        arrangement => (1..=MAX_AMBISONIC_ORDER)
+            .find(|&order| arrangement == ambisonic_arrangement(order))
+            .map(|order| ChannelLayout::Ambisonic { order })
+            .unwrap_or(ChannelLayout::Discrete(arrangement.count_ones())),
+    }
+}
+
+/// The ACN speakers used for an ambisonic layout of the given order. The order should not exceed
+/// [`MAX_AMBISONIC_ORDER`].
+fn ambisonic_arrangement(order: u32) -> SpeakerArrangement {
+    let num_channels = (order + 1) * (order + 1);
+    let low_channels = num_channels.min(4);
+    let high_channels = num_channels - low_channels;
+
+    let low = SPEAKER_ACN0_3 & (((1 << low_channels) - 1) << 20);
+    let high = SPEAKER_ACN4_15 & (((1 << high_channels) - 1) << 38);
+
+    low | high
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_layouts_roundtrip() {
+        for layout in [
+            ChannelLayout::Mono,
+            ChannelLayout::Stereo,
+            ChannelLayout::Surround5_1,
+            ChannelLayout::Surround7_1,
+            ChannelLayout::Ambisonic { order: 1 },
+            ChannelLayout::Ambisonic { order: 2 },
+            ChannelLayout::Ambisonic { order: 3 },
+            ChannelLayout::Discrete(0),
+            ChannelLayout::Discrete(3),
+        ] {
+            let arrangement = layout_to_arrangement(layout);
+            assert_eq!(arrangement.count_ones(), layout.num_channels());
+            assert_eq!(arrangement_to_layout(arrangement), layout);
+        }
+    }
+
+    #[test]
+    fn unknown_arrangement_is_discrete() {
+        // `k50`, without the LFE channel
+        let arrangement = SPEAKER_L | SPEAKER_R | SPEAKER_C | SPEAKER_LS | SPEAKER_RS;
+        assert_eq!(
+            arrangement_to_layout(arrangement),
+            ChannelLayout::Discrete(5)
+        );
+    }
+}
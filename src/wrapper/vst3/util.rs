@@ -19,6 +19,14 @@ pub const VST3_MIDI_PARAMS_START: u32 = VST3_MIDI_PARAMS_END - VST3_MIDI_NUM_PAR
 /// The (exlucive) end of the MIDI CC parameter range. Anything above this is reserved by the host.
 pub const VST3_MIDI_PARAMS_END: u32 = (1 << 31) + 1;
 
+/// If the plugin has factory presets, then we'll register an additional hidden parameter with this
+/// ID that the host can use to switch between them. This comes right before the MIDI CC parameter
+/// range.
+pub const VST3_PROGRAM_CHANGE_PARAM_ID: u32 = VST3_MIDI_PARAMS_START - 1;
+/// The ID of the program list containing the plugin's factory presets. This program list is
+/// attached to the root unit.
+pub const VST3_FACTORY_PRESETS_PROGRAM_LIST_ID: i32 = 0;
+
 /// Early exit out of a VST3 function when one of the passed pointers is null
 macro_rules! check_null_ptr {
     ($ptr:expr $(, $ptrs:expr)* $(, )?) => {
@@ -314,10 +314,14 @@ impl<P: Vst3Plugin> IPlugView for WrapperView<P> {
                 }
             };
 
-            *editor_handle = Some(self.editor.spawn(
+            *editor_handle = self.editor.spawn(
                 ParentWindowHandle { handle },
                 self.inner.clone().make_gui_context(),
-            ));
+            );
+            if editor_handle.is_none() {
+                nih_debug_assert_failure!("The editor could not be opened");
+                return kResultFalse;
+            }
             *self.inner.plug_view.write() = Some(ObjectPtr::from(self));
 
             kResultOk
@@ -390,7 +394,6 @@ impl<P: Vst3Plugin> IPlugView for WrapperView<P> {
     unsafe fn on_size(&self, new_size: *mut ViewRect) -> tresult {
         check_null_ptr!(new_size);
 
-        // TODO: Implement Host->Plugin resizing
         let (unscaled_width, unscaled_height) = self.editor.size();
         let scaling_factor = self.scaling_factor.load(Ordering::Relaxed);
         let (editor_width, editor_height) = (
@@ -402,6 +405,13 @@ impl<P: Vst3Plugin> IPlugView for WrapperView<P> {
         let height = (*new_size).bottom - (*new_size).top;
         if width == editor_width && height == editor_height {
             kResultOk
+        } else if self.editor.can_resize()
+            && self.editor.set_size(
+                (width as f32 / scaling_factor).round() as u32,
+                (height as f32 / scaling_factor).round() as u32,
+            )
+        {
+            kResultOk
         } else {
             kResultFalse
         }
@@ -439,19 +449,35 @@ impl<P: Vst3Plugin> IPlugView for WrapperView<P> {
     }
 
     unsafe fn can_resize(&self) -> tresult {
-        // TODO: Implement Host->Plugin resizing
-        kResultFalse
+        if self.editor.can_resize() {
+            kResultOk
+        } else {
+            kResultFalse
+        }
     }
 
     unsafe fn check_size_constraint(&self, rect: *mut ViewRect) -> tresult {
         check_null_ptr!(rect);
 
-        // TODO: Implement Host->Plugin resizing
-        if (*rect).right - (*rect).left > 0 && (*rect).bottom - (*rect).top > 0 {
-            kResultOk
-        } else {
-            kResultFalse
+        let rect = &mut *rect;
+        let width = rect.right - rect.left;
+        let height = rect.bottom - rect.top;
+        if width <= 0 || height <= 0 {
+            return kResultFalse;
+        }
+
+        if self.editor.can_resize() {
+            // The host's sizes are in physical pixels, while the resize hints are in logical pixels
+            let scaling_factor = self.scaling_factor.load(Ordering::Relaxed);
+            let (constrained_width, constrained_height) = self.editor.resize_hints().constrain(
+                (width as f32 / scaling_factor).round() as u32,
+                (height as f32 / scaling_factor).round() as u32,
+            );
+            rect.right = rect.left + (constrained_width as f32 * scaling_factor).round() as i32;
+            rect.bottom = rect.top + (constrained_height as f32 * scaling_factor).round() as i32;
         }
+
+        kResultOk
     }
 }
 
@@ -1,6 +1,7 @@
 use std::cmp;
-use std::ffi::c_void;
+use std::ffi::{c_void, CStr};
 use std::mem::{self, MaybeUninit};
+use std::os::raw::c_char;
 use std::ptr;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
@@ -9,30 +10,34 @@ use vst3_sys::base::{kInvalidArgument, kNoInterface, kResultFalse, kResultOk, tr
 use vst3_sys::base::{IBStream, IPluginBase};
 use vst3_sys::utils::SharedVstPtr;
 use vst3_sys::vst::{
-    kNoParamId, kNoParentUnitId, kNoProgramListId, kRootUnitId, Event, EventTypes, IAudioProcessor,
-    IComponent, IEditController, IEventList, IMidiMapping, INoteExpressionController,
-    IParamValueQueue, IParameterChanges, IProcessContextRequirements, IUnitInfo,
-    LegacyMidiCCOutEvent, NoteExpressionTypeInfo, NoteExpressionValueDescription, NoteOffEvent,
-    NoteOnEvent, ParameterFlags, PolyPressureEvent, ProgramListInfo, TChar, UnitInfo,
+    kNoParamId, kNoParentUnitId, kNoProgramListId, kRootUnitId, DataEvent, Event, EventTypes,
+    IAudioProcessor, IComponent, IEditController, IEventList, IMidiMapping,
+    INoteExpressionController, IParamValueQueue, IParameterChanges, IProcessContextRequirements,
+    IUnitInfo, LegacyMidiCCOutEvent, NoteExpressionTypeInfo, NoteExpressionValueDescription,
+    NoteOffEvent, NoteOnEvent, ParameterFlags, PolyPressureEvent, ProgramListInfo, TChar, UnitInfo,
 };
 use vst3_sys::VST3;
 use widestring::U16CStr;
 
 use super::inner::WrapperInner;
+use super::speaker_arrangements::{arrangement_to_layout, layout_to_arrangement};
 use super::util::{
-    u16strlcpy, VstPtr, VST3_MIDI_CCS, VST3_MIDI_NUM_PARAMS, VST3_MIDI_PARAMS_START,
+    u16strlcpy, VstPtr, VST3_FACTORY_PRESETS_PROGRAM_LIST_ID, VST3_MIDI_CCS, VST3_MIDI_NUM_PARAMS,
+    VST3_MIDI_PARAMS_START, VST3_PROGRAM_CHANGE_PARAM_ID,
 };
 use super::view::WrapperView;
+use crate::buffer::{AuxiliaryBuffers, Buffer};
 use crate::context::Transport;
 use crate::midi::{MidiConfig, NoteEvent};
 use crate::param::ParamFlags;
 use crate::plugin::{
-    AuxiliaryIOConfig, BufferConfig, BusConfig, ProcessMode, ProcessStatus, Vst3Plugin,
+    AuxiliaryIOConfig, BufferConfig, BusConfig, ChannelLayout, FactoryPreset, ProcessMode,
+    ProcessStatus, Vst3Plugin,
 };
 use crate::util::permit_alloc;
 use crate::wrapper::state;
-use crate::wrapper::util::process_wrapper;
-use crate::wrapper::vst3::inner::ProcessEvent;
+use crate::wrapper::util::{process_wrapper, voice_id_from_note_id};
+use crate::wrapper::vst3::inner::{ProcessEvent, Task};
 use crate::wrapper::vst3::note_expressions::{self, NoteExpressionController};
 use crate::wrapper::vst3::util::{VST3_MIDI_CHANNELS, VST3_MIDI_PARAMS_END};
 
@@ -56,6 +61,44 @@ impl<P: Vst3Plugin> Wrapper<P> {
     pub fn new() -> Box<Self> {
         Self::allocate(WrapperInner::new())
     }
+
+    /// Convert a normalized value for the program change parameter to an index into the factory
+    /// presets list.
+    fn factory_preset_normalized_to_index(&self, value_normalized: f64) -> usize {
+        let num_presets = self.inner.factory_presets.len();
+        if num_presets <= 1 {
+            return 0;
+        }
+
+        let step_count = (num_presets - 1) as f64;
+        (value_normalized.clamp(0.0, 1.0) * step_count).round() as usize
+    }
+
+    /// The inverse of [`factory_preset_normalized_to_index()`][Self::factory_preset_normalized_to_index()].
+    fn factory_preset_index_to_normalized(&self, index: usize) -> f64 {
+        let num_presets = self.inner.factory_presets.len();
+        if num_presets <= 1 {
+            return 0.0;
+        }
+
+        index.min(num_presets - 1) as f64 / (num_presets - 1) as f64
+    }
+
+    fn factory_preset_by_normalized(&self, value_normalized: f64) -> Option<&FactoryPreset> {
+        self.inner
+            .factory_presets
+            .get(self.factory_preset_normalized_to_index(value_normalized))
+    }
+
+    /// Get a factory preset from the factory presets program list, if `list_id` refers to that
+    /// list and the index is in range.
+    fn factory_preset_by_index(&self, list_id: i32, program_index: i32) -> Option<&FactoryPreset> {
+        if list_id != VST3_FACTORY_PRESETS_PROGRAM_LIST_ID || program_index < 0 {
+            return None;
+        }
+
+        self.inner.factory_presets.get(program_index as usize)
+    }
 }
 
 impl<P: Vst3Plugin> IPluginBase for Wrapper<P> {
@@ -342,6 +385,13 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
                     param.update_smoother(buffer_config.sample_rate, true);
                 }
 
+                if P::QUEUED_AUTOMATION {
+                    self.inner
+                        .param_changes
+                        .borrow_mut()
+                        .reset(buffer_config.max_buffer_size);
+                }
+
                 let bus_config = self.inner.current_bus_config.load();
                 let mut plugin = self.inner.plugin.write();
                 if plugin.initialize(
@@ -365,7 +415,52 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
                                 .resize_with(bus_config.num_output_channels as usize, || &mut [])
                         });
 
-                    // TODO: Initialize auxiliary IO
+                    // The sidechain inputs are copied to owned buffers before being passed to the
+                    // plugin, and auxiliary outputs without host buffers are written to scratch
+                    // buffers
+                    let max_buffer_size = buffer_config.max_buffer_size as usize;
+                    *self.inner.aux_input_storage.borrow_mut() =
+                        vec![
+                            vec![
+                                vec![0.0; max_buffer_size];
+                                bus_config.aux_input_busses.num_channels as usize
+                            ];
+                            bus_config.aux_input_busses.num_busses as usize
+                        ];
+                    *self.inner.aux_output_storage.borrow_mut() =
+                        vec![
+                            vec![
+                                vec![0.0; max_buffer_size];
+                                bus_config.aux_output_busses.num_channels as usize
+                            ];
+                            bus_config.aux_output_busses.num_busses as usize
+                        ];
+
+                    // The slices in these buffers are updated during every process call
+                    let mut aux_input_buffers = self.inner.aux_input_buffers.borrow_mut();
+                    aux_input_buffers.clear();
+                    for _ in 0..bus_config.aux_input_busses.num_busses {
+                        let mut buffer = Buffer::default();
+                        buffer.with_raw_vec(|channel_slices| {
+                            channel_slices.resize_with(
+                                bus_config.aux_input_busses.num_channels as usize,
+                                || &mut [],
+                            )
+                        });
+                        aux_input_buffers.push(buffer);
+                    }
+                    let mut aux_output_buffers = self.inner.aux_output_buffers.borrow_mut();
+                    aux_output_buffers.clear();
+                    for _ in 0..bus_config.aux_output_busses.num_busses {
+                        let mut buffer = Buffer::default();
+                        buffer.with_raw_vec(|channel_slices| {
+                            channel_slices.resize_with(
+                                bus_config.aux_output_busses.num_channels as usize,
+                                || &mut [],
+                            )
+                        });
+                        aux_output_buffers.push(buffer);
+                    }
 
                     kResultOk
                 } else {
@@ -417,16 +512,19 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
             return kResultFalse;
         }
 
-        let success = state::deserialize_json(
+        let success = state::deserialize_bytes::<P>(
             &read_buffer,
             self.inner.params.clone(),
             state::make_params_getter(&self.inner.param_by_hash, &self.inner.param_id_to_hash),
             self.inner.current_buffer_config.load().as_ref(),
+            &self.inner.undo_history,
         );
         if !success {
             return kResultFalse;
         }
 
+        self.inner.field_change_tracker.reset();
+
         // Reinitialize the plugin after loading state so it can respond to the new parameter values
         self.inner.notify_param_values_changed();
 
@@ -454,7 +552,7 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
 
         let state = state.upgrade().unwrap();
 
-        let serialized = state::serialize_json(
+        let serialized = state::serialize_bytes::<P>(
             self.inner.params.clone(),
             state::make_params_iter(&self.inner.param_by_hash, &self.inner.param_id_to_hash),
         );
@@ -498,11 +596,17 @@ impl<P: Vst3Plugin> IEditController for Wrapper<P> {
     }
 
     unsafe fn get_parameter_count(&self) -> i32 {
+        // Factory presets are selected through an additional program change parameter
+        let mut num_params = self.inner.param_hashes.len() as i32;
+        if !self.inner.factory_presets.is_empty() {
+            num_params += 1;
+        }
+
         // We need to add a whole bunch of parameters if the plugin accepts MIDI CCs
         if P::MIDI_INPUT >= MidiConfig::MidiCCs {
-            self.inner.param_hashes.len() as i32 + VST3_MIDI_NUM_PARAMS as i32
+            num_params + VST3_MIDI_NUM_PARAMS as i32
         } else {
-            self.inner.param_hashes.len() as i32
+            num_params
         }
     }
 
@@ -520,11 +624,22 @@ impl<P: Vst3Plugin> IEditController for Wrapper<P> {
         *info = std::mem::zeroed();
         let info = &mut *info;
 
-        // If the parameter is a generated MIDI CC/channel pressure/pitch bend then it needs to be
-        // handled separately
+        // If the parameter is the program change parameter or a generated MIDI CC/channel
+        // pressure/pitch bend then it needs to be handled separately. The program change parameter
+        // comes right after the plugin's own parameters.
         let num_actual_params = self.inner.param_hashes.len() as i32;
-        if P::MIDI_INPUT >= MidiConfig::MidiCCs && param_index >= num_actual_params {
-            let midi_param_relative_idx = (param_index - num_actual_params) as u32;
+        let num_factory_presets = self.inner.factory_presets.len() as i32;
+        let num_generated_params = num_actual_params + (num_factory_presets > 0) as i32;
+        if num_factory_presets > 0 && param_index == num_actual_params {
+            info.id = VST3_PROGRAM_CHANGE_PARAM_ID;
+            u16strlcpy(&mut info.title, "Program");
+            u16strlcpy(&mut info.short_title, "Program");
+            info.step_count = num_factory_presets - 1;
+            info.default_normalized_value = 0.0;
+            info.unit_id = kRootUnitId;
+            info.flags = ParameterFlags::kIsProgramChange as i32 | ParameterFlags::kIsList as i32;
+        } else if P::MIDI_INPUT >= MidiConfig::MidiCCs && param_index >= num_generated_params {
+            let midi_param_relative_idx = (param_index - num_generated_params) as u32;
             // This goes up to 130 for the 128 CCs followed by channel pressure and pitch bend
             let midi_cc = midi_param_relative_idx % VST3_MIDI_CCS;
             let midi_channel = midi_param_relative_idx / VST3_MIDI_CCS;
@@ -551,7 +666,8 @@ impl<P: Vst3Plugin> IEditController for Wrapper<P> {
             let default_value = param_ptr.default_normalized_value();
             let flags = param_ptr.flags();
             let automatable = !flags.contains(ParamFlags::NON_AUTOMATABLE);
-            let hidden = flags.contains(ParamFlags::HIDDEN);
+            let hidden = flags.contains(ParamFlags::HIDDEN)
+                || self.inner.hidden_param_hashes.contains(param_hash);
             let is_bypass = flags.contains(ParamFlags::BYPASS);
 
             info.id = *param_hash;
@@ -586,6 +702,16 @@ impl<P: Vst3Plugin> IEditController for Wrapper<P> {
 
         let dest = &mut *(string as *mut [TChar; 128]);
 
+        if id == VST3_PROGRAM_CHANGE_PARAM_ID {
+            return match self.factory_preset_by_normalized(value_normalized) {
+                Some(preset) => {
+                    u16strlcpy(dest, &preset.name);
+                    kResultOk
+                }
+                None => kInvalidArgument,
+            };
+        }
+
         // TODO: We don't implement these methods at all for our generated MIDI CC parameters,
         //       should be fine right? They should be hidden anyways.
         match self.inner.param_by_hash.get(&id) {
@@ -614,6 +740,21 @@ impl<P: Vst3Plugin> IEditController for Wrapper<P> {
             Err(_) => return kInvalidArgument,
         };
 
+        if id == VST3_PROGRAM_CHANGE_PARAM_ID {
+            return match self
+                .inner
+                .factory_presets
+                .iter()
+                .position(|preset| preset.name == string)
+            {
+                Some(index) => {
+                    *value_normalized = self.factory_preset_index_to_normalized(index);
+                    kResultOk
+                }
+                None => kResultFalse,
+            };
+        }
+
         match self.inner.param_by_hash.get(&id) {
             Some(param_ptr) => {
                 let value = match param_ptr.string_to_normalized_value(&string) {
@@ -629,6 +770,10 @@ impl<P: Vst3Plugin> IEditController for Wrapper<P> {
     }
 
     unsafe fn normalized_param_to_plain(&self, id: u32, value_normalized: f64) -> f64 {
+        if id == VST3_PROGRAM_CHANGE_PARAM_ID {
+            return self.factory_preset_normalized_to_index(value_normalized) as f64;
+        }
+
         match self.inner.param_by_hash.get(&id) {
             Some(param_ptr) => param_ptr.preview_plain(value_normalized as f32) as f64,
             _ => value_normalized,
@@ -636,6 +781,10 @@ impl<P: Vst3Plugin> IEditController for Wrapper<P> {
     }
 
     unsafe fn plain_param_to_normalized(&self, id: u32, plain_value: f64) -> f64 {
+        if id == VST3_PROGRAM_CHANGE_PARAM_ID {
+            return self.factory_preset_index_to_normalized(plain_value.max(0.0).round() as usize);
+        }
+
         match self.inner.param_by_hash.get(&id) {
             Some(param_ptr) => param_ptr.preview_normalized(plain_value as f32) as f64,
             _ => plain_value,
@@ -643,6 +792,12 @@ impl<P: Vst3Plugin> IEditController for Wrapper<P> {
     }
 
     unsafe fn get_param_normalized(&self, id: u32) -> f64 {
+        if id == VST3_PROGRAM_CHANGE_PARAM_ID {
+            return self.factory_preset_index_to_normalized(
+                self.inner.current_factory_preset.load(Ordering::SeqCst),
+            );
+        }
+
         match self.inner.param_by_hash.get(&id) {
             Some(param_ptr) => param_ptr.normalized_value() as f64,
             _ => 0.5,
@@ -650,6 +805,17 @@ impl<P: Vst3Plugin> IEditController for Wrapper<P> {
     }
 
     unsafe fn set_param_normalized(&self, id: u32, value: f64) -> tresult {
+        // Program changes are never handled in the process function since loading a preset
+        // involves allocations and reinitializing the plugin
+        if id == VST3_PROGRAM_CHANGE_PARAM_ID {
+            let index = self.factory_preset_normalized_to_index(value);
+            return if self.inner.load_factory_preset(index) {
+                kResultOk
+            } else {
+                kInvalidArgument
+            };
+        }
+
         // If the plugin is currently processing audio, then this parameter change will also be sent
         // to the process function
         if self.inner.is_processing.load(Ordering::SeqCst) {
@@ -709,10 +875,10 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
         // account when asserting this. If that's the case, then the first bus for that direction
         // will have been marked auxiliary.
         let aux_inputs_only = P::DEFAULT_NUM_INPUTS == 0 && P::DEFAULT_AUX_INPUTS.is_some();
-        let num_input_channels = if aux_inputs_only || num_ins < 1 {
-            0
+        let input_layout = if aux_inputs_only || num_ins < 1 {
+            ChannelLayout::Discrete(0)
         } else {
-            (*inputs).count_ones()
+            arrangement_to_layout(*inputs)
         };
 
         let aux_input_start_idx = if aux_inputs_only { 0 } else { 1 };
@@ -732,14 +898,14 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
         }
 
         let aux_outputs_only = P::DEFAULT_NUM_OUTPUTS == 0 && P::DEFAULT_AUX_OUTPUTS.is_some();
-        let num_output_channels = if aux_outputs_only || num_ins < 1 {
-            0
+        let output_layout = if aux_outputs_only || num_outs < 1 {
+            ChannelLayout::Discrete(0)
         } else {
-            (*outputs).count_ones()
+            arrangement_to_layout(*outputs)
         };
 
         let aux_output_start_idx = if aux_outputs_only { 0 } else { 1 };
-        let num_aux_output_busses = (num_ins as u32).saturating_sub(aux_output_start_idx);
+        let num_aux_output_busses = (num_outs as u32).saturating_sub(aux_output_start_idx);
         let num_aux_output_channels = if num_aux_output_busses == 0 {
             0
         } else {
@@ -755,8 +921,10 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
         }
 
         let proposed_config = BusConfig {
-            num_input_channels,
-            num_output_channels,
+            num_input_channels: input_layout.num_channels(),
+            num_output_channels: output_layout.num_channels(),
+            input_layout,
+            output_layout,
             aux_input_busses: AuxiliaryIOConfig {
                 num_busses: num_aux_input_busses,
                 num_channels: num_aux_input_channels,
@@ -788,34 +956,19 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
     ) -> tresult {
         check_null_ptr!(arr);
 
-        let channel_count_to_map = |count| match count {
-            0 => vst3_sys::vst::kEmpty,
-            1 => vst3_sys::vst::kMono,
-            2 => vst3_sys::vst::kStereo,
-            5 => vst3_sys::vst::k50,
-            6 => vst3_sys::vst::k51,
-            7 => vst3_sys::vst::k70Cine,
-            8 => vst3_sys::vst::k71Cine,
-            n => {
-                nih_debug_assert_failure!(
-                    "No defined layout for {} channels, making something up on the spot...",
-                    n
-                );
-                (1 << n) - 1
-            }
-        };
-
         let bus_config = self.inner.current_bus_config.load();
-        let num_channels = if dir == vst3_sys::vst::BusDirections::kInput as i32 {
+        // Auxiliary busses don't have a layout, so we'll use the default layout for their channel
+        // count
+        let layout = if dir == vst3_sys::vst::BusDirections::kInput as i32 {
             let aux_inputs_only = P::DEFAULT_NUM_INPUTS == 0 && P::DEFAULT_AUX_INPUTS.is_some();
             let aux_input_start_idx = if aux_inputs_only { 0 } else { 1 };
             if !aux_inputs_only && index == 0 {
-                bus_config.num_input_channels
+                bus_config.input_layout
             } else if (aux_input_start_idx
                 ..(aux_input_start_idx + bus_config.aux_input_busses.num_busses as i32))
                 .contains(&index)
             {
-                bus_config.aux_input_busses.num_channels
+                ChannelLayout::from_num_channels(bus_config.aux_input_busses.num_channels)
             } else {
                 return kInvalidArgument;
             }
@@ -823,21 +976,21 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
             let aux_outputs_only = P::DEFAULT_NUM_OUTPUTS == 0 && P::DEFAULT_AUX_OUTPUTS.is_some();
             let aux_output_start_idx = if aux_outputs_only { 0 } else { 1 };
             if !aux_outputs_only && index == 0 {
-                bus_config.num_output_channels
+                bus_config.output_layout
             } else if (aux_output_start_idx
                 ..(aux_output_start_idx + bus_config.aux_output_busses.num_busses as i32))
                 .contains(&index)
             {
-                bus_config.aux_output_busses.num_channels
+                ChannelLayout::from_num_channels(bus_config.aux_output_busses.num_channels)
             } else {
                 return kInvalidArgument;
             }
         } else {
             return kInvalidArgument;
         };
-        let channel_map = channel_count_to_map(num_channels);
+        let channel_map = layout_to_arrangement(layout);
 
-        nih_debug_assert_eq!(num_channels, channel_map.count_ones());
+        nih_debug_assert_eq!(layout.num_channels(), channel_map.count_ones());
         *arr = channel_map;
 
         kResultOk
@@ -901,6 +1054,7 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
         // thing. We don't call `reset()` in `setup_processing()` for that same reason.
         if state {
             process_wrapper(|| self.inner.plugin.write().reset());
+            self.inner.mpe_state.borrow_mut().reset();
         }
 
         // We don't have any special handling for suspending and resuming plugins, yet
@@ -912,13 +1066,14 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
     unsafe fn process(&self, data: *mut vst3_sys::vst::ProcessData) -> tresult {
         check_null_ptr!(data);
 
-        // TODO: Handle auxiliary IO, this still assumes main IO is always present and there's no auxiliary IO
-
         // Panic on allocations if the `assert_process_allocs` feature has been enabled, and make
         // sure that FTZ is set up correctly
         process_wrapper(|| {
             // We need to handle incoming automation first
             let data = &*data;
+
+            // The SysEx messages from the previous cycle are no longer referenced by the host
+            self.inner.sysex_storage.borrow_mut().clear();
             let sample_rate = self
                 .inner
                 .current_buffer_config
@@ -926,19 +1081,21 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 .expect("Process call without prior setup call")
                 .sample_rate;
 
-            // The setups we suppport are:
-            // - 1 input bus
-            // - 1 output bus
-            // - 1 input bus and 1 output bus
-            //
-            // Depending on the host either of these may also be missing if the number of channels
-            // is set to 0.
+            // The main input and output busses always come first if the plugin has them, followed
+            // by the auxiliary busses. The host may also leave out busses entirely.
+            let bus_config = self.inner.current_bus_config.load();
+            let has_main_input = P::DEFAULT_NUM_INPUTS > 0;
+            let has_main_output = P::DEFAULT_NUM_OUTPUTS > 0;
+            let aux_input_start_idx = if has_main_input { 1 } else { 0 };
+            let aux_output_start_idx = if has_main_output { 1 } else { 0 };
             nih_debug_assert!(
                 data.num_inputs >= 0
-                    && data.num_inputs <= 1
+                    && data.num_inputs as u32
+                        <= aux_input_start_idx + bus_config.aux_input_busses.num_busses
                     && data.num_outputs >= 0
-                    && data.num_outputs <= 1,
-                "The host provides more than one input or output bus"
+                    && data.num_outputs as u32
+                        <= aux_output_start_idx + bus_config.aux_output_busses.num_busses,
+                "The host provides more input or output busses than expected"
             );
             nih_debug_assert_eq!(
                 data.symbolic_sample_size,
@@ -966,7 +1123,8 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                     {
                         let param_hash = param_change_queue.get_parameter_id();
                         let num_changes = param_change_queue.get_point_count();
-                        if num_changes <= 0 {
+                        // Program changes are handled in `set_param_normalized()`
+                        if num_changes <= 0 || param_hash == VST3_PROGRAM_CHANGE_PARAM_ID {
                             continue;
                         }
 
@@ -1017,7 +1175,9 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                             },
                                         },
                                     });
-                                } else if P::SAMPLE_ACCURATE_AUTOMATION {
+                                } else if P::QUEUED_AUTOMATION && timing > 0 {
+                                    self.inner.queue_param_change(param_hash, timing, value);
+                                } else if P::SAMPLE_ACCURATE_AUTOMATION && !P::QUEUED_AUTOMATION {
                                     process_events.push(ProcessEvent::ParameterChange {
                                         timing,
                                         hash: param_hash,
@@ -1062,6 +1222,7 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                 timing,
                                 event: NoteEvent::NoteOn {
                                     timing,
+                                    voice_id: voice_id_from_note_id(event.note_id),
                                     channel: event.channel as u8,
                                     note: event.pitch as u8,
                                     velocity: event.velocity,
@@ -1073,6 +1234,7 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                 timing,
                                 event: NoteEvent::NoteOff {
                                     timing,
+                                    voice_id: voice_id_from_note_id(event.note_id),
                                     channel: event.channel as u8,
                                     note: event.pitch as u8,
                                     velocity: event.velocity,
@@ -1084,6 +1246,7 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                 timing,
                                 event: NoteEvent::PolyPressure {
                                     timing,
+                                    voice_id: voice_id_from_note_id(event.note_id),
                                     channel: event.channel as u8,
                                     note: event.pitch as u8,
                                     pressure: event.pressure,
@@ -1103,6 +1266,26 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                     event.type_id
                                 ),
                             }
+                        } else if event.type_ == EventTypes::kDataEvent as u16
+                            && P::MIDI_INPUT >= MidiConfig::MidiCCs
+                        {
+                            let event = event.event.data;
+                            // `kMidiSysEx`, this constant is missing from vst3-sys
+                            if event.type_ == 0 && !event.bytes.is_null() {
+                                let data =
+                                    std::slice::from_raw_parts(event.bytes, event.size as usize);
+                                let message = self.inner.sysex_storage.borrow_mut().push(data);
+                                match message {
+                                    Some(message) => process_events.push(ProcessEvent::NoteEvent {
+                                        timing,
+                                        event: NoteEvent::MidiSysEx { timing, message },
+                                    }),
+                                    None => nih_debug_assert_failure!(
+                                        "The SysEx storage is full, dropping a {} byte message",
+                                        data.len()
+                                    ),
+                                }
+                            }
                         }
                     }
                 }
@@ -1132,6 +1315,7 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 {
                     let mut input_events = self.inner.input_events.borrow_mut();
                     input_events.clear();
+                    let mut mpe_state = self.inner.mpe_state.borrow_mut();
 
                     block_end = data.num_samples as usize;
                     for event_idx in event_start_idx..process_events.len() {
@@ -1164,7 +1348,13 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                 // We need to make sure to compensate the event for any block splitting,
                                 // since we had to create the event object beforehand
                                 event.subtract_timing(block_start as u32);
-                                input_events.push_back(event);
+                                if P::MPE_INPUT && P::MIDI_INPUT >= MidiConfig::MidiCCs {
+                                    mpe_state.process_event(event, |event| {
+                                        input_events.push_back(event)
+                                    });
+                                } else {
+                                    input_events.push_back(event);
+                                }
                             }
                         }
                     }
@@ -1186,7 +1376,7 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                     // Buffers for zero-channel plugins like note effects should always be allowed
                     buffer_is_valid = output_slices.is_empty();
 
-                    if !data.outputs.is_null() {
+                    if has_main_output && data.num_outputs > 0 && !data.outputs.is_null() {
                         let num_output_channels = (*data.outputs).num_channels as usize;
                         buffer_is_valid = num_output_channels == output_slices.len();
                         nih_debug_assert_eq!(num_output_channels, output_slices.len());
@@ -1219,7 +1409,13 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 // Some hosts process data in place, in which case we don't need to do any copying
                 // ourselves. If the pointers do not alias, then we'll do the copy here and then the
                 // plugin can just do normal in place processing.
-                if !data.outputs.is_null() && !data.inputs.is_null() {
+                if has_main_input
+                    && has_main_output
+                    && data.num_outputs > 0
+                    && !data.outputs.is_null()
+                    && data.num_inputs > 0
+                    && !data.inputs.is_null()
+                {
                     let num_output_channels = (*data.outputs).num_channels as usize;
                     let num_input_channels = (*data.inputs).num_channels as usize;
                     nih_debug_assert!(
@@ -1241,6 +1437,100 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                     }
                 }
 
+                // The sidechain inputs are copied to our own storage so the plugin can freely
+                // modify them. Inputs the host didn't provide are filled with silence.
+                let mut aux_input_storage = self.inner.aux_input_storage.borrow_mut();
+                let mut aux_input_buffers = self.inner.aux_input_buffers.borrow_mut();
+                for (aux_input_idx, (storage, buffer)) in aux_input_storage
+                    .iter_mut()
+                    .zip(aux_input_buffers.iter_mut())
+                    .enumerate()
+                {
+                    let host_input_idx = aux_input_idx as i32 + aux_input_start_idx as i32;
+                    let host_input = if host_input_idx < data.num_inputs
+                        && !data.inputs.is_null()
+                        && !(*data.inputs.offset(host_input_idx as isize))
+                            .buffers
+                            .is_null()
+                    {
+                        Some(&*data.inputs.offset(host_input_idx as isize))
+                    } else {
+                        None
+                    };
+
+                    for (channel_idx, channel_storage) in storage.iter_mut().enumerate() {
+                        let channel_storage = &mut channel_storage[..block_end - block_start];
+                        match host_input {
+                            Some(host_input) if channel_idx < host_input.num_channels as usize => {
+                                let input_channel_ptr =
+                                    *(host_input.buffers as *const *const f32).add(channel_idx);
+                                channel_storage.copy_from_slice(std::slice::from_raw_parts(
+                                    input_channel_ptr.add(block_start),
+                                    block_end - block_start,
+                                ));
+                            }
+                            _ => channel_storage.fill(0.0),
+                        }
+                    }
+
+                    buffer.with_raw_vec(|channel_slices| {
+                        for (channel_slice, channel_storage) in
+                            channel_slices.iter_mut().zip(storage.iter_mut())
+                        {
+                            // SAFETY: The storage outlives the process call and it is not resized
+                            //         until the plugin gets reactivated
+                            *channel_slice = std::slice::from_raw_parts_mut(
+                                channel_storage.as_mut_ptr(),
+                                block_end - block_start,
+                            );
+                        }
+                    });
+                }
+
+                // Auxiliary outputs point directly to the host's buffers, or to our scratch
+                // storage if the host didn't provide them
+                let mut aux_output_storage = self.inner.aux_output_storage.borrow_mut();
+                let mut aux_output_buffers = self.inner.aux_output_buffers.borrow_mut();
+                for (aux_output_idx, (storage, buffer)) in aux_output_storage
+                    .iter_mut()
+                    .zip(aux_output_buffers.iter_mut())
+                    .enumerate()
+                {
+                    let host_output_idx = aux_output_idx as i32 + aux_output_start_idx as i32;
+                    let host_output = if host_output_idx < data.num_outputs
+                        && !data.outputs.is_null()
+                        && !(*data.outputs.offset(host_output_idx as isize))
+                            .buffers
+                            .is_null()
+                    {
+                        Some(&*data.outputs.offset(host_output_idx as isize))
+                    } else {
+                        None
+                    };
+
+                    buffer.with_raw_vec(|channel_slices| {
+                        for (channel_idx, (channel_slice, channel_storage)) in channel_slices
+                            .iter_mut()
+                            .zip(storage.iter_mut())
+                            .enumerate()
+                        {
+                            let channel_ptr = match host_output {
+                                Some(host_output)
+                                    if channel_idx < host_output.num_channels as usize =>
+                                {
+                                    (*(host_output.buffers as *mut *mut f32).add(channel_idx))
+                                        .add(block_start)
+                                }
+                                _ => channel_storage.as_mut_ptr(),
+                            };
+                            *channel_slice = std::slice::from_raw_parts_mut(
+                                channel_ptr,
+                                block_end - block_start,
+                            );
+                        }
+                    });
+                }
+
                 // Some of the fields are left empty because VST3 does not provide this
                 // information, but the methods on [`Transport`] can reconstruct these values
                 // from the other fields
@@ -1303,7 +1593,17 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 let result = if buffer_is_valid {
                     let mut plugin = self.inner.plugin.write();
                     let mut context = self.inner.make_process_context(transport);
-                    let result = plugin.process(&mut output_buffer, &mut context);
+                    let mut aux = AuxiliaryBuffers {
+                        // SAFETY: The buffers' lifetimes are only extended to `'static` because
+                        //         they're stored in the wrapper. The slices are only valid for
+                        //         the duration of this process call.
+                        inputs: &mut *(aux_input_buffers.as_mut_slice() as *mut [Buffer<'static>]
+                            as *mut [Buffer]),
+                        outputs: &mut *(aux_output_buffers.as_mut_slice()
+                            as *mut [Buffer<'static>]
+                            as *mut [Buffer]),
+                    };
+                    let result = plugin.process(&mut output_buffer, &mut aux, &mut context);
                     self.inner.last_process_status.store(result);
                     result
                 } else {
@@ -1313,6 +1613,7 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 // Send any events output by the plugin during the process cycle
                 if let Some(events) = data.output_events.upgrade() {
                     let mut output_events = self.inner.output_events.borrow_mut();
+                    let mut sysex_storage = self.inner.sysex_storage.borrow_mut();
                     while let Some(event) = output_events.pop_front() {
                         // We'll set the correct variant on this struct, or skip to the next
                         // loop iteration if we don't handle the event type
@@ -1324,6 +1625,7 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                         match event {
                             NoteEvent::NoteOn {
                                 timing: _,
+                                voice_id,
                                 channel,
                                 note,
                                 velocity,
@@ -1335,13 +1637,16 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                     tuning: 0.0,
                                     velocity,
                                     length: 0, // What?
-                                    // We'll use this for our note IDs, that way we don't have
+                                    // If the plugin didn't assign a voice ID then we'll derive
+                                    // one from the channel and note number, that way we don't have
                                     // to do anything complicated here
-                                    note_id: ((channel as i32) << 8) | note as i32,
+                                    note_id: voice_id
+                                        .unwrap_or(((channel as i32) << 8) | note as i32),
                                 };
                             }
                             NoteEvent::NoteOff {
                                 timing: _,
+                                voice_id,
                                 channel,
                                 note,
                                 velocity,
@@ -1351,12 +1656,14 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                     channel: channel as i16,
                                     pitch: note as i16,
                                     velocity,
-                                    note_id: ((channel as i32) << 8) | note as i32,
+                                    note_id: voice_id
+                                        .unwrap_or(((channel as i32) << 8) | note as i32),
                                     tuning: 0.0,
                                 };
                             }
                             NoteEvent::PolyPressure {
                                 timing: _,
+                                voice_id,
                                 channel,
                                 note,
                                 pressure,
@@ -1365,20 +1672,49 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                 vst3_event.event.poly_pressure = PolyPressureEvent {
                                     channel: channel as i16,
                                     pitch: note as i16,
-                                    note_id: ((channel as i32) << 8) | note as i32,
+                                    note_id: voice_id
+                                        .unwrap_or(((channel as i32) << 8) | note as i32),
                                     pressure,
                                 };
                             }
-                            event @ (NoteEvent::PolyVolume { channel, note, .. }
-                            | NoteEvent::PolyPan { channel, note, .. }
-                            | NoteEvent::PolyTuning { channel, note, .. }
-                            | NoteEvent::PolyVibrato { channel, note, .. }
-                            | NoteEvent::PolyExpression { channel, note, .. }
-                            | NoteEvent::PolyBrightness { channel, note, .. })
-                                if P::MIDI_OUTPUT >= MidiConfig::Basic =>
-                            {
+                            event @ (NoteEvent::PolyVolume {
+                                voice_id,
+                                channel,
+                                note,
+                                ..
+                            }
+                            | NoteEvent::PolyPan {
+                                voice_id,
+                                channel,
+                                note,
+                                ..
+                            }
+                            | NoteEvent::PolyTuning {
+                                voice_id,
+                                channel,
+                                note,
+                                ..
+                            }
+                            | NoteEvent::PolyVibrato {
+                                voice_id,
+                                channel,
+                                note,
+                                ..
+                            }
+                            | NoteEvent::PolyExpression {
+                                voice_id,
+                                channel,
+                                note,
+                                ..
+                            }
+                            | NoteEvent::PolyBrightness {
+                                voice_id,
+                                channel,
+                                note,
+                                ..
+                            }) if P::MIDI_OUTPUT >= MidiConfig::Basic => {
                                 match NoteExpressionController::translate_event_reverse(
-                                    ((channel as i32) << 8) | note as i32,
+                                    voice_id.unwrap_or(((channel as i32) << 8) | note as i32),
                                     &event,
                                 ) {
                                     Some(translated_event) => {
@@ -1435,6 +1771,32 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                                     value2: 0,
                                 };
                             }
+                            NoteEvent::MidiSysEx { timing: _, message }
+                                if P::MIDI_OUTPUT >= MidiConfig::MidiCCs =>
+                            {
+                                // The event only points to the message's data, so the message is
+                                // stored in the wrapper until the host has had a chance to read it
+                                let data = match sysex_storage.get_pinned(&message) {
+                                    Some(data) => data,
+                                    None => {
+                                        nih_debug_assert_failure!(
+                                            "The SysEx storage is full, dropping a {} byte message",
+                                            message.len()
+                                        );
+                                        continue;
+                                    }
+                                };
+
+                                vst3_event.type_ = EventTypes::kDataEvent as u16;
+                                vst3_event.event.data = DataEvent {
+                                    size: data.len() as u32,
+                                    type_: 0, // kMidiSysEx
+                                    bytes: data.as_ptr(),
+                                };
+                            }
+                            // VST3 does not have a way to signal voice termination, and it also
+                            // doesn't support polyphonic modulation
+                            NoteEvent::VoiceTerminated { .. } => continue,
                             _ => {
                                 nih_debug_assert_failure!(
                                     "Invalid output event for the current MIDI_OUTPUT setting"
@@ -1457,6 +1819,55 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                     _ => kResultOk,
                 };
 
+                // Parameter changes the plugin made during the process call are applied now and
+                // sent to the host
+                let output_param_changes = data.output_param_changes.upgrade();
+                let mut output_values_changed = false;
+                while let Some(change) = self.inner.output_param_changes.pop() {
+                    self.inner.set_normalized_value_by_hash(
+                        change.param_hash,
+                        change.normalized_value,
+                        Some(sample_rate),
+                    );
+                    output_values_changed = true;
+
+                    if let Some(output_param_changes) = &output_param_changes {
+                        let mut queue_idx = 0;
+                        let queue = output_param_changes
+                            .add_parameter_data(&change.param_hash, &mut queue_idx)
+                            .upgrade();
+                        match queue {
+                            Some(queue) => {
+                                let mut point_idx = 0;
+                                let result = queue.add_point(
+                                    block_start as i32,
+                                    change.normalized_value as f64,
+                                    &mut point_idx,
+                                );
+                                nih_debug_assert_eq!(result, kResultOk);
+                            }
+                            None => nih_debug_assert_failure!(
+                                "The host did not provide an output parameter queue"
+                            ),
+                        }
+                    }
+                }
+                if output_values_changed {
+                    self.inner.notify_param_values_changed();
+                }
+
+                // Parameter changes that were queued during this buffer only take effect now
+                if P::QUEUED_AUTOMATION {
+                    let sample_rate = self
+                        .inner
+                        .current_buffer_config
+                        .load()
+                        .map(|c| c.sample_rate);
+                    if self.inner.param_changes.borrow_mut().apply(sample_rate) {
+                        self.inner.notify_param_values_changed();
+                    }
+                }
+
                 // If our block ends at the end of the buffer then that means there are no more
                 // unprocessed (parameter) events. If there are more events, we'll just keep going
                 // through this process until we've processed the entire buffer.
@@ -1467,6 +1878,14 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 }
             };
 
+            // Persistent fields may have been set since the last process call. Finding out whether
+            // this instance's fields were the ones that changed requires serializing them, so that
+            // check happens on the main thread.
+            if self.inner.field_change_tracker.should_check() {
+                let task_posted = self.inner.do_maybe_async(Task::CheckFieldChanges);
+                nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+            }
+
             // After processing audio, we'll check if the editor has sent us updated plugin state.
             // We'll restore that here on the audio thread to prevent changing the values during the
             // process call and also to prevent inconsistent state when the host also wants to load
@@ -1643,13 +2062,40 @@ impl<P: Vst3Plugin> IProcessContextRequirements for Wrapper<P> {
 
 impl<P: Vst3Plugin> IUnitInfo for Wrapper<P> {
     unsafe fn get_unit_count(&self) -> i32 {
-        self.inner.param_units.len() as i32
+        // The factory presets program list is attached to the root unit, so if the plugin has
+        // factory presets we'll need to explicitly list that unit first
+        if self.inner.factory_presets.is_empty() {
+            self.inner.param_units.len() as i32
+        } else {
+            self.inner.param_units.len() as i32 + 1
+        }
     }
 
     unsafe fn get_unit_info(&self, unit_index: i32, info: *mut UnitInfo) -> tresult {
         check_null_ptr!(info);
 
-        match self.inner.param_units.info(unit_index as usize) {
+        if unit_index < 0 {
+            return kInvalidArgument;
+        }
+
+        let mut unit_index = unit_index as usize;
+        if !self.inner.factory_presets.is_empty() {
+            if unit_index == 0 {
+                *info = mem::zeroed();
+
+                let info = &mut *info;
+                info.id = kRootUnitId;
+                info.parent_unit_id = kNoParentUnitId;
+                u16strlcpy(&mut info.name, "Root");
+                info.program_list_id = VST3_FACTORY_PRESETS_PROGRAM_LIST_ID;
+
+                return kResultOk;
+            }
+
+            unit_index -= 1;
+        }
+
+        match self.inner.param_units.info(unit_index) {
             Some((unit_id, unit_info)) => {
                 *info = mem::zeroed();
 
@@ -1666,35 +2112,72 @@ impl<P: Vst3Plugin> IUnitInfo for Wrapper<P> {
     }
 
     unsafe fn get_program_list_count(&self) -> i32 {
-        // TODO: Do we want program lists? Probably not, CLAP doesn't even support them.
-        0
+        // The plugin's factory presets are exposed as a single program list
+        if self.inner.factory_presets.is_empty() {
+            0
+        } else {
+            1
+        }
     }
 
-    unsafe fn get_program_list_info(
-        &self,
-        _list_index: i32,
-        _info: *mut ProgramListInfo,
-    ) -> tresult {
-        kInvalidArgument
+    unsafe fn get_program_list_info(&self, list_index: i32, info: *mut ProgramListInfo) -> tresult {
+        check_null_ptr!(info);
+
+        if list_index != 0 || self.inner.factory_presets.is_empty() {
+            return kInvalidArgument;
+        }
+
+        *info = mem::zeroed();
+
+        let info = &mut *info;
+        info.id = VST3_FACTORY_PRESETS_PROGRAM_LIST_ID;
+        u16strlcpy(&mut info.name, "Factory Presets");
+        info.program_count = self.inner.factory_presets.len() as i32;
+
+        kResultOk
     }
 
-    unsafe fn get_program_name(
-        &self,
-        _list_id: i32,
-        _program_index: i32,
-        _name: *mut u16,
-    ) -> tresult {
-        kInvalidArgument
+    unsafe fn get_program_name(&self, list_id: i32, program_index: i32, name: *mut u16) -> tresult {
+        check_null_ptr!(name);
+
+        match self.factory_preset_by_index(list_id, program_index) {
+            Some(preset) => {
+                u16strlcpy(&mut *(name as *mut [TChar; 128]), &preset.name);
+
+                kResultOk
+            }
+            None => kInvalidArgument,
+        }
     }
 
     unsafe fn get_program_info(
         &self,
-        _list_id: i32,
-        _program_index: i32,
-        _attribute_id: *const u8,
-        _attribute_value: *mut u16,
+        list_id: i32,
+        program_index: i32,
+        attribute_id: *const u8,
+        attribute_value: *mut u16,
     ) -> tresult {
-        kInvalidArgument
+        check_null_ptr!(attribute_id, attribute_value);
+
+        let preset = match self.factory_preset_by_index(list_id, program_index) {
+            Some(preset) => preset,
+            None => return kInvalidArgument,
+        };
+
+        // These are the `PresetAttributes` keys from the VST3 SDK. The preset's category is
+        // `kInstrument`. We don't have anything to put in `kStyle` or `kCharacter`, and the state
+        // and file related keys don't apply to factory presets.
+        let attribute_id = CStr::from_ptr(attribute_id as *const c_char);
+        let value = match (attribute_id.to_bytes(), &preset.category) {
+            (b"Name", _) => preset.name.as_str(),
+            (b"PlugInName", _) => P::NAME,
+            (b"PlugInCategory", _) => P::VST3_CATEGORIES,
+            (b"MusicalInstrument", Some(category)) => category.as_str(),
+            _ => return kResultFalse,
+        };
+        u16strlcpy(&mut *(attribute_value as *mut [TChar; 128]), value);
+
+        kResultOk
     }
 
     unsafe fn has_program_pitch_names(&self, _id: i32, _index: i32) -> tresult {